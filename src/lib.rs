@@ -1,7 +1,29 @@
+extern crate base64;
 extern crate bitcoin;
+#[cfg(feature = "bitcoin-bech32")]
+extern crate bitcoin_bech32;
 extern crate bitcoin_hashes;
 extern crate byteorder;
 extern crate core;
+#[cfg(feature = "futures")]
+extern crate futures;
 extern crate hex;
+extern crate lru;
+#[cfg(feature = "miniz_oxide")]
+extern crate miniz_oxide;
+#[cfg(feature = "proptest")]
+extern crate proptest;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "reqwest")]
+extern crate reqwest;
+#[cfg(feature = "secp256k1")]
+extern crate secp256k1;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(any(feature = "serde_json", all(test, feature = "serde")))]
+extern crate serde_json;
+#[cfg(feature = "url")]
+extern crate url;
 
 pub mod openassets;
@@ -3,10 +3,33 @@ use bitcoin::consensus::encode::Error::ParseFailed;
 use bitcoin::network::constants::Network;
 use bitcoin::util::address::Payload;
 use bitcoin::util::base58;
+#[cfg(feature = "bip32")]
+use bitcoin::util::bip32::{self, ChildNumber, DerivationPath, ExtendedPubKey};
+use bitcoin::util::key::PublicKey;
+use bitcoin::Script;
+#[cfg(feature = "segwit")]
+use bitcoin_bech32::{u5, WitnessProgram};
+use bitcoin_hashes::{hash160, Hash};
+#[cfg(feature = "bip32")]
+use secp256k1::Secp256k1;
+#[cfg(feature = "serde")]
+use serde::de::{self, Visitor};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::convert::TryFrom;
+use std::error;
 use std::fmt::{self, Display, Formatter};
+#[cfg(feature = "bip32")]
+use std::ops::Range;
+use std::str::FromStr;
 
 /// A Open Assets Address
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+///
+/// P2PKH and P2SH payloads are encoded in base58check. With the `segwit` feature, witness
+/// programs are encoded in bech32 with the human-readable part `oa` on the main network, `toa`
+/// on testnet and `oart` on regtest, followed by the witness version and program like segwit
+/// addresses.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 pub struct Address {
     pub network: Network,
     pub payload: Payload,
@@ -14,55 +37,650 @@ pub struct Address {
 
 const NAMESPACE: u8 = 0x13;
 
+/// The human-readable part of bech32 encoded addresses of witness programs on each network.
+#[cfg(feature = "segwit")]
+const BECH32_HRPS: [(&str, Network); 3] = [
+    ("oa", Network::Bitcoin),
+    ("toa", Network::Testnet),
+    ("oart", Network::Regtest),
+];
+
+/// The characters encoding 5-bit values in bech32, by value.
+#[cfg(feature = "segwit")]
+const BECH32_CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+const PUBKEY_ADDRESS_PREFIX_MAIN: u8 = 0;
+const SCRIPT_ADDRESS_PREFIX_MAIN: u8 = 5;
+const PUBKEY_ADDRESS_PREFIX_TEST: u8 = 111;
+const SCRIPT_ADDRESS_PREFIX_TEST: u8 = 196;
+
+/// The bytes prefixing the hash in the base58check encoding of the addresses of a chain, letting
+/// regtest and chains other than Bitcoin be told apart.
+pub trait AddressParams {
+    /// Returns the network of the addresses of the chain.
+    fn network(&self) -> Network;
+
+    /// Returns the byte identifying Open Assets addresses.
+    fn namespace(&self) -> u8 {
+        NAMESPACE
+    }
+
+    /// Returns the version byte of P2PKH addresses.
+    fn pubkey_version(&self) -> u8;
+
+    /// Returns the version byte of P2SH addresses.
+    fn script_version(&self) -> u8;
+}
+
+/// Testnet and regtest addresses share the same version bytes.
+impl AddressParams for Network {
+    fn network(&self) -> Network {
+        *self
+    }
+
+    fn pubkey_version(&self) -> u8 {
+        match *self {
+            Network::Bitcoin => PUBKEY_ADDRESS_PREFIX_MAIN,
+            Network::Testnet | Network::Regtest => PUBKEY_ADDRESS_PREFIX_TEST,
+        }
+    }
+
+    fn script_version(&self) -> u8 {
+        match *self {
+            Network::Bitcoin => SCRIPT_ADDRESS_PREFIX_MAIN,
+            Network::Testnet | Network::Regtest => SCRIPT_ADDRESS_PREFIX_TEST,
+        }
+    }
+}
+
+/// Address parameters of a chain with bytes of its own, e.g. a Tapyrus-based chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CustomAddressParams {
+    pub network: Network,
+    pub namespace: u8,
+    pub pubkey_version: u8,
+    pub script_version: u8,
+}
+
+impl AddressParams for CustomAddressParams {
+    fn network(&self) -> Network {
+        self.network
+    }
+
+    fn namespace(&self) -> u8 {
+        self.namespace
+    }
+
+    fn pubkey_version(&self) -> u8 {
+        self.pubkey_version
+    }
+
+    fn script_version(&self) -> u8 {
+        self.script_version
+    }
+}
+
 impl Address {
+    /// Returns the address of `payload` on `network`.
+    ///
+    /// Witness program payloads require the `segwit` feature.
     pub fn new(
         payload: Payload,
         network: bitcoin::network::constants::Network,
     ) -> Result<Self, encode::Error> {
         match payload {
             Payload::PubkeyHash(_) | Payload::ScriptHash(_) => {}
-            _ => {
+            #[cfg(feature = "segwit")]
+            Payload::WitnessProgram(_) => {}
+            #[cfg(not(feature = "segwit"))]
+            Payload::WitnessProgram(_) => {
                 return Err(ParseFailed(
-                    "The Open Assets Address of the witness program does not defined.",
+                    "Open Assets Addresses of witness programs require the segwit feature.",
                 ));
             }
         }
         Ok(Address { payload, network })
     }
 
+    /// Returns the address of the outputs locked by `script`, which must be a P2PKH or P2SH
+    /// script, or a witness program with the `segwit` feature.
+    pub fn from_script(script: &Script, network: Network) -> Result<Self, encode::Error> {
+        let bytes = script.as_bytes();
+        let payload = if script.is_p2pkh() {
+            Payload::PubkeyHash(hash160::Hash::from_slice(&bytes[3..23]).expect("20 bytes long"))
+        } else if script.is_p2sh() {
+            Payload::ScriptHash(hash160::Hash::from_slice(&bytes[2..22]).expect("20 bytes long"))
+        } else {
+            #[cfg(feature = "segwit")]
+            {
+                match WitnessProgram::from_scriptpubkey(bytes, bech_network(network)) {
+                    Ok(program) => Payload::WitnessProgram(program),
+                    Err(_) => return Err(ParseFailed("The script has no Open Assets Address.")),
+                }
+            }
+            #[cfg(not(feature = "segwit"))]
+            return Err(ParseFailed("The script has no Open Assets Address."));
+        };
+        Ok(Address { network, payload })
+    }
+
     pub fn to_btc_addr(&self) -> Result<bitcoin::Address, encode::Error> {
         Ok(bitcoin::Address {
             network: self.network,
             payload: self.payload.clone(),
         })
     }
+
+    /// Returns the script locking outputs sent to this address.
+    pub fn script_pubkey(&self) -> Script {
+        bitcoin::Address {
+            network: self.network,
+            payload: self.payload.clone(),
+        }
+        .script_pubkey()
+    }
 }
 
-impl Display for Address {
-    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
-        let mut prefixed = [0; 22];
-        prefixed[0] = NAMESPACE;
-        prefixed[1] = match self.network {
-            bitcoin::network::constants::Network::Bitcoin => 0,
-            bitcoin::network::constants::Network::Testnet
-            | bitcoin::network::constants::Network::Regtest => 111,
-        };
+impl Address {
+    /// Parses an address of the chain described by `params`, which gives its network to the
+    /// address, e.g. to parse regtest addresses as such.
+    ///
+    /// Witness program addresses must be of the network of `params`.
+    pub fn parse_with_params<P: AddressParams>(
+        s: &str,
+        params: &P,
+    ) -> Result<Address, AddressError> {
+        #[cfg(feature = "segwit")]
+        {
+            if let Some(address) = parse_witness_program(s)? {
+                return address.expect_network(params.network());
+            }
+        }
+        decode_base58(s, &[params])
+    }
+
+    /// Returns the encoding of the address with the bytes, or the human-readable part of
+    /// witness program addresses, of the chain described by `params`.
+    ///
+    /// Panics like `to_string` for witness program addresses without the `segwit` feature,
+    /// see `try_to_string_with_params`.
+    pub fn to_string_with_params<P: AddressParams>(&self, params: &P) -> String {
+        self.try_to_string_with_params(params)
+            .expect("witness program addresses require the segwit feature")
+    }
+
+    /// Returns the encoding of the address, failing with
+    /// `AddressError::WitnessProgramUnsupported` for witness program addresses without the
+    /// `segwit` feature, which `Display` fails to format.
+    pub fn try_to_string(&self) -> Result<String, AddressError> {
+        self.try_to_string_with_params(&self.network)
+    }
+
+    /// Same as `to_string_with_params`, failing like `try_to_string`.
+    pub fn try_to_string_with_params<P: AddressParams>(
+        &self,
+        params: &P,
+    ) -> Result<String, AddressError> {
         match self.payload {
+            Payload::WitnessProgram(ref program) => {
+                encode_witness_program(program, params.network())
+            }
+            _ => {
+                let data = self.base58_data(params).expect("base58 payload");
+                Ok(base58::check_encode_slice(&data[..]))
+            }
+        }
+    }
+
+    fn base58_data<P: AddressParams>(&self, params: &P) -> Option<[u8; 22]> {
+        let mut prefixed = [0; 22];
+        prefixed[0] = params.namespace();
+        let hash = match self.payload {
             Payload::PubkeyHash(ref hash) => {
-                prefixed[2..].copy_from_slice(&hash[..]);
-                base58::check_encode_slice_to_fmt(fmt, &prefixed[..])
+                prefixed[1] = params.pubkey_version();
+                hash
             }
             Payload::ScriptHash(ref hash) => {
-                prefixed[2..].copy_from_slice(&hash[..]);
-                base58::check_encode_slice_to_fmt(fmt, &prefixed[..])
+                prefixed[1] = params.script_version();
+                hash
             }
-            Payload::WitnessProgram(_) => {
-                fmt.write_str("The Open Assets Address of the witness program does not defined.")
+            Payload::WitnessProgram(_) => return None,
+        };
+        prefixed[2..].copy_from_slice(&hash[..]);
+        Some(prefixed)
+    }
+}
+
+/// Fails for witness program addresses without the `segwit` feature, so that `to_string`
+/// panics on them: use `Address::try_to_string` for addresses which may not be encodable.
+impl Display for Address {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match self.payload {
+            Payload::WitnessProgram(ref program) => {
+                let encoded =
+                    encode_witness_program(program, self.network).map_err(|_| fmt::Error)?;
+                fmt.write_str(&encoded)
+            }
+            _ => {
+                let data = self.base58_data(&self.network).expect("base58 payload");
+                base58::check_encode_slice_to_fmt(fmt, &data[..])
             }
         }
     }
 }
 
+/// Parses the base58check encoding of an address, i.e. the namespace byte 0x13 followed by the
+/// version byte and the hash of a P2PKH or P2SH address. Regtest addresses are parsed as
+/// testnet ones.
+impl FromStr for Address {
+    type Err = encode::Error;
+
+    fn from_str(s: &str) -> Result<Address, encode::Error> {
+        Ok(decode(s)?)
+    }
+}
+
+/// The kind of payload of an address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PayloadType {
+    PubkeyHash,
+    ScriptHash,
+    WitnessProgram,
+}
+
+/// What is known of a valid address string without building its `Address`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ValidatedInfo {
+    pub network: Network,
+    pub payload_type: PayloadType,
+}
+
+/// Reasons an address string is invalid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressError {
+    /// The string is not valid base58check, e.g. because of a bad character or checksum.
+    Base58(base58::Error),
+    /// The decoded payload is not 22 bytes long.
+    InvalidLength(usize),
+    /// The first byte is not the Open Assets namespace byte 0x13.
+    InvalidNamespace(u8),
+    /// The second byte is not the version byte of a P2PKH or P2SH address.
+    InvalidVersion(u8),
+    /// The string has the human-readable part of a bech32 address but is not valid bech32.
+    /// Only raised with the `segwit` feature.
+    InvalidBech32,
+    /// The bech32 data is not a valid witness program. Only raised with the `segwit` feature.
+    InvalidWitnessProgram,
+    /// Witness program addresses can not be encoded without the `segwit` feature.
+    WitnessProgramUnsupported,
+    /// The address belongs to another network than the expected one.
+    NetworkMismatch { expected: Network, actual: Network },
+}
+
+impl Display for AddressError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            AddressError::Base58(ref e) => write!(f, "invalid base58: {:?}", e),
+            AddressError::InvalidLength(len) => {
+                write!(f, "invalid address length: {} bytes, expected 22", len)
+            }
+            AddressError::InvalidNamespace(b) => write!(f, "invalid namespace byte: {}", b),
+            AddressError::InvalidVersion(b) => write!(f, "invalid version byte: {}", b),
+            AddressError::InvalidBech32 => write!(f, "invalid bech32 address"),
+            AddressError::InvalidWitnessProgram => write!(f, "invalid witness program"),
+            AddressError::WitnessProgramUnsupported => write!(
+                f,
+                "Open Assets Addresses of witness programs require the segwit feature"
+            ),
+            AddressError::NetworkMismatch { expected, actual } => write!(
+                f,
+                "network mismatch: expected {}, actual {}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl error::Error for AddressError {}
+
+impl From<AddressError> for encode::Error {
+    fn from(e: AddressError) -> encode::Error {
+        match e {
+            AddressError::Base58(e) => encode::Error::Base58(e),
+            AddressError::InvalidLength(len) => {
+                encode::Error::Base58(base58::Error::InvalidLength(len))
+            }
+            AddressError::InvalidNamespace(b) | AddressError::InvalidVersion(b) => {
+                encode::Error::Base58(base58::Error::InvalidVersion(vec![b]))
+            }
+            AddressError::InvalidBech32 => ParseFailed("Invalid bech32 Open Assets Address."),
+            AddressError::InvalidWitnessProgram => ParseFailed("Invalid witness program."),
+            AddressError::WitnessProgramUnsupported => {
+                ParseFailed("Open Assets Addresses of witness programs require the segwit feature.")
+            }
+            AddressError::NetworkMismatch { .. } => {
+                ParseFailed("Open Assets Address of another network.")
+            }
+        }
+    }
+}
+
+impl Address {
+    /// Returns whether `s` is a valid address.
+    pub fn is_valid(s: &str) -> bool {
+        decode(s).is_ok()
+    }
+
+    /// Returns the address if it belongs to `network`, e.g. to reject testnet addresses pasted
+    /// into a mainnet service.
+    ///
+    /// Testnet and regtest share the base58 version bytes, so base58 addresses parsed as
+    /// testnet ones are accepted for regtest and returned as regtest addresses.
+    pub fn expect_network(self, network: Network) -> Result<Address, AddressError> {
+        let test_networks = |n| n == Network::Testnet || n == Network::Regtest;
+        match self.payload {
+            Payload::PubkeyHash(_) | Payload::ScriptHash(_)
+                if test_networks(self.network) && test_networks(network) =>
+            {
+                Ok(Address { network, ..self })
+            }
+            _ if self.network == network => Ok(self),
+            _ => Err(AddressError::NetworkMismatch {
+                expected: network,
+                actual: self.network,
+            }),
+        }
+    }
+
+    /// Returns the network and payload type of the address `s`, or why it is invalid.
+    pub fn validate(s: &str) -> Result<ValidatedInfo, AddressError> {
+        let address = decode(s)?;
+        let payload_type = match address.payload {
+            Payload::PubkeyHash(_) => PayloadType::PubkeyHash,
+            Payload::ScriptHash(_) => PayloadType::ScriptHash,
+            Payload::WitnessProgram(_) => PayloadType::WitnessProgram,
+        };
+        Ok(ValidatedInfo {
+            network: address.network,
+            payload_type,
+        })
+    }
+}
+
+pub(crate) fn decode(s: &str) -> Result<Address, AddressError> {
+    #[cfg(feature = "segwit")]
+    {
+        if let Some(address) = parse_witness_program(s)? {
+            return Ok(address);
+        }
+    }
+    decode_base58(s, &[&Network::Bitcoin, &Network::Testnet])
+}
+
+/// Decodes the base58check encoding of an address of the first chain of `candidates` whose
+/// bytes match.
+fn decode_base58<P: AddressParams>(s: &str, candidates: &[&P]) -> Result<Address, AddressError> {
+    let data = base58::from_check(s).map_err(AddressError::Base58)?;
+    if data.len() != 22 {
+        return Err(AddressError::InvalidLength(data.len()));
+    }
+    let hash = hash160::Hash::from_slice(&data[2..]).expect("20 bytes long");
+    let mut known_namespace = false;
+    for params in candidates {
+        if data[0] != params.namespace() {
+            continue;
+        }
+        known_namespace = true;
+        let payload = if data[1] == params.pubkey_version() {
+            Payload::PubkeyHash(hash)
+        } else if data[1] == params.script_version() {
+            Payload::ScriptHash(hash)
+        } else {
+            continue;
+        };
+        return Ok(Address {
+            network: params.network(),
+            payload,
+        });
+    }
+    Err(if known_namespace {
+        AddressError::InvalidVersion(data[1])
+    } else {
+        AddressError::InvalidNamespace(data[0])
+    })
+}
+
+/// Serialized as its string encoding, which is parsed and validated on deserialization.
+#[cfg(feature = "serde")]
+impl Serialize for Address {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct AddressVisitor;
+
+        impl<'de> Visitor<'de> for AddressVisitor {
+            type Value = Address;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an Open Assets address")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Address, E> {
+                Address::from_str(v).map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(AddressVisitor)
+    }
+}
+
+/// Returns the bech32 encoding of a witness program address.
+#[cfg(feature = "segwit")]
+fn encode_witness_program(
+    program: &WitnessProgram,
+    network: Network,
+) -> Result<String, AddressError> {
+    let hrp = BECH32_HRPS
+        .iter()
+        .find(|&&(_, n)| n == network)
+        .map(|&(hrp, _)| hrp)
+        .expect("every network has a human-readable part");
+    let mut data = vec![program.version().to_u8()];
+    data.extend(convert_bits(program.program(), 8, 5, true).expect("padded"));
+    Ok(bech32_encode(hrp, &data))
+}
+
+/// Witness program addresses cannot be encoded without the `segwit` feature, and can only be
+/// built by setting the fields of `Address` directly.
+#[cfg(not(feature = "segwit"))]
+fn encode_witness_program<T>(_: &T, _: Network) -> Result<String, AddressError> {
+    Err(AddressError::WitnessProgramUnsupported)
+}
+
+/// Parses the bech32 encoding of a witness program address, returning `None` if `s` does not
+/// start with the human-readable part of an Open Assets address.
+#[cfg(feature = "segwit")]
+fn parse_witness_program(s: &str) -> Result<Option<Address>, AddressError> {
+    let separator = match s.rfind('1') {
+        Some(i) => i,
+        None => return Ok(None),
+    };
+    let network = match BECH32_HRPS
+        .iter()
+        .find(|&&(hrp, _)| s[..separator].eq_ignore_ascii_case(hrp))
+    {
+        Some(&(_, network)) => network,
+        None => return Ok(None),
+    };
+    let data = bech32_decode(s).ok_or(AddressError::InvalidBech32)?;
+    if data.is_empty() {
+        return Err(AddressError::InvalidWitnessProgram);
+    }
+    let version = u5::try_from_u8(data[0]).map_err(|_| AddressError::InvalidWitnessProgram)?;
+    let program =
+        convert_bits(&data[1..], 5, 8, false).ok_or(AddressError::InvalidWitnessProgram)?;
+    let program = WitnessProgram::new(version, program, bech_network(network))
+        .map_err(|_| AddressError::InvalidWitnessProgram)?;
+    Ok(Some(Address {
+        network,
+        payload: Payload::WitnessProgram(program),
+    }))
+}
+
+#[cfg(feature = "segwit")]
+fn bech_network(network: Network) -> bitcoin_bech32::constants::Network {
+    match network {
+        Network::Bitcoin => bitcoin_bech32::constants::Network::Bitcoin,
+        Network::Testnet => bitcoin_bech32::constants::Network::Testnet,
+        Network::Regtest => bitcoin_bech32::constants::Network::Regtest,
+    }
+}
+
+/// Computes the BIP173 checksum of the values of 5 bits.
+#[cfg(feature = "segwit")]
+fn bech32_polymod<I: IntoIterator<Item = u8>>(values: I) -> u32 {
+    const GENERATOR: [u32; 5] = [
+        0x3b6a_57b2,
+        0x2650_8e6d,
+        0x1ea1_19fa,
+        0x3d42_33dd,
+        0x2a14_62b3,
+    ];
+    values.into_iter().fold(1, |chk, value| {
+        let top = chk >> 25;
+        let chk = (chk & 0x1ff_ffff) << 5 ^ u32::from(value);
+        (0..5)
+            .filter(|i| (top >> i) & 1 == 1)
+            .fold(chk, |chk, i| chk ^ GENERATOR[i])
+    })
+}
+
+/// Returns the human-readable part as checksummed, i.e. the high bits of its characters,
+/// a zero and their low bits.
+#[cfg(feature = "segwit")]
+fn bech32_hrp_expand(hrp: &str) -> Vec<u8> {
+    let mut expanded: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+    expanded.push(0);
+    expanded.extend(hrp.bytes().map(|b| b & 31));
+    expanded
+}
+
+/// Returns the bech32 encoding of the values of 5 bits `data` with the lowercase
+/// human-readable part `hrp`.
+#[cfg(feature = "segwit")]
+fn bech32_encode(hrp: &str, data: &[u8]) -> String {
+    let mut values = bech32_hrp_expand(hrp);
+    values.extend_from_slice(data);
+    values.extend_from_slice(&[0; 6]);
+    let checksum = bech32_polymod(values) ^ 1;
+    let mut encoded = format!("{}1", hrp);
+    encoded.extend(
+        data.iter()
+            .cloned()
+            .chain((0..6).map(|i| (checksum >> (5 * (5 - i))) as u8 & 31))
+            .map(|value| BECH32_CHARSET[value as usize] as char),
+    );
+    encoded
+}
+
+/// Returns the values of 5 bits of the bech32 string `s`, without its checksum, or `None` if
+/// `s` is not valid bech32.
+#[cfg(feature = "segwit")]
+fn bech32_decode(s: &str) -> Option<Vec<u8>> {
+    let has_lower = s.bytes().any(|b| b.is_ascii_lowercase());
+    let has_upper = s.bytes().any(|b| b.is_ascii_uppercase());
+    if s.len() > 90 || (has_lower && has_upper) || s.bytes().any(|b| !(33..=126).contains(&b)) {
+        return None;
+    }
+    let s = s.to_ascii_lowercase();
+    let separator = s.rfind('1')?;
+    if separator == 0 || s.len() - separator - 1 < 6 {
+        return None;
+    }
+    let (hrp, rest) = (&s[..separator], &s[separator + 1..]);
+    let data = rest
+        .bytes()
+        .map(|b| BECH32_CHARSET.iter().position(|&c| c == b).map(|v| v as u8))
+        .collect::<Option<Vec<u8>>>()?;
+    let mut values = bech32_hrp_expand(hrp);
+    values.extend_from_slice(&data);
+    if bech32_polymod(values) != 1 {
+        return None;
+    }
+    Some(data[..data.len() - 6].to_vec())
+}
+
+/// Regroups `data` from groups of `from` bits to groups of `to` bits, padding the last group
+/// with zeros if `pad`, else failing unless the leftover bits are zero padding.
+#[cfg(feature = "segwit")]
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Option<Vec<u8>> {
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+    let max = (1 << to) - 1;
+    let mut converted = vec![];
+    for &value in data {
+        if u32::from(value) >> from != 0 {
+            return None;
+        }
+        acc = acc << from | u32::from(value);
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            converted.push((acc >> bits & max) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            converted.push((acc << (to - bits) & max) as u8);
+        }
+    } else if bits >= from || acc << (to - bits) & max != 0 {
+        return None;
+    }
+    Some(converted)
+}
+
+impl<'a> TryFrom<&'a str> for Address {
+    type Error = encode::Error;
+
+    fn try_from(s: &'a str) -> Result<Address, encode::Error> {
+        Address::from_str(s)
+    }
+}
+
+/// Destinations outputs can be sent to, i.e. scripts and the addresses encoding them.
+pub trait ToScriptPubkey {
+    fn to_script_pubkey(&self) -> Script;
+}
+
+impl ToScriptPubkey for Script {
+    fn to_script_pubkey(&self) -> Script {
+        self.clone()
+    }
+}
+
+impl ToScriptPubkey for Address {
+    fn to_script_pubkey(&self) -> Script {
+        self.script_pubkey()
+    }
+}
+
+impl ToScriptPubkey for bitcoin::Address {
+    fn to_script_pubkey(&self) -> Script {
+        self.script_pubkey()
+    }
+}
+
+impl<T: ToScriptPubkey> ToScriptPubkey for &T {
+    fn to_script_pubkey(&self) -> Script {
+        (*self).to_script_pubkey()
+    }
+}
+
 pub trait OAAddressConverter {
     fn to_oa_address(&self) -> Result<Address, encode::Error>;
 }
@@ -73,9 +691,122 @@ impl OAAddressConverter for bitcoin::Address {
     }
 }
 
+/// The failure to convert the address at `index` of a list.
+#[derive(Debug)]
+pub struct ConversionError {
+    pub index: usize,
+    pub cause: encode::Error,
+}
+
+impl Display for ConversionError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "address #{}: {}", self.index, self.cause)
+    }
+}
+
+impl error::Error for ConversionError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.cause)
+    }
+}
+
+/// Converts each of `addrs` to an Open Assets address, failing with the error of every address
+/// which cannot be converted.
+pub fn convert_all(addrs: &[bitcoin::Address]) -> Result<Vec<Address>, Vec<ConversionError>> {
+    collect_conversions(addrs.iter().map(|a| a.to_oa_address()))
+}
+
+/// Converts each of `addrs` to a bitcoin address, failing with the error of every address which
+/// cannot be converted.
+pub fn convert_all_to_btc(
+    addrs: &[Address],
+) -> Result<Vec<bitcoin::Address>, Vec<ConversionError>> {
+    collect_conversions(addrs.iter().map(|a| a.to_btc_addr()))
+}
+
+fn collect_conversions<T, I>(results: I) -> Result<Vec<T>, Vec<ConversionError>>
+where
+    I: Iterator<Item = Result<T, encode::Error>>,
+{
+    let mut converted = Vec::new();
+    let mut errors = Vec::new();
+    for (index, result) in results.enumerate() {
+        match result {
+            Ok(addr) => converted.push(addr),
+            Err(cause) => errors.push(ConversionError { index, cause }),
+        }
+    }
+    if errors.is_empty() {
+        Ok(converted)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Returns the P2PKH addresses of the keys derived from `xpub` at `path` followed by each index
+/// of `indexes`, e.g. the receive addresses of a watch-only wallet with `path` `m/0`.
+///
+/// Fails if `path` or `indexes` contain hardened derivations, which require the private key.
+#[cfg(feature = "bip32")]
+pub fn derive_addresses(
+    xpub: &ExtendedPubKey,
+    path: &DerivationPath,
+    indexes: Range<u32>,
+) -> Result<Vec<Address>, bip32::Error> {
+    let secp = Secp256k1::verification_only();
+    let parent = xpub.derive_pub(&secp, path)?;
+    indexes
+        .map(|i| {
+            let child = parent.ckd_pub(&secp, ChildNumber::from_normal_idx(i)?)?;
+            Ok(Address {
+                network: xpub.network,
+                payload: Payload::PubkeyHash(hash160::Hash::hash(&child.public_key.to_bytes())),
+            })
+        })
+        .collect()
+}
+
+/// Conversion of values not tied to a network, i.e. keys and scripts, to addresses.
+pub trait OAAddressNetworkConverter {
+    fn to_oa_address(&self, network: Network) -> Result<Address, encode::Error>;
+}
+
+/// Converts to the P2PKH address of the key.
+impl OAAddressNetworkConverter for PublicKey {
+    fn to_oa_address(&self, network: Network) -> Result<Address, encode::Error> {
+        bitcoin::Address::p2pkh(self, network).to_oa_address()
+    }
+}
+
+/// Converts the script_pubkey to the address it pays to, see `Address::from_script`.
+impl OAAddressNetworkConverter for Script {
+    fn to_oa_address(&self, network: Network) -> Result<Address, encode::Error> {
+        Address::from_script(self, network)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use openassets::address::OAAddressConverter;
+    use bitcoin::blockdata::opcodes;
+    use bitcoin::blockdata::script::Builder;
+    use bitcoin::consensus::encode;
+    use bitcoin::network::constants::Network;
+    use bitcoin::util::base58;
+    #[cfg(feature = "bip32")]
+    use bitcoin::util::bip32::{ChildNumber, DerivationPath, ExtendedPubKey};
+    use bitcoin::util::key::PublicKey;
+    use bitcoin::Script;
+    #[cfg(feature = "bip32")]
+    use openassets::address::derive_addresses;
+    use openassets::address::{
+        convert_all, convert_all_to_btc, Address, AddressError, CustomAddressParams,
+        OAAddressConverter, OAAddressNetworkConverter, PayloadType, ToScriptPubkey, ValidatedInfo,
+    };
+    #[cfg(feature = "bip32")]
+    use secp256k1::Secp256k1;
+    #[cfg(feature = "serde")]
+    use std::collections::HashMap;
+    use std::convert::TryFrom;
     use std::str::FromStr;
     use std::string::ToString;
 
@@ -99,8 +830,477 @@ mod tests {
             testnet_addr.to_oa_address().unwrap().to_btc_addr().unwrap()
         );
 
+        let oa_addr = addr.to_oa_address().unwrap();
+        assert_eq!(addr.script_pubkey(), oa_addr.script_pubkey());
+        assert_eq!(addr.script_pubkey(), oa_addr.to_script_pubkey());
+
+        #[cfg(not(feature = "segwit"))]
+        {
+            let segwit_addr =
+                bitcoin::Address::from_str("bc1qvzvkjn4q3nszqxrv3nraga2r822xjty3ykvkuw").unwrap();
+            assert!(segwit_addr.to_oa_address().is_err());
+        }
+    }
+
+    #[cfg(not(feature = "segwit"))]
+    #[test]
+    fn test_witness_program_without_segwit() {
+        use std::fmt::Write;
+
+        // only built by setting the fields directly
         let segwit_addr =
             bitcoin::Address::from_str("bc1qvzvkjn4q3nszqxrv3nraga2r822xjty3ykvkuw").unwrap();
-        assert!(segwit_addr.to_oa_address().is_err());
+        let address = Address {
+            network: segwit_addr.network,
+            payload: segwit_addr.payload,
+        };
+        assert_eq!(
+            Err(AddressError::WitnessProgramUnsupported),
+            address.try_to_string()
+        );
+        assert_eq!(
+            Err(AddressError::WitnessProgramUnsupported),
+            address.try_to_string_with_params(&Network::Testnet)
+        );
+        // never formatted as anything but an address
+        let mut formatted = String::new();
+        assert!(write!(formatted, "{}", address).is_err());
+        assert!(formatted.is_empty());
+
+        let p2pkh = bitcoin::Address::from_str("1F2AQr6oqNtcJQ6p9SiCLQTrHuM9en44H8")
+            .unwrap()
+            .to_oa_address()
+            .unwrap();
+        assert_eq!(Ok(p2pkh.to_string()), p2pkh.try_to_string());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let address = Address::from_str("akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U6E").unwrap();
+        let json = serde_json::to_string(&address).unwrap();
+        assert_eq!(r#""akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U6E""#, json);
+        assert_eq!(address, serde_json::from_str(&json).unwrap());
+
+        // as a map key
+        let mut balances = HashMap::new();
+        balances.insert(address.clone(), 100u64);
+        let json = serde_json::to_string(&balances).unwrap();
+        assert_eq!(r#"{"akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U6E":100}"#, json);
+        assert_eq!(
+            balances,
+            serde_json::from_str::<HashMap<Address, u64>>(&json).unwrap()
+        );
+
+        assert!(
+            serde_json::from_str::<Address>(r#""akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U6F""#).is_err()
+        );
+        assert!(
+            serde_json::from_str::<Address>(r#""1F2AQr6oqNtcJQ6p9SiCLQTrHuM9en44H8""#).is_err()
+        );
+        assert!(serde_json::from_str::<Address>("100").is_err());
+    }
+
+    #[test]
+    fn test_convert_all() {
+        let btc_addrs: Vec<bitcoin::Address> = [
+            "1F2AQr6oqNtcJQ6p9SiCLQTrHuM9en44H8",
+            "3EktnHQD7RiAE6uzMj2ZifT9YgRrkSgzQX",
+            "mkgW6hNYBctmqDtTTsTJrsf2Gh2NPtoCU4",
+        ]
+        .iter()
+        .map(|a| bitcoin::Address::from_str(a).unwrap())
+        .collect();
+        let oa_addrs = convert_all(&btc_addrs).unwrap();
+        assert_eq!(
+            btc_addrs
+                .iter()
+                .map(|a| a.to_oa_address().unwrap())
+                .collect::<Vec<Address>>(),
+            oa_addrs
+        );
+        assert_eq!(btc_addrs, convert_all_to_btc(&oa_addrs).unwrap());
+        assert!(convert_all(&[]).unwrap().is_empty());
+
+        let mut btc_addrs = btc_addrs;
+        let segwit_addr =
+            bitcoin::Address::from_str("bc1qvzvkjn4q3nszqxrv3nraga2r822xjty3ykvkuw").unwrap();
+        btc_addrs.insert(1, segwit_addr.clone());
+        btc_addrs.push(segwit_addr);
+        let result = convert_all(&btc_addrs);
+        #[cfg(feature = "segwit")]
+        assert_eq!(5, result.unwrap().len());
+        #[cfg(not(feature = "segwit"))]
+        assert_eq!(
+            vec![1, 4],
+            result
+                .unwrap_err()
+                .iter()
+                .map(|e| e.index)
+                .collect::<Vec<usize>>()
+        );
+    }
+
+    #[cfg(feature = "bip32")]
+    #[test]
+    fn test_derive_addresses() {
+        let xpub = ExtendedPubKey::from_str("xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8").unwrap();
+        let path = DerivationPath::from_str("m/0").unwrap();
+        let addresses = derive_addresses(&xpub, &path, 0..5).unwrap();
+        assert_eq!(5, addresses.len());
+        let secp = Secp256k1::verification_only();
+        for (i, address) in addresses.iter().enumerate() {
+            let child = xpub
+                .derive_pub(
+                    &secp,
+                    &path.child(ChildNumber::from_normal_idx(i as u32).unwrap()),
+                )
+                .unwrap();
+            assert_eq!(
+                &bitcoin::Address::p2pkh(&child.public_key, Network::Bitcoin)
+                    .to_oa_address()
+                    .unwrap(),
+                address
+            );
+        }
+        assert_eq!(
+            addresses[2..],
+            derive_addresses(&xpub, &path, 2..5).unwrap()[..]
+        );
+        assert!(derive_addresses(&xpub, &path, 0..0).unwrap().is_empty());
+
+        // hardened derivations require the private key
+        let hardened = DerivationPath::from_str("m/0'").unwrap();
+        assert!(derive_addresses(&xpub, &hardened, 0..5).is_err());
+        assert!(derive_addresses(&xpub, &path, (1 << 31)..(1 << 31) + 1).is_err());
+    }
+
+    #[test]
+    fn test_network_converter() {
+        let pubkey = PublicKey::from_str(
+            "0250863ad64a87ae8a2fe83c1af1a8403cb53f53e486d8511dad8a04887e5b2352",
+        )
+        .unwrap();
+        let btc_addr = bitcoin::Address::p2pkh(&pubkey, Network::Bitcoin);
+        assert_eq!(
+            btc_addr.to_oa_address().unwrap(),
+            pubkey.to_oa_address(Network::Bitcoin).unwrap()
+        );
+        assert_eq!(
+            Network::Testnet,
+            pubkey.to_oa_address(Network::Testnet).unwrap().network
+        );
+        assert_eq!(
+            btc_addr.to_oa_address().unwrap(),
+            btc_addr
+                .script_pubkey()
+                .to_oa_address(Network::Bitcoin)
+                .unwrap()
+        );
+        assert!(Script::new().to_oa_address(Network::Bitcoin).is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(
+            Ok(ValidatedInfo {
+                network: Network::Bitcoin,
+                payload_type: PayloadType::PubkeyHash,
+            }),
+            Address::validate("akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U6E")
+        );
+        assert_eq!(
+            Ok(ValidatedInfo {
+                network: Network::Bitcoin,
+                payload_type: PayloadType::ScriptHash,
+            }),
+            Address::validate("anQin2TDYaubr6M5MQM8kNXMitHc2hsmfGc")
+        );
+        assert_eq!(
+            Ok(ValidatedInfo {
+                network: Network::Testnet,
+                payload_type: PayloadType::PubkeyHash,
+            }),
+            Address::validate("bWvePLsBsf6nThU3pWVZVWjZbcJCYQxHCpE")
+        );
+        assert!(Address::is_valid("akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U6E"));
+
+        assert_eq!(
+            Err(AddressError::InvalidLength(21)),
+            Address::validate("1F2AQr6oqNtcJQ6p9SiCLQTrHuM9en44H8")
+        );
+        match Address::validate("akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U6F") {
+            Err(AddressError::Base58(base58::Error::BadChecksum(_, _))) => {}
+            r => panic!("expected BadChecksum, got {:?}", r),
+        }
+        match Address::validate("akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U60") {
+            Err(AddressError::Base58(base58::Error::BadByte(_))) => {}
+            r => panic!("expected BadByte, got {:?}", r),
+        }
+        let mut data = vec![0x14, 0];
+        data.extend_from_slice(&[0; 20]);
+        assert_eq!(
+            Err(AddressError::InvalidNamespace(0x14)),
+            Address::validate(&base58::check_encode_slice(&data))
+        );
+        data[0] = 0x13;
+        data[1] = 42;
+        assert_eq!(
+            Err(AddressError::InvalidVersion(42)),
+            Address::validate(&base58::check_encode_slice(&data))
+        );
+        assert!(!Address::is_valid(""));
+    }
+
+    #[test]
+    fn test_address_params() {
+        let testnet = Address::from_str("bWvePLsBsf6nThU3pWVZVWjZbcJCYQxHCpE").unwrap();
+        assert_eq!(Network::Testnet, testnet.network);
+        let regtest = Address::parse_with_params(&testnet.to_string(), &Network::Regtest).unwrap();
+        assert_eq!(Network::Regtest, regtest.network);
+        assert_eq!(testnet.payload, regtest.payload);
+        assert_eq!(
+            testnet.to_string(),
+            regtest.to_string_with_params(&Network::Regtest)
+        );
+        assert_eq!(
+            Err(AddressError::InvalidVersion(111)),
+            Address::parse_with_params(&testnet.to_string(), &Network::Bitcoin)
+        );
+
+        let params = CustomAddressParams {
+            network: Network::Regtest,
+            namespace: 0x14,
+            pubkey_version: 0x32,
+            script_version: 0x33,
+        };
+        for &addr in &[
+            "akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U6E",
+            "anQin2TDYaubr6M5MQM8kNXMitHc2hsmfGc",
+        ] {
+            let address = Address::from_str(addr).unwrap();
+            let encoded = address.to_string_with_params(&params);
+            assert_ne!(addr, encoded);
+            let parsed = Address::parse_with_params(&encoded, &params).unwrap();
+            assert_eq!(Network::Regtest, parsed.network);
+            assert_eq!(address.script_pubkey(), parsed.script_pubkey());
+            assert_eq!(encoded, parsed.to_string_with_params(&params));
+            assert_eq!(
+                Err(AddressError::InvalidNamespace(0x14)),
+                Address::validate(&encoded)
+            );
+            assert_eq!(
+                Err(AddressError::InvalidNamespace(0x13)),
+                Address::parse_with_params(addr, &params)
+            );
+        }
+    }
+
+    #[test]
+    fn test_expect_network() {
+        let mainnet = Address::from_str("akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U6E").unwrap();
+        assert_eq!(Network::Bitcoin, mainnet.network);
+        assert_eq!(
+            mainnet,
+            mainnet.clone().expect_network(Network::Bitcoin).unwrap()
+        );
+        assert_eq!(
+            Err(AddressError::NetworkMismatch {
+                expected: Network::Testnet,
+                actual: Network::Bitcoin,
+            }),
+            mainnet.expect_network(Network::Testnet)
+        );
+
+        let testnet = Address::from_str("bWvePLsBsf6nThU3pWVZVWjZbcJCYQxHCpE").unwrap();
+        assert_eq!(Network::Testnet, testnet.network);
+        assert_eq!(
+            Err(AddressError::NetworkMismatch {
+                expected: Network::Bitcoin,
+                actual: Network::Testnet,
+            }),
+            testnet.clone().expect_network(Network::Bitcoin)
+        );
+        let regtest = testnet.clone().expect_network(Network::Regtest).unwrap();
+        assert_eq!(Network::Regtest, regtest.network);
+        assert_eq!(testnet.payload, regtest.payload);
+        assert_eq!(testnet.to_string(), regtest.to_string());
+        assert_eq!(testnet, regtest.expect_network(Network::Testnet).unwrap());
+    }
+
+    #[test]
+    fn test_from_script() {
+        for &btc_addr in &[
+            "1F2AQr6oqNtcJQ6p9SiCLQTrHuM9en44H8",
+            "3EktnHQD7RiAE6uzMj2ZifT9YgRrkSgzQX",
+            "mkgW6hNYBctmqDtTTsTJrsf2Gh2NPtoCU4",
+            "2MzQwSSnBHWHqSAqtTVQ6v47XtaisrJa1Vc",
+        ] {
+            let btc_addr = bitcoin::Address::from_str(btc_addr).unwrap();
+            assert_eq!(
+                btc_addr.to_oa_address().unwrap(),
+                Address::from_script(&btc_addr.script_pubkey(), btc_addr.network).unwrap()
+            );
+        }
+
+        let segwit_addr =
+            bitcoin::Address::from_str("bc1qvzvkjn4q3nszqxrv3nraga2r822xjty3ykvkuw").unwrap();
+        let segwit_oa_addr = Address::from_script(&segwit_addr.script_pubkey(), Network::Bitcoin);
+        #[cfg(feature = "segwit")]
+        assert_eq!(
+            segwit_addr.to_oa_address().unwrap(),
+            segwit_oa_addr.unwrap()
+        );
+        #[cfg(not(feature = "segwit"))]
+        assert!(segwit_oa_addr.is_err());
+
+        let op_return = Builder::new()
+            .push_opcode(opcodes::all::OP_RETURN)
+            .push_slice(b"OA")
+            .into_script();
+        assert!(Address::from_script(&op_return, Network::Bitcoin).is_err());
+        assert!(Address::from_script(&Script::new(), Network::Bitcoin).is_err());
+    }
+
+    #[cfg(feature = "segwit")]
+    #[test]
+    fn test_segwit() {
+        let p2wpkh =
+            bitcoin::Address::from_str("bc1qvzvkjn4q3nszqxrv3nraga2r822xjty3ykvkuw").unwrap();
+        let regtest = bitcoin::Address::p2wsh(&Script::new(), Network::Regtest);
+        for &(ref btc_addr, prefix) in &[
+            (p2wpkh.clone(), "oa1q"),
+            (
+                bitcoin::Address::from_str(
+                    "bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3",
+                )
+                .unwrap(),
+                "oa1q",
+            ),
+            (
+                bitcoin::Address::from_str("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx").unwrap(),
+                "toa1q",
+            ),
+            (regtest, "oart1q"),
+        ] {
+            let oa_addr = btc_addr.to_oa_address().unwrap();
+            let encoded = oa_addr.to_string();
+            assert!(encoded.starts_with(prefix), "{}", encoded);
+            let parsed = Address::from_str(&encoded).unwrap();
+            assert_eq!(oa_addr, parsed);
+            assert_eq!(btc_addr, &parsed.to_btc_addr().unwrap());
+            assert_eq!(btc_addr.script_pubkey(), parsed.script_pubkey());
+            assert_eq!(parsed, Address::from_str(&encoded.to_uppercase()).unwrap());
+        }
+
+        for &(btc_addr, oa_addr) in &[
+            (
+                "bc1qvzvkjn4q3nszqxrv3nraga2r822xjty3ykvkuw",
+                "oa1qvzvkjn4q3nszqxrv3nraga2r822xjty3dj7yrr",
+            ),
+            (
+                "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx",
+                "toa1qw508d6qejxtdg4y5r3zarvary0c5xw7kl9f2gn",
+            ),
+        ] {
+            let address = bitcoin::Address::from_str(btc_addr)
+                .unwrap()
+                .to_oa_address()
+                .unwrap();
+            assert_eq!(Ok(oa_addr.to_string()), address.try_to_string());
+            assert_eq!(oa_addr, address.to_string());
+            assert_eq!(oa_addr, address.to_string_with_params(&address.network));
+
+            // with the human-readable part of the chain of the params
+            let regtest = address
+                .try_to_string_with_params(&Network::Regtest)
+                .unwrap();
+            assert!(regtest.starts_with("oart1q"));
+            assert_eq!(regtest, address.to_string_with_params(&Network::Regtest));
+            let parsed = Address::parse_with_params(&regtest, &Network::Regtest).unwrap();
+            assert_eq!(Network::Regtest, parsed.network);
+            assert_eq!(address.script_pubkey(), parsed.script_pubkey());
+        }
+
+        // a corrupted checksum
+        let encoded = p2wpkh.to_oa_address().unwrap().to_string();
+        let mut corrupted = encoded.clone();
+        let last = corrupted.pop().unwrap();
+        corrupted.push(if last == 'q' { 'p' } else { 'q' });
+        assert!(Address::from_str(&corrupted).is_err());
+        assert_eq!(
+            Err(AddressError::InvalidBech32),
+            Address::validate(&corrupted)
+        );
+        assert_eq!(
+            Ok(ValidatedInfo {
+                network: Network::Bitcoin,
+                payload_type: PayloadType::WitnessProgram,
+            }),
+            Address::validate(&encoded)
+        );
+        // bitcoin segwit addresses are not Open Assets addresses
+        assert!(Address::from_str("bc1qvzvkjn4q3nszqxrv3nraga2r822xjty3ykvkuw").is_err());
+    }
+
+    #[test]
+    fn test_from_str() {
+        for &(btc_addr, oa_addr) in &[
+            (
+                "1F2AQr6oqNtcJQ6p9SiCLQTrHuM9en44H8",
+                "akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U6E",
+            ),
+            (
+                "mkgW6hNYBctmqDtTTsTJrsf2Gh2NPtoCU4",
+                "bWvePLsBsf6nThU3pWVZVWjZbcJCYQxHCpE",
+            ),
+            ("3EktnHQD7RiAE6uzMj2ZifT9YgRrkSgzQX", ""),
+            ("2MzQwSSnBHWHqSAqtTVQ6v47XtaisrJa1Vc", ""),
+        ] {
+            let btc_addr = bitcoin::Address::from_str(btc_addr).unwrap();
+            let encoded = btc_addr.to_oa_address().unwrap().to_string();
+            if !oa_addr.is_empty() {
+                assert_eq!(oa_addr, encoded);
+            }
+            let parsed = Address::from_str(&encoded).unwrap();
+            assert_eq!(btc_addr, parsed.to_btc_addr().unwrap());
+            assert_eq!(parsed, Address::try_from(encoded.as_str()).unwrap());
+        }
+
+        // P2SH addresses use the P2SH version bytes
+        let p2sh = bitcoin::Address::from_str("3EktnHQD7RiAE6uzMj2ZifT9YgRrkSgzQX").unwrap();
+        assert_eq!(
+            "anQin2TDYaubr6M5MQM8kNXMitHc2hsmfGc",
+            p2sh.to_oa_address().unwrap().to_string()
+        );
+
+        // bitcoin addresses lack the namespace byte
+        match Address::from_str("1F2AQr6oqNtcJQ6p9SiCLQTrHuM9en44H8") {
+            Err(encode::Error::Base58(base58::Error::InvalidLength(21))) => {}
+            _ => panic!("expected InvalidLength"),
+        }
+        // unknown namespace or address version
+        let mut data = vec![0x14, 0];
+        data.extend_from_slice(&[0; 20]);
+        match Address::from_str(&base58::check_encode_slice(&data)) {
+            Err(encode::Error::Base58(base58::Error::InvalidVersion(v))) => {
+                assert_eq!(vec![0x14], v)
+            }
+            _ => panic!("expected InvalidVersion"),
+        }
+        data[0] = 0x13;
+        data[1] = 42;
+        match Address::from_str(&base58::check_encode_slice(&data)) {
+            Err(encode::Error::Base58(base58::Error::InvalidVersion(v))) => assert_eq!(vec![42], v),
+            _ => panic!("expected InvalidVersion"),
+        }
+        data[1] = 111;
+        assert_eq!(
+            Network::Testnet,
+            Address::from_str(&base58::check_encode_slice(&data))
+                .unwrap()
+                .network
+        );
+        assert!(Address::from_str("akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U6F").is_err());
     }
 }
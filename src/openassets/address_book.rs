@@ -0,0 +1,218 @@
+use openassets::address::{self, Address, AddressError};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::btree_map::{self, BTreeMap};
+
+/// A counterparty of an address book.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Entry {
+    pub address: Address,
+    /// Free-form metadata about the counterparty, e.g. an email or a note.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl Entry {
+    pub fn new(address: Address) -> Entry {
+        Entry {
+            address,
+            metadata: BTreeMap::new(),
+        }
+    }
+}
+
+/// Addresses of counterparties, by label.
+///
+/// Serialized as a map from labels to entries, holding addresses as their string encoding.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct AddressBook {
+    entries: BTreeMap<String, Entry>,
+}
+
+impl AddressBook {
+    pub fn new() -> AddressBook {
+        AddressBook::default()
+    }
+
+    /// Adds `address` under `label`, returning the entry it replaces.
+    pub fn insert(&mut self, label: &str, address: Address) -> Option<Entry> {
+        self.entries.insert(label.to_string(), Entry::new(address))
+    }
+
+    /// Parses `address` and adds it under `label`, returning the entry it replaces.
+    pub fn insert_str(
+        &mut self,
+        label: &str,
+        address: &str,
+    ) -> Result<Option<Entry>, AddressError> {
+        let address = address::decode(address)?;
+        Ok(self.insert(label, address))
+    }
+
+    pub fn get(&self, label: &str) -> Option<&Entry> {
+        self.entries.get(label)
+    }
+
+    pub fn get_mut(&mut self, label: &str) -> Option<&mut Entry> {
+        self.entries.get_mut(label)
+    }
+
+    /// Returns the label of the first entry, in label order, holding `address`.
+    pub fn label_of(&self, address: &Address) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|&(_, entry)| entry.address == *address)
+            .map(|(label, _)| label.as_str())
+    }
+
+    /// Sets the metadata `key` of the entry labelled `label`, returning false if there is no
+    /// such entry.
+    pub fn set_metadata(&mut self, label: &str, key: &str, value: &str) -> bool {
+        match self.entries.get_mut(label) {
+            Some(entry) => {
+                entry.metadata.insert(key.to_string(), value.to_string());
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn remove(&mut self, label: &str) -> Option<Entry> {
+        self.entries.remove(label)
+    }
+
+    /// Returns the labels and entries, in label order.
+    pub fn iter(&self) -> btree_map::Iter<'_, String, Entry> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Loads the address book from `store`.
+    pub fn load<S: AddressBookStore>(store: &S) -> Result<AddressBook, S::Error> {
+        store.load()
+    }
+
+    /// Saves the address book to `store`.
+    pub fn save<S: AddressBookStore>(&self, store: &mut S) -> Result<(), S::Error> {
+        store.save(self)
+    }
+}
+
+/// Persistent storage of an address book, e.g. a file or a database row.
+pub trait AddressBookStore {
+    type Error;
+
+    fn load(&self) -> Result<AddressBook, Self::Error>;
+
+    fn save(&mut self, book: &AddressBook) -> Result<(), Self::Error>;
+}
+
+#[cfg(test)]
+mod tests {
+    use openassets::address::{Address, AddressError};
+    use openassets::address_book::{AddressBook, AddressBookStore};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_address_book() {
+        let alice = Address::from_str("akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U6E").unwrap();
+        let bob = Address::from_str("anQin2TDYaubr6M5MQM8kNXMitHc2hsmfGc").unwrap();
+        let mut book = AddressBook::new();
+        assert!(book.is_empty());
+        assert!(book.insert("alice", alice.clone()).is_none());
+        assert!(book
+            .insert_str("bob", "anQin2TDYaubr6M5MQM8kNXMitHc2hsmfGc")
+            .unwrap()
+            .is_none());
+        assert_eq!(2, book.len());
+        assert_eq!(alice, book.get("alice").unwrap().address);
+        assert_eq!(Some("bob"), book.label_of(&bob));
+        assert!(book.get("carol").is_none());
+
+        assert!(book.set_metadata("alice", "email", "alice@example.com"));
+        assert!(!book.set_metadata("carol", "email", "carol@example.com"));
+        assert_eq!(
+            "alice@example.com",
+            book.get("alice").unwrap().metadata["email"]
+        );
+        assert_eq!(
+            vec!["alice", "bob"],
+            book.iter().map(|(l, _)| l.as_str()).collect::<Vec<&str>>()
+        );
+
+        // replacing an entry drops its metadata
+        let replaced = book.insert("alice", bob.clone()).unwrap();
+        assert_eq!(alice, replaced.address);
+        assert!(book.get("alice").unwrap().metadata.is_empty());
+        assert_eq!(Some("alice"), book.label_of(&bob));
+
+        assert_eq!(
+            Err(AddressError::InvalidLength(21)),
+            book.insert_str("carol", "1F2AQr6oqNtcJQ6p9SiCLQTrHuM9en44H8")
+        );
+        assert_eq!(bob, book.remove("bob").unwrap().address);
+        assert!(book.remove("bob").is_none());
+        assert_eq!(1, book.len());
+    }
+
+    #[test]
+    fn test_store() {
+        struct MemoryStore(Option<AddressBook>);
+
+        impl AddressBookStore for MemoryStore {
+            type Error = &'static str;
+
+            fn load(&self) -> Result<AddressBook, &'static str> {
+                self.0.clone().ok_or("empty store")
+            }
+
+            fn save(&mut self, book: &AddressBook) -> Result<(), &'static str> {
+                self.0 = Some(book.clone());
+                Ok(())
+            }
+        }
+
+        let mut store = MemoryStore(None);
+        assert!(AddressBook::load(&store).is_err());
+        let mut book = AddressBook::new();
+        book.insert_str("alice", "akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U6E")
+            .unwrap();
+        book.save(&mut store).unwrap();
+        assert_eq!(book, AddressBook::load(&store).unwrap());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let mut book = AddressBook::new();
+        book.insert_str("alice", "akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U6E")
+            .unwrap();
+        book.set_metadata("alice", "note", "supplier");
+        let json = serde_json::to_string(&book).unwrap();
+        assert_eq!(
+            r#"{"alice":{"address":"akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U6E","metadata":{"note":"supplier"}}}"#,
+            json
+        );
+        assert_eq!(book, serde_json::from_str(&json).unwrap());
+
+        // metadata is optional, addresses are validated
+        assert!(serde_json::from_str::<AddressBook>(
+            r#"{"bob":{"address":"anQin2TDYaubr6M5MQM8kNXMitHc2hsmfGc"}}"#
+        )
+        .is_ok());
+        assert!(serde_json::from_str::<AddressBook>(
+            r#"{"bob":{"address":"1F2AQr6oqNtcJQ6p9SiCLQTrHuM9en44H8"}}"#
+        )
+        .is_err());
+    }
+}
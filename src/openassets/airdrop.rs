@@ -0,0 +1,239 @@
+use bitcoin::Script;
+use openassets::address::ToScriptPubkey;
+use openassets::asset_id::AssetId;
+use openassets::builder::{BuildError, TransferBuilder};
+use openassets::dust::DustPolicy;
+use openassets::fee::estimate_vsize;
+use openassets::marker_output::MAX_OP_RETURN_SIZE;
+use openassets::quantity::checked_sum;
+use openassets::selection::CoinSelector;
+use openassets::unsigned::UnsignedColoredTransaction;
+use openassets::utxo::ColoredUtxo;
+
+/// The weight of the largest transaction relayed by default.
+pub const MAX_STANDARD_TX_WEIGHT: u64 = 400_000;
+
+/// Builds the transactions distributing units of an asset to many recipients.
+///
+/// Recipients are split, in order, into as few transactions as the size limits of the marker
+/// output and of the transaction allow. Each transaction spends its own inputs, selected from
+/// the UTXOs added to the builder, so that the transactions can be signed and broadcast
+/// independently. The colored change of a transaction is not spent by the following ones, so
+/// the units to distribute must be held by enough UTXOs.
+#[derive(Debug, Clone)]
+pub struct AirdropBuilder {
+    asset_id: AssetId,
+    inputs: Vec<ColoredUtxo>,
+    recipients: Vec<(Script, u64)>,
+    change_script: Script,
+    selector: CoinSelector,
+    fee: u64,
+    fee_rate: Option<u64>,
+    dust: DustPolicy,
+    max_marker_size: usize,
+    max_weight: u64,
+}
+
+impl AirdropBuilder {
+    pub fn new(asset_id: AssetId, change_script: Script) -> AirdropBuilder {
+        AirdropBuilder {
+            asset_id,
+            inputs: vec![],
+            recipients: vec![],
+            change_script,
+            selector: CoinSelector::default(),
+            fee: 0,
+            fee_rate: None,
+            dust: DustPolicy::default(),
+            max_marker_size: MAX_OP_RETURN_SIZE,
+            max_weight: MAX_STANDARD_TX_WEIGHT,
+        }
+    }
+
+    /// Adds a colored or uncolored UTXO the transactions can spend.
+    pub fn add_input(mut self, utxo: ColoredUtxo) -> Self {
+        self.inputs.push(utxo);
+        self
+    }
+
+    /// Sends `quantity` units to `recipient`, a script, an Open Assets address or a bitcoin
+    /// address.
+    pub fn add_recipient<R: ToScriptPubkey>(mut self, recipient: R, quantity: u64) -> Self {
+        self.recipients
+            .push((recipient.to_script_pubkey(), quantity));
+        self
+    }
+
+    /// Sets the selector choosing the inputs of each transaction.
+    pub fn coin_selector(mut self, selector: CoinSelector) -> Self {
+        self.selector = selector;
+        self
+    }
+
+    /// Sets the fee paid by each transaction, in satoshis.
+    pub fn fee(mut self, fee: u64) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    /// Sets the fee rate, in satoshis per virtual byte, the fee of each transaction is
+    /// estimated from once signed. Overrides the fee set by `fee`.
+    pub fn fee_rate(mut self, fee_rate: u64) -> Self {
+        self.fee_rate = Some(fee_rate);
+        self
+    }
+
+    /// Sets the policy giving the value of the outputs carrying assets.
+    pub fn dust_policy(mut self, dust: DustPolicy) -> Self {
+        self.dust = dust;
+        self
+    }
+
+    /// Sets the size, in bytes, the payload of each marker output must not exceed.
+    pub fn max_marker_size(mut self, size: usize) -> Self {
+        self.max_marker_size = size;
+        self
+    }
+
+    /// Sets the weight each transaction must not exceed. Defaults to
+    /// `MAX_STANDARD_TX_WEIGHT`.
+    pub fn max_weight(mut self, weight: u64) -> Self {
+        self.max_weight = weight;
+        self
+    }
+
+    /// Builds the unsigned transactions, in recipient order.
+    ///
+    /// Fails if a single recipient does not fit in a transaction, or if the UTXOs left once the
+    /// previous transactions are built do not cover a transaction.
+    pub fn build(&self) -> Result<Vec<UnsignedColoredTransaction>, BuildError> {
+        let mut pool = self.inputs.clone();
+        let mut batch = vec![];
+        let mut start = 0;
+        while start < self.recipients.len() {
+            let mut end = start + 1;
+            let mut unsigned = self.build_chunk(&self.recipients[start..end], &pool)?;
+            while end < self.recipients.len() {
+                match self.build_chunk(&self.recipients[start..=end], &pool) {
+                    Ok(larger) => {
+                        unsigned = larger;
+                        end += 1;
+                    }
+                    Err(BuildError::NonStandardMarker(_))
+                    | Err(BuildError::TransactionTooLarge { .. }) => break,
+                    Err(e) => return Err(e),
+                }
+            }
+            pool.retain(|u| !unsigned.inputs.iter().any(|i| i.outpoint == u.outpoint));
+            batch.push(unsigned);
+            start = end;
+        }
+        Ok(batch)
+    }
+
+    /// Builds the transaction sending units to `recipients`, spending UTXOs from `pool`.
+    ///
+    /// The satoshis to select start at zero and are raised to the amount the previous attempt
+    /// fell short of, until the selected UTXOs cover the outputs and the fee.
+    fn build_chunk(
+        &self,
+        recipients: &[(Script, u64)],
+        pool: &[ColoredUtxo],
+    ) -> Result<UnsignedColoredTransaction, BuildError> {
+        let quantity = checked_sum(recipients.iter().map(|r| r.1))?;
+        let targets = [(self.asset_id.clone(), quantity)];
+        let mut value = 0;
+        loop {
+            let selected = self.selector.select(pool, &targets, value)?;
+            let mut builder = TransferBuilder::new(self.change_script.clone())
+                .fee(self.fee)
+                .dust_policy(self.dust)
+                .max_marker_size(self.max_marker_size);
+            if let Some(fee_rate) = self.fee_rate {
+                builder = builder.fee_rate(fee_rate);
+            }
+            let builder = selected.into_iter().fold(builder, |b, u| b.add_input(u));
+            let builder = recipients.iter().fold(builder, |b, r| {
+                b.add_recipient(self.asset_id.clone(), r.1, &r.0)
+            });
+            match builder.build_unsigned() {
+                Err(BuildError::InsufficientFunds { required, .. }) if required > value => {
+                    value = required;
+                }
+                Err(e) => return Err(e),
+                Ok(unsigned) => {
+                    let weight = estimate_vsize(&unsigned.tx, &unsigned.prev_scripts()) * 4;
+                    if weight > self.max_weight {
+                        return Err(BuildError::TransactionTooLarge {
+                            weight,
+                            limit: self.max_weight,
+                        });
+                    }
+                    return Ok(unsigned);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::network::constants::Network;
+    use openassets::airdrop::AirdropBuilder;
+    use openassets::asset_id::AssetId;
+    use openassets::builder::BuildError;
+    use openassets::coloring::{ColoredOutput, OutputKind};
+    use openassets::test_helpers::{script, utxo};
+
+    #[test]
+    fn test_build() {
+        let change = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let alice = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+        let asset_id = AssetId::new(&change, Network::Bitcoin);
+        let mut builder = AirdropBuilder::new(asset_id.clone(), change.clone()).fee_rate(1);
+        for vout in 0..4 {
+            builder = builder.add_input(utxo(
+                vout,
+                600,
+                ColoredOutput::new(asset_id.clone(), 15_000, OutputKind::Transfer),
+            ));
+        }
+        builder = builder
+            .add_input(utxo(4, 100_000, ColoredOutput::uncolored()))
+            .add_input(utxo(5, 100_000, ColoredOutput::uncolored()));
+        for _ in 0..50 {
+            builder = builder.add_recipient(&alice, 1_000);
+        }
+        let batch = builder.build().unwrap();
+
+        // 4 bytes of marker and version, 1 byte of count, 2 bytes per recipient, 2 bytes of
+        // colored change and 1 byte of metadata fit 36 recipients in 80 bytes
+        assert_eq!(2, batch.len());
+        let transferred = |i: usize| -> Vec<u64> {
+            batch[i]
+                .output_colors
+                .iter()
+                .zip(batch[i].tx.output.iter())
+                .filter(|&(c, o)| c.is_colored() && o.script_pubkey == alice)
+                .map(|(c, _)| c.asset_quantity)
+                .collect()
+        };
+        assert_eq!(vec![1_000; 36], transferred(0));
+        assert_eq!(vec![1_000; 14], transferred(1));
+        assert!(!batch[1].inputs.iter().any(|u| batch[0].inputs.contains(u)));
+
+        // the colored change of the first transaction is not spent by the second one
+        match builder.clone().add_recipient(&alice, 1_001).build() {
+            Err(BuildError::InsufficientAssetUnits {
+                required: 15_001,
+                available: 15_000,
+                ..
+            }) => {}
+            _ => panic!("expected InsufficientAssetUnits"),
+        }
+        match builder.max_marker_size(5).build() {
+            Err(BuildError::NonStandardMarker(_)) => {}
+            _ => panic!("expected NonStandardMarker"),
+        }
+    }
+}
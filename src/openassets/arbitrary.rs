@@ -0,0 +1,142 @@
+use bitcoin::network::constants::Network;
+use bitcoin::util::address;
+use bitcoin::Script;
+use bitcoin_hashes::{hash160, Hash};
+use openassets::address::Address;
+use openassets::marker_output::{Metadata, Payload, MAX_QUANTITY};
+use proptest::prelude::*;
+
+impl Arbitrary for Metadata {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Metadata>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop::collection::vec(any::<u8>(), 0..64)
+            .prop_map(Metadata::new)
+            .boxed()
+    }
+}
+
+/// Generates version 1.0 payloads of up to 16 quantities, each within `MAX_QUANTITY`.
+impl Arbitrary for Payload {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Payload>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            prop::collection::vec(0..=MAX_QUANTITY, 0..16),
+            any::<Metadata>(),
+        )
+            .prop_map(|(quantities, metadata)| Payload::new(quantities, metadata))
+            .boxed()
+    }
+}
+
+/// Generates P2PKH and P2SH addresses of every network.
+impl Arbitrary for Address {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Address>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            any::<[u8; 20]>(),
+            any::<bool>(),
+            prop_oneof![
+                Just(Network::Bitcoin),
+                Just(Network::Testnet),
+                Just(Network::Regtest)
+            ],
+        )
+            .prop_map(|(hash, script, network)| {
+                let hash = hash160::Hash::from_slice(&hash).expect("20 bytes long");
+                let payload = if script {
+                    address::Payload::ScriptHash(hash)
+                } else {
+                    address::Payload::PubkeyHash(hash)
+                };
+                Address { network, payload }
+            })
+            .boxed()
+    }
+}
+
+/// Returns a strategy generating the scripts of marker outputs carrying arbitrary payloads.
+pub fn marker_script() -> impl Strategy<Value = Script> {
+    any::<Payload>().prop_map(|payload| payload.to_txout().unwrap().script_pubkey)
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::consensus::{deserialize, serialize};
+    use bitcoin::util::base58;
+    use openassets::address::{Address, AddressError};
+    use openassets::arbitrary::marker_script;
+    use openassets::marker_output::{Payload, PayloadPolicy, PayloadRef, ScriptExt};
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn test_payload_round_trip(payload in any::<Payload>()) {
+            let data = serialize(&payload);
+            prop_assert_eq!(&payload, &deserialize::<Payload>(&data).unwrap());
+            prop_assert_eq!(&payload, &Payload::from_slice(&data, PayloadPolicy::Strict).unwrap());
+            let payload_ref = PayloadRef::parse(&data, PayloadPolicy::Strict).unwrap();
+            prop_assert_eq!(&payload, &payload_ref.to_payload());
+        }
+
+        #[test]
+        fn test_marker_script(script in marker_script()) {
+            prop_assert!(script.is_openassets_marker());
+        }
+
+        #[test]
+        fn test_address_round_trip(address in any::<Address>()) {
+            let encoded = address.to_string();
+            prop_assert_eq!(22, base58::from_check(&encoded).unwrap().len());
+            prop_assert_eq!(
+                &address,
+                &Address::parse_with_params(&encoded, &address.network).unwrap()
+            );
+        }
+
+        #[test]
+        fn test_address_base58_payload(
+            data in prop::collection::vec(any::<u8>(), 0..48),
+            namespace in any::<bool>(),
+        ) {
+            let mut data = data;
+            if namespace && !data.is_empty() {
+                data[0] = 0x13;
+            }
+            let encoded = base58::check_encode_slice(&data);
+            match Address::validate(&encoded) {
+                Ok(_) => {
+                    // accepted only if nothing is left out of the address
+                    prop_assert_eq!(22, data.len());
+                    let address: Address = encoded.parse().unwrap();
+                    prop_assert_eq!(&encoded, &address.to_string());
+                }
+                Err(AddressError::InvalidLength(len)) => {
+                    prop_assert_ne!(22, data.len());
+                    prop_assert_eq!(data.len(), len);
+                }
+                Err(AddressError::InvalidNamespace(_)) | Err(AddressError::InvalidVersion(_)) => {
+                    prop_assert_eq!(22, data.len());
+                }
+                Err(e) => prop_assert!(false, "unexpected error {:?}", e),
+            }
+        }
+
+        #[test]
+        fn test_address_any_string(s in "\\PC*") {
+            let _ = Address::validate(&s);
+        }
+
+        #[test]
+        fn test_address_base58_string(s in "[1-9A-HJ-NP-Za-km-z]{0,64}") {
+            if let Ok(address) = s.parse::<Address>() {
+                prop_assert_eq!(&s, &address.to_string());
+            }
+        }
+    }
+}
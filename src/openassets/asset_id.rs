@@ -1,67 +1,485 @@
+use bitcoin::blockdata::opcodes;
+use bitcoin::blockdata::script::{Builder, Instruction};
 use bitcoin::consensus::encode;
 use bitcoin::util::base58;
-use bitcoin::Script;
-use bitcoin_hashes::{hash160, Hash};
+use bitcoin::util::key::PublicKey;
+use bitcoin::{Address, OutPoint, Script, TxIn};
+use bitcoin_hashes::{hash160, sha256, Hash};
+use openassets::coloring::OutputProvider;
+use openassets::error::Error;
+use openassets::uri;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+#[cfg(feature = "serde")]
+use serde::de::{self, Visitor};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt::{self, Display, Formatter};
 use std::str::FromStr;
 
+/// The version byte of asset ids on the main network.
+pub const BITCOIN_VERSION: u8 = 0x17;
+
+/// The version byte of asset ids on the test networks.
+pub const TESTNET_VERSION: u8 = 0x73;
+
+/// Returns the version byte of asset ids on `network`.
+///
+/// The Open Assets specification defines no version byte for regtest, whose asset ids use the
+/// testnet one.
+pub fn version_byte(network: bitcoin::network::constants::Network) -> u8 {
+    network.asset_id_version()
+}
+
+/// Parameters of the chain an asset id belongs to, letting chains other than Bitcoin use
+/// version bytes of their own.
+pub trait NetworkParams {
+    /// Returns the network recorded in the asset ids of the chain.
+    fn network(&self) -> bitcoin::network::constants::Network;
+
+    /// Returns the version byte of the base58check encoding of asset ids.
+    fn asset_id_version(&self) -> u8;
+}
+
+impl NetworkParams for bitcoin::network::constants::Network {
+    fn network(&self) -> bitcoin::network::constants::Network {
+        *self
+    }
+
+    fn asset_id_version(&self) -> u8 {
+        match *self {
+            bitcoin::network::constants::Network::Bitcoin => BITCOIN_VERSION,
+            bitcoin::network::constants::Network::Testnet
+            | bitcoin::network::constants::Network::Regtest => TESTNET_VERSION,
+        }
+    }
+}
+
+/// The scheme of asset URIs.
+pub const URI_SCHEME: &str = "openassets";
+
+/// A reference to an asset, optionally with an amount and a label, encoded as an
+/// `openassets:<asset id>?amount=<quantity>&label=<label>` URI for QR codes and deep links.
 #[derive(PartialEq, Eq, Debug, Clone)]
+pub struct AssetUri {
+    pub asset_id: AssetId,
+    /// A quantity of the asset, e.g. requested for payment.
+    pub amount: Option<u64>,
+    pub label: Option<String>,
+}
+
+impl AssetUri {
+    pub fn new(asset_id: AssetId) -> AssetUri {
+        AssetUri {
+            asset_id,
+            amount: None,
+            label: None,
+        }
+    }
+
+    pub fn amount(mut self, amount: u64) -> AssetUri {
+        self.amount = Some(amount);
+        self
+    }
+
+    pub fn label(mut self, label: &str) -> AssetUri {
+        self.label = Some(label.to_string());
+        self
+    }
+}
+
+impl Display for AssetUri {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        write!(fmt, "{}:{}", URI_SCHEME, self.asset_id)?;
+        let mut separator = '?';
+        if let Some(amount) = self.amount {
+            write!(fmt, "{}amount={}", separator, amount)?;
+            separator = '&';
+        }
+        if let Some(ref label) = self.label {
+            write!(fmt, "{}label={}", separator, uri::percent_encode(label))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses asset URIs, ignoring unknown query parameters unless they are prefixed with `req-`.
+impl FromStr for AssetUri {
+    type Err = encode::Error;
+
+    fn from_str(s: &str) -> Result<AssetUri, encode::Error> {
+        let (asset_id, params) = uri::split(s, URI_SCHEME)?;
+        let mut uri = AssetUri::new(AssetId::from_str(asset_id)?);
+        for (key, value) in params {
+            match key {
+                "amount" => uri.amount = Some(uri::parse_amount(value)?),
+                "label" => uri.label = Some(uri::percent_decode(value)?),
+                k => uri::check_optional(k)?,
+            }
+        }
+        Ok(uri)
+    }
+}
+
+/// Parameters of a chain with an asset id version byte of its own, e.g. a Tapyrus-based chain.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub struct CustomParams {
+    /// The network recorded in the asset ids of the chain.
+    pub network: bitcoin::network::constants::Network,
+    pub asset_id_version: u8,
+}
+
+impl NetworkParams for CustomParams {
+    fn network(&self) -> bitcoin::network::constants::Network {
+        self.network
+    }
+
+    fn asset_id_version(&self) -> u8 {
+        self.asset_id_version
+    }
+}
+
+/// Which script identifies the asset issued by spending a segwit output.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash, Default)]
+pub enum SegwitPolicy {
+    /// The script_pubkey of the spent output is hashed as is, like for any other output.
+    /// This is what the Open Assets specification and the coloring engine do.
+    #[default]
+    WitnessProgram,
+    /// The script committed to by the witness program is hashed instead: the P2PKH script of
+    /// the key hash for P2WPKH and the witness script for P2WSH, also when nested in P2SH.
+    RedeemScript,
+}
+
+/// An asset, identified by the hash of its issuance script.
+///
+/// Equality takes the network into account, so that assets of different networks never mix,
+/// e.g. as map keys. Use `same_asset` to compare assets across networks, e.g. mainnet data
+/// replayed on regtest.
+#[derive(PartialEq, Eq, Debug, Clone, Hash)]
 pub struct AssetId {
     pub hash: bitcoin_hashes::hash160::Hash,
     pub network: bitcoin::network::constants::Network,
 }
 
 impl AssetId {
+    /// Returns the asset issued by spending outputs whose script_pubkey is `script`.
+    ///
+    /// Witness programs, nested in P2SH or not, are hashed as is. Use `from_input` with
+    /// `SegwitPolicy::RedeemScript` to identify segwit issuances by the script they commit to.
     pub fn new(script: &Script, network: bitcoin::network::constants::Network) -> AssetId {
         AssetId {
-            hash: hash160::Hash::hash(&script.to_bytes()),
+            hash: hash160::Hash::hash(script.as_bytes()),
             network,
         }
     }
+
+    /// Returns the asset issued by each of `scripts`, in order, hashing them in parallel when
+    /// the `rayon` feature is enabled.
+    pub fn batch_new(
+        scripts: &[Script],
+        network: bitcoin::network::constants::Network,
+    ) -> Vec<AssetId> {
+        let new = |script: &Script| AssetId::new(script, network);
+        #[cfg(feature = "rayon")]
+        let asset_ids = scripts.par_iter().map(new).collect();
+        #[cfg(not(feature = "rayon"))]
+        let asset_ids = scripts.iter().map(new).collect();
+        asset_ids
+    }
+
+    /// Returns the asset of `network` whose issuance script hashes to `hash`.
+    pub fn from_hash(
+        hash: hash160::Hash,
+        network: bitcoin::network::constants::Network,
+    ) -> AssetId {
+        AssetId { hash, network }
+    }
+
+    /// Returns the 20 bytes of the hash of the issuance script.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.hash[..]
+    }
+
+    /// Returns the hash of the issuance script, dropping the network.
+    pub fn into_inner(self) -> [u8; 20] {
+        self.hash.into_inner()
+    }
+
+    /// Returns the asset issued by a transaction whose first input is `input`, spending an
+    /// output whose script_pubkey is `prev_script`.
+    ///
+    /// Under `SegwitPolicy::RedeemScript` a script which cannot be resolved from `input`, such as
+    /// a witness script not matching the witness program, falls back to `prev_script`, as do
+    /// outputs other than P2WPKH, P2WSH and their P2SH-nested forms.
+    pub fn from_input(
+        prev_script: &Script,
+        input: &TxIn,
+        policy: SegwitPolicy,
+        network: bitcoin::network::constants::Network,
+    ) -> AssetId {
+        match policy {
+            SegwitPolicy::WitnessProgram => AssetId::new(prev_script, network),
+            SegwitPolicy::RedeemScript => {
+                let script = resolve_witness_script(prev_script, input)
+                    .unwrap_or_else(|| prev_script.clone());
+                AssetId::new(&script, network)
+            }
+        }
+    }
+
+    /// Returns the asset issued by a transaction whose first input spends `outpoint`, fetching
+    /// the spent output from `provider`.
+    pub fn from_outpoint<P: OutputProvider>(
+        outpoint: &OutPoint,
+        provider: &P,
+        network: bitcoin::network::constants::Network,
+    ) -> Result<AssetId, Error> {
+        let output = provider.get_output(outpoint)?;
+        Ok(AssetId::new(&output.script_pubkey, network))
+    }
+
+    /// Returns whether `other` has the same issuance script hash, regardless of network.
+    pub fn same_asset(&self, other: &AssetId) -> bool {
+        self.hash == other.hash
+    }
+
+    /// Returns whether `script` is the issuance script of the asset, regardless of network.
+    ///
+    /// The hashes are compared in constant time.
+    pub fn matches_script(&self, script: &Script) -> bool {
+        let hash = hash160::Hash::hash(script.as_bytes());
+        hash[..]
+            .iter()
+            .zip(self.hash[..].iter())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+    }
+
+    /// Returns the `openassets:` URI of the asset, without amount nor label.
+    pub fn to_uri(&self) -> String {
+        AssetUri::new(self.clone()).to_string()
+    }
+
+    /// Parses an `openassets:` URI, returning the asset it refers to along with its optional
+    /// amount and label.
+    pub fn from_uri(s: &str) -> Result<AssetUri, encode::Error> {
+        AssetUri::from_str(s)
+    }
+
+    /// Returns the hex encoding of the hash of the issuance script.
+    pub fn to_hex(&self) -> String {
+        hex::encode(&self.hash[..])
+    }
+
+    /// Parses the hex encoding of the hash of the issuance script of an asset of `network`.
+    pub fn from_hex(
+        s: &str,
+        network: bitcoin::network::constants::Network,
+    ) -> Result<AssetId, encode::Error> {
+        let data = hex::decode(s).map_err(|_| encode::Error::ParseFailed("Invalid hex."))?;
+        let hash = hash160::Hash::from_slice(&data)
+            .map_err(|_| encode::Error::ParseFailed("Asset id hash must be 20 bytes long."))?;
+        Ok(AssetId { hash, network })
+    }
+
+    /// Returns the asset issued by spending outputs sent to the P2PKH address of `pubkey`.
+    pub fn from_pubkey(
+        pubkey: &PublicKey,
+        network: bitcoin::network::constants::Network,
+    ) -> AssetId {
+        AssetId::from_address(&Address::p2pkh(pubkey, network))
+    }
+
+    /// Returns the asset issued by spending outputs sent to `address`.
+    pub fn from_address(address: &Address) -> AssetId {
+        AssetId::new(&address.script_pubkey(), address.network)
+    }
+
+    /// Parses an asset id of `network`, failing if its version byte is not the one of
+    /// `network`.
+    ///
+    /// Unlike `from_str`, which resolves testnet version bytes to testnet, this returns regtest
+    /// asset ids when parsing them for regtest.
+    pub fn parse(
+        s: &str,
+        network: bitcoin::network::constants::Network,
+    ) -> Result<AssetId, encode::Error> {
+        AssetId::parse_with_params(s, &network)
+    }
+
+    /// Parses an asset id of the chain described by `params`, failing if its version byte is
+    /// not the one of the chain.
+    pub fn parse_with_params<P: NetworkParams>(
+        s: &str,
+        params: &P,
+    ) -> Result<AssetId, encode::Error> {
+        let (actual, hash) = decode_base58(s)?;
+        if actual != params.asset_id_version() {
+            return Err(encode::Error::Base58(base58::Error::InvalidVersion(vec![
+                actual,
+            ])));
+        }
+        Ok(AssetId {
+            hash,
+            network: params.network(),
+        })
+    }
+
+    /// Parses an asset id of `network` encoded with the version byte `version`, e.g. for
+    /// networks using a version byte of their own.
+    pub fn parse_with_version(
+        s: &str,
+        version: u8,
+        network: bitcoin::network::constants::Network,
+    ) -> Result<AssetId, encode::Error> {
+        AssetId::parse_with_params(
+            s,
+            &CustomParams {
+                network,
+                asset_id_version: version,
+            },
+        )
+    }
+
+    /// Returns the base58check encoding of the asset id with the version byte `version`
+    /// rather than the one of its network.
+    pub fn to_string_with_version(&self, version: u8) -> String {
+        let mut prefixed = [0; 21];
+        prefixed[0] = version;
+        prefixed[1..].copy_from_slice(&self.hash[..]);
+        base58::check_encode_slice(&prefixed[..])
+    }
+
+    /// Returns the base58check encoding of the asset id with the version byte of the chain
+    /// described by `params`.
+    pub fn to_string_with_params<P: NetworkParams>(&self, params: &P) -> String {
+        self.to_string_with_version(params.asset_id_version())
+    }
 }
 
 impl Display for AssetId {
     fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
         let mut prefixed = [0; 21];
-        prefixed[0] = match self.network {
-            bitcoin::network::constants::Network::Bitcoin => 0x17,
-            bitcoin::network::constants::Network::Testnet
-            | bitcoin::network::constants::Network::Regtest => 0x73,
-        };
+        prefixed[0] = self.network.asset_id_version();
         prefixed[1..].copy_from_slice(&self.hash[..]);
         base58::check_encode_slice_to_fmt(fmt, &prefixed[..])
     }
 }
 
+/// Parses mainnet and testnet asset ids.
+///
+/// Fails with `InvalidLength` carrying the decoded length unless the payload is exactly 21
+/// bytes long, and with `InvalidVersion` carrying the version byte unless it is a known one.
 impl FromStr for AssetId {
     type Err = encode::Error;
 
     fn from_str(s: &str) -> Result<AssetId, encode::Error> {
-        let data = base58::from_check(s)?;
-        let (network, hash) = match data[0] {
-            0x17 => (
-                bitcoin::network::constants::Network::Bitcoin,
-                hash160::Hash::from_slice(&data[1..]).unwrap(),
-            ),
-            0x73 => (
-                bitcoin::network::constants::Network::Testnet,
-                hash160::Hash::from_slice(&data[1..]).unwrap(),
-            ),
-            x => {
-                return Err(encode::Error::Base58(base58::Error::InvalidVersion(vec![
-                    x,
-                ])))
+        let (version, hash) = decode_base58(s)?;
+        let network = [
+            bitcoin::network::constants::Network::Bitcoin,
+            bitcoin::network::constants::Network::Testnet,
+        ]
+        .iter()
+        .find(|n| n.asset_id_version() == version)
+        .cloned()
+        .ok_or_else(|| encode::Error::Base58(base58::Error::InvalidVersion(vec![version])))?;
+        Ok(AssetId { hash, network })
+    }
+}
+
+/// Serialized as its base58check encoding. Regtest asset ids are deserialized as testnet ones,
+/// as by `from_str`.
+#[cfg(feature = "serde")]
+impl Serialize for AssetId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for AssetId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct AssetIdVisitor;
+
+        impl<'de> Visitor<'de> for AssetIdVisitor {
+            type Value = AssetId;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a base58check encoded asset id")
             }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<AssetId, E> {
+                AssetId::from_str(v).map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(AssetIdVisitor)
+    }
+}
+
+/// Returns the script committed to by the segwit output `prev_script` spent by `input`.
+fn resolve_witness_script(prev_script: &Script, input: &TxIn) -> Option<Script> {
+    let program = if prev_script.is_p2sh() {
+        let redeem_script = match input.script_sig.iter(true).last() {
+            Some(Instruction::PushBytes(data)) => Script::from(data.to_vec()),
+            _ => return None,
         };
-        Ok(AssetId { hash, network })
+        if redeem_script.to_p2sh() != *prev_script {
+            return None;
+        }
+        redeem_script
+    } else {
+        prev_script.clone()
+    };
+
+    if program.is_v0_p2wpkh() {
+        Some(
+            Builder::new()
+                .push_opcode(opcodes::all::OP_DUP)
+                .push_opcode(opcodes::all::OP_HASH160)
+                .push_slice(&program[2..])
+                .push_opcode(opcodes::all::OP_EQUALVERIFY)
+                .push_opcode(opcodes::all::OP_CHECKSIG)
+                .into_script(),
+        )
+    } else if program.is_v0_p2wsh() {
+        let witness_script = input.witness.last()?;
+        if sha256::Hash::hash(witness_script)[..] != program[2..] {
+            return None;
+        }
+        Some(Script::from(witness_script.clone()))
+    } else {
+        None
+    }
+}
+
+/// Decodes the version byte and the hash of a base58check encoded asset id.
+fn decode_base58(s: &str) -> Result<(u8, hash160::Hash), encode::Error> {
+    let data = base58::from_check(s)?;
+    if data.len() != 21 {
+        return Err(encode::Error::Base58(base58::Error::InvalidLength(
+            data.len(),
+        )));
     }
+    let hash = hash160::Hash::from_slice(&data[1..]).expect("20 bytes long");
+    Ok((data[0], hash))
 }
 
 #[cfg(test)]
 mod tests {
     use bitcoin::blockdata::script::Builder;
+    use bitcoin::consensus::encode;
+    use bitcoin::network::constants::Network;
+    use bitcoin::util::base58;
+    use bitcoin::util::key::PublicKey;
+    use bitcoin::{Address, OutPoint, Script, Transaction, TxIn, TxOut};
+    use bitcoin_hashes::{hash160, Hash};
     use hex::decode as hex_decode;
-    use openassets::asset_id::AssetId;
+    use openassets::asset_id::{AssetId, AssetUri, CustomParams, NetworkParams, SegwitPolicy};
+    use openassets::error::Error;
+    use openassets::test_helpers::script;
+    use std::collections::HashMap;
     use std::str::FromStr;
 
     #[test]
@@ -93,4 +511,428 @@ mod tests {
             testnet_asset
         );
     }
+
+    #[test]
+    fn test_raw_bytes() {
+        let asset_id = AssetId::from_str("ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC").unwrap();
+        let bytes = asset_id.as_bytes().to_vec();
+        assert_eq!(20, bytes.len());
+        assert_eq!(&asset_id.hash[..], &bytes[..]);
+
+        let hash = hash160::Hash::from_slice(&bytes).unwrap();
+        assert_eq!(asset_id, AssetId::from_hash(hash, Network::Bitcoin));
+        assert_ne!(asset_id, AssetId::from_hash(hash, Network::Testnet));
+        assert_eq!(&bytes[..], &asset_id.into_inner()[..]);
+    }
+
+    #[test]
+    fn test_from_outpoint() {
+        let p2pkh = Builder::from(
+            hex_decode("76a914010966776006953d5567439e5e39f86a0d273bee88ac").unwrap(),
+        )
+        .into_script();
+        let tx = Transaction {
+            version: 1,
+            lock_time: 0,
+            input: vec![],
+            output: vec![
+                TxOut {
+                    value: 600,
+                    script_pubkey: Script::new(),
+                },
+                TxOut {
+                    value: 600,
+                    script_pubkey: p2pkh.clone(),
+                },
+            ],
+        };
+        let mut provider = HashMap::new();
+        provider.insert(tx.txid(), tx.clone());
+
+        let outpoint = OutPoint {
+            txid: tx.txid(),
+            vout: 1,
+        };
+        assert_eq!(
+            AssetId::new(&p2pkh, Network::Bitcoin),
+            AssetId::from_outpoint(&outpoint, &provider, Network::Bitcoin).unwrap()
+        );
+        let outpoint = OutPoint {
+            txid: tx.txid(),
+            vout: 2,
+        };
+        match AssetId::from_outpoint(&outpoint, &provider, Network::Bitcoin) {
+            Err(Error::OutputNotFound(o)) => assert_eq!(outpoint, o),
+            _ => panic!("expected OutputNotFound"),
+        }
+        match AssetId::from_outpoint(&OutPoint::default(), &provider, Network::Bitcoin) {
+            Err(Error::TransactionNotFound(_)) => {}
+            _ => panic!("expected TransactionNotFound"),
+        }
+    }
+
+    #[test]
+    fn test_hex() {
+        let p2pkh = Builder::from(
+            hex_decode("76a914010966776006953d5567439e5e39f86a0d273bee88ac").unwrap(),
+        )
+        .into_script();
+        let asset_id = AssetId::new(&p2pkh, Network::Bitcoin);
+        let hex = asset_id.to_hex();
+        assert_eq!(40, hex.len());
+        assert_eq!(hex_decode(&hex).unwrap(), asset_id.as_bytes());
+        assert_eq!(asset_id, AssetId::from_hex(&hex, Network::Bitcoin).unwrap());
+        assert_eq!(
+            Network::Testnet,
+            AssetId::from_hex(&hex, Network::Testnet).unwrap().network
+        );
+        assert!(AssetId::from_hex(&hex[2..], Network::Bitcoin).is_err());
+        assert!(AssetId::from_hex("zz", Network::Bitcoin).is_err());
+    }
+
+    #[test]
+    fn test_regtest() {
+        let p2pkh = Builder::from(
+            hex_decode("76a914010966776006953d5567439e5e39f86a0d273bee88ac").unwrap(),
+        )
+        .into_script();
+        let regtest_asset = AssetId::new(&p2pkh, Network::Regtest);
+        let encoded = regtest_asset.to_string();
+        assert_eq!(AssetId::new(&p2pkh, Network::Testnet).to_string(), encoded);
+        assert_eq!(
+            Network::Testnet,
+            AssetId::from_str(&encoded).unwrap().network
+        );
+        assert_eq!(
+            regtest_asset,
+            AssetId::parse(&encoded, Network::Regtest).unwrap()
+        );
+        assert!(AssetId::parse(&encoded, Network::Bitcoin).is_err());
+
+        // a version byte of its own
+        let encoded = regtest_asset.to_string_with_version(0x55);
+        assert!(AssetId::from_str(&encoded).is_err());
+        assert!(AssetId::parse(&encoded, Network::Regtest).is_err());
+        assert_eq!(
+            regtest_asset,
+            AssetId::parse_with_version(&encoded, 0x55, Network::Regtest).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_matches_script() {
+        let p2pkh = Builder::from(
+            hex_decode("76a914010966776006953d5567439e5e39f86a0d273bee88ac").unwrap(),
+        )
+        .into_script();
+        let other = Builder::from(
+            hex_decode("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac").unwrap(),
+        )
+        .into_script();
+        let asset_id = AssetId::new(&p2pkh, Network::Bitcoin);
+        assert!(asset_id.matches_script(&p2pkh));
+        assert!(!asset_id.matches_script(&other));
+        assert!(!asset_id.matches_script(&Script::new()));
+        assert!(AssetId::new(&p2pkh, Network::Testnet).matches_script(&p2pkh));
+    }
+
+    #[test]
+    fn test_from_str_untrusted() {
+        // every payload length around 21 bytes, with every known version byte
+        for len in 0..43 {
+            for &version in &[0x17, 0x73, 0x00] {
+                let mut data = vec![0xAB; len];
+                if len > 0 {
+                    data[0] = version;
+                }
+                let encoded = base58::check_encode_slice(&data);
+                match AssetId::from_str(&encoded) {
+                    Ok(asset_id) => {
+                        assert_eq!(21, len);
+                        assert_eq!(&data[1..], asset_id.as_bytes());
+                    }
+                    Err(encode::Error::Base58(base58::Error::InvalidLength(l))) => {
+                        assert_ne!(21, len);
+                        assert_eq!(len, l);
+                    }
+                    Err(encode::Error::Base58(base58::Error::InvalidVersion(v))) => {
+                        assert_eq!(21, len);
+                        assert_eq!(vec![version], v);
+                    }
+                    Err(e) => {
+                        // payloads shorter than the checksum
+                        assert!(len == 0, "{}: {:?}", len, e);
+                    }
+                }
+            }
+        }
+
+        // arbitrary strings never panic
+        let valid = "ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC";
+        for i in 0..valid.len() {
+            assert!(AssetId::from_str(&valid[..i]).is_err());
+            assert!(AssetId::from_str(&format!("{}{}", valid, &valid[..i + 1])).is_err());
+            let mut corrupted = valid.as_bytes().to_vec();
+            corrupted[i] = if corrupted[i] == b'1' { b'2' } else { b'1' };
+            assert!(AssetId::from_str(::std::str::from_utf8(&corrupted).unwrap()).is_err());
+        }
+        for s in &[
+            "",
+            " ",
+            "0OIl",
+            "ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC ",
+            "\u{0}",
+            "é",
+        ] {
+            assert!(AssetId::from_str(s).is_err());
+        }
+    }
+
+    #[test]
+    fn test_uri() {
+        let asset_id = AssetId::from_str("ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC").unwrap();
+        assert_eq!(
+            "openassets:ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC",
+            asset_id.to_uri()
+        );
+        assert_eq!(
+            AssetUri::new(asset_id.clone()),
+            AssetId::from_uri(&asset_id.to_uri()).unwrap()
+        );
+
+        let uri = AssetUri::new(asset_id.clone())
+            .amount(100)
+            .label("Gold & Silver");
+        let encoded = uri.to_string();
+        assert_eq!(
+            "openassets:ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC?amount=100&label=Gold%20%26%20Silver",
+            encoded
+        );
+        assert_eq!(uri, AssetId::from_uri(&encoded).unwrap());
+        assert_eq!(
+            AssetUri::new(asset_id.clone()).label("Gold"),
+            AssetId::from_uri("OPENASSETS:ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC?label=Gold&foo=bar")
+                .unwrap()
+        );
+
+        for invalid in &[
+            "ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC",
+            "bitcoin:ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC",
+            "openassets:ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBD",
+            "openassets:ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC?amount=-1",
+            "openassets:ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC?label=%2",
+            "openassets:ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC?label=%ff",
+            "openassets:ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC?req-foo=bar",
+        ] {
+            assert!(AssetId::from_uri(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_batch_new() {
+        let scripts: Vec<Script> = (0..100u8)
+            .map(|i| Builder::new().push_slice(&[i; 20]).into_script())
+            .collect();
+        let asset_ids = AssetId::batch_new(&scripts, Network::Testnet);
+        assert_eq!(scripts.len(), asset_ids.len());
+        for (script, asset_id) in scripts.iter().zip(asset_ids.iter()) {
+            assert_eq!(&AssetId::new(script, Network::Testnet), asset_id);
+        }
+        assert!(AssetId::batch_new(&[], Network::Bitcoin).is_empty());
+    }
+
+    #[test]
+    fn test_same_asset() {
+        let p2pkh = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let mainnet = AssetId::new(&p2pkh, Network::Bitcoin);
+        let regtest = AssetId::new(&p2pkh, Network::Regtest);
+        assert_ne!(mainnet, regtest);
+        assert!(mainnet.same_asset(&regtest));
+        assert!(mainnet.same_asset(&mainnet));
+        let other = AssetId::new(
+            &script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac"),
+            Network::Bitcoin,
+        );
+        assert!(!mainnet.same_asset(&other));
+    }
+
+    #[test]
+    fn test_custom_params() {
+        let params = CustomParams {
+            network: Network::Regtest,
+            asset_id_version: 0x55,
+        };
+        let asset_id = AssetId::new(
+            &script("76a914010966776006953d5567439e5e39f86a0d273bee88ac"),
+            Network::Regtest,
+        );
+        let encoded = asset_id.to_string_with_params(&params);
+        assert_eq!(asset_id.to_string_with_version(0x55), encoded);
+        assert_eq!(
+            asset_id,
+            AssetId::parse_with_params(&encoded, &params).unwrap()
+        );
+        assert!(AssetId::from_str(&encoded).is_err());
+        assert!(AssetId::parse_with_params(&asset_id.to_string(), &params).is_err());
+
+        assert_eq!(0x17, Network::Bitcoin.asset_id_version());
+        assert_eq!(0x73, Network::Regtest.asset_id_version());
+        assert_eq!(
+            asset_id.to_string(),
+            asset_id.to_string_with_params(&Network::Regtest)
+        );
+    }
+
+    #[test]
+    fn test_segwit() {
+        let p2pkh = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let p2wpkh = script("0014010966776006953d5567439e5e39f86a0d273bee");
+        let input = |script_sig: Script, witness: Vec<Vec<u8>>| TxIn {
+            previous_output: OutPoint::default(),
+            script_sig,
+            sequence: 0xFFFF_FFFF,
+            witness,
+        };
+        let from_input = |prev: &Script, input: &TxIn, policy| {
+            AssetId::from_input(prev, input, policy, Network::Bitcoin)
+        };
+
+        // witness programs are hashed as is by default
+        let spend = input(Script::new(), vec![vec![0x30], vec![0x02]]);
+        assert_eq!(
+            AssetId::new(&p2wpkh, Network::Bitcoin),
+            from_input(&p2wpkh, &spend, SegwitPolicy::default())
+        );
+        assert_ne!(
+            AssetId::new(&p2pkh, Network::Bitcoin),
+            AssetId::new(&p2wpkh, Network::Bitcoin)
+        );
+
+        // P2WPKH resolves to the P2PKH script of the same key hash
+        let expected = AssetId::new(&p2pkh, Network::Bitcoin);
+        assert_eq!(
+            expected,
+            from_input(&p2wpkh, &spend, SegwitPolicy::RedeemScript)
+        );
+        // also when nested in P2SH
+        let p2sh = p2wpkh.to_p2sh();
+        let nested = input(
+            Builder::new().push_slice(p2wpkh.as_bytes()).into_script(),
+            vec![vec![0x30], vec![0x02]],
+        );
+        assert_eq!(
+            expected,
+            from_input(&p2sh, &nested, SegwitPolicy::RedeemScript)
+        );
+        assert_eq!(
+            AssetId::new(&p2sh, Network::Bitcoin),
+            from_input(&p2sh, &nested, SegwitPolicy::WitnessProgram)
+        );
+
+        // P2WSH resolves to the witness script
+        let witness_script =
+            script("5121020000000000000000000000000000000000000000000000000000000000000251ae");
+        let p2wsh = witness_script.to_v0_p2wsh();
+        let spend = input(Script::new(), vec![vec![], witness_script.to_bytes()]);
+        assert_eq!(
+            AssetId::new(&witness_script, Network::Bitcoin),
+            from_input(&p2wsh, &spend, SegwitPolicy::RedeemScript)
+        );
+        assert_eq!(
+            AssetId::new(&p2wsh, Network::Bitcoin),
+            from_input(&p2wsh, &spend, SegwitPolicy::WitnessProgram)
+        );
+
+        // unresolvable scripts fall back to the script_pubkey
+        let spend = input(Script::new(), vec![p2pkh.to_bytes()]);
+        assert_eq!(
+            AssetId::new(&p2wsh, Network::Bitcoin),
+            from_input(&p2wsh, &spend, SegwitPolicy::RedeemScript)
+        );
+        assert_eq!(
+            AssetId::new(&p2sh, Network::Bitcoin),
+            from_input(&p2sh, &spend, SegwitPolicy::RedeemScript)
+        );
+        assert_eq!(
+            expected,
+            from_input(&p2pkh, &spend, SegwitPolicy::RedeemScript)
+        );
+    }
+
+    #[test]
+    fn test_invalid_length() {
+        // truncated
+        let asset_id = AssetId::from_str("36cYQuJb4S57XaaXiP8Kqgi2hEodn4wce");
+        match asset_id {
+            Err(encode::Error::Base58(base58::Error::InvalidLength(20))) => {}
+            _ => panic!("expected InvalidLength"),
+        }
+        // oversized
+        let asset_id = AssetId::parse("hrD4YWN3MXDPtK5w57EBYDFdAqA1713zHGe", Network::Bitcoin);
+        match asset_id {
+            Err(encode::Error::Base58(base58::Error::InvalidLength(22))) => {}
+            _ => panic!("expected InvalidLength"),
+        }
+        let asset_id = AssetId::parse_with_version(
+            "36cYQuJb4S57XaaXiP8Kqgi2hEodn4wce",
+            0x17,
+            Network::Bitcoin,
+        );
+        match asset_id {
+            Err(encode::Error::Base58(base58::Error::InvalidLength(20))) => {}
+            _ => panic!("expected InvalidLength"),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let asset_id = AssetId::from_str("ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC").unwrap();
+        let json = serde_json::to_string(&asset_id).unwrap();
+        assert_eq!(r#""ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC""#, json);
+        assert_eq!(asset_id, serde_json::from_str(&json).unwrap());
+
+        // as a map key
+        let mut quantities = HashMap::new();
+        quantities.insert(asset_id.clone(), 100u64);
+        let json = serde_json::to_string(&quantities).unwrap();
+        assert_eq!(r#"{"ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC":100}"#, json);
+        assert_eq!(
+            quantities,
+            serde_json::from_str::<HashMap<AssetId, u64>>(&json).unwrap()
+        );
+
+        assert!(
+            serde_json::from_str::<AssetId>(r#""ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBD""#).is_err()
+        );
+        assert!(serde_json::from_str::<AssetId>(r#""16UwLL9Risc3QfPqBUvKofHmBQ7wMtjvM""#).is_err());
+        assert!(serde_json::from_str::<AssetId>("100").is_err());
+    }
+
+    #[test]
+    fn test_from_pubkey_and_address() {
+        let address = Address::from_str("16UwLL9Risc3QfPqBUvKofHmBQ7wMtjvM").unwrap();
+        assert_eq!(
+            "ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC",
+            AssetId::from_address(&address).to_string()
+        );
+
+        let pubkey = PublicKey::from_str(
+            "0250863ad64a87ae8a2fe83c1af1a8403cb53f53e486d8511dad8a04887e5b2352",
+        )
+        .unwrap();
+        let p2pkh = Builder::from(
+            hex_decode("76a914f54a5851e9372b87810a8e60cdd2e7cfd80b6e3188ac").unwrap(),
+        )
+        .into_script();
+        assert_eq!(
+            AssetId::new(&p2pkh, Network::Testnet),
+            AssetId::from_pubkey(&pubkey, Network::Testnet)
+        );
+        let address = Address::from_str("1PMycacnJaSqwwJqjawXBErnLsZ7RkXUAs").unwrap();
+        assert_eq!(
+            AssetId::from_address(&address),
+            AssetId::from_pubkey(&pubkey, Network::Bitcoin)
+        );
+    }
 }
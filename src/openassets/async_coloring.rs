@@ -0,0 +1,188 @@
+use bitcoin::{OutPoint, Transaction, TxOut};
+use bitcoin_hashes::sha256d;
+use futures::future::{self, BoxFuture, FutureExt, TryFutureExt};
+use openassets::coloring::{color_coinbase, find_marker, ColoredOutput, ColoringEngine};
+use openassets::error::Error;
+use std::collections::{HashMap, HashSet};
+
+/// Asynchronous source of previous transactions, e.g. backed by an HTTP API.
+pub trait AsyncOutputProvider: Sync {
+    fn get_transaction<'a>(
+        &'a self,
+        txid: &sha256d::Hash,
+    ) -> BoxFuture<'a, Result<Transaction, Error>>;
+
+    fn get_output<'a>(&'a self, outpoint: &OutPoint) -> BoxFuture<'a, Result<TxOut, Error>> {
+        let outpoint = *outpoint;
+        self.get_transaction(&outpoint.txid)
+            .map(move |tx| {
+                tx.and_then(|tx| {
+                    tx.output
+                        .get(outpoint.vout as usize)
+                        .cloned()
+                        .ok_or(Error::OutputNotFound(outpoint))
+                })
+            })
+            .boxed()
+    }
+}
+
+impl AsyncOutputProvider for HashMap<sha256d::Hash, Transaction> {
+    fn get_transaction<'a>(
+        &'a self,
+        txid: &sha256d::Hash,
+    ) -> BoxFuture<'a, Result<Transaction, Error>> {
+        let tx = self
+            .get(txid)
+            .cloned()
+            .ok_or(Error::TransactionNotFound(*txid));
+        future::ready(tx).boxed()
+    }
+}
+
+impl ColoringEngine {
+    /// Asynchronous variant of `resolve_transaction`.
+    ///
+    /// The colors of the inputs of a transaction are resolved concurrently.
+    pub fn resolve_transaction_async<'a, P: AsyncOutputProvider>(
+        &'a self,
+        tx: &Transaction,
+        provider: &'a P,
+    ) -> BoxFuture<'a, Result<Vec<ColoredOutput>, Error>> {
+        resolve(self, tx.clone(), provider, HashSet::new())
+    }
+
+    /// Asynchronous variant of `resolve_output`.
+    pub fn resolve_output_async<'a, P: AsyncOutputProvider>(
+        &'a self,
+        outpoint: &OutPoint,
+        provider: &'a P,
+    ) -> BoxFuture<'a, Result<ColoredOutput, Error>> {
+        resolve_outpoint(self, *outpoint, provider, HashSet::new())
+    }
+}
+
+/// Colors `tx`, `path` holds the ids of the descendants being resolved.
+fn resolve<'a, P: AsyncOutputProvider>(
+    engine: &'a ColoringEngine,
+    tx: Transaction,
+    provider: &'a P,
+    mut path: HashSet<sha256d::Hash>,
+) -> BoxFuture<'a, Result<Vec<ColoredOutput>, Error>> {
+    if tx.is_coin_base() {
+        return future::ok(color_coinbase(&tx)).boxed();
+    }
+    let (marker_index, payload) = match find_marker(&tx) {
+        Some(marker) => marker,
+        None => return future::ok(vec![ColoredOutput::uncolored(); tx.output.len()]).boxed(),
+    };
+    let txid = tx.txid();
+    if !path.insert(txid) {
+        return future::err(Error::CyclicReference(txid)).boxed();
+    }
+    let issuance_output = provider.get_output(&tx.input[0].previous_output);
+    let input_colors = if payload.quantities.len() > marker_index {
+        let inputs = tx
+            .input
+            .iter()
+            .map(|input| resolve_outpoint(engine, input.previous_output, provider, path.clone()));
+        future::try_join_all(inputs).boxed()
+    } else {
+        // only issuance, the colors of inputs are irrelevant
+        future::ok(vec![ColoredOutput::uncolored(); tx.input.len()]).boxed()
+    };
+    future::try_join(issuance_output, input_colors)
+        .and_then(move |(issuance_output, input_colors)| {
+            let colors = engine.assign(
+                &tx,
+                &issuance_output.script_pubkey,
+                &input_colors,
+                marker_index,
+                &payload,
+                None,
+            );
+            future::ready(engine.apply_policy(colors, tx.output.len()))
+        })
+        .boxed()
+}
+
+fn resolve_outpoint<'a, P: AsyncOutputProvider>(
+    engine: &'a ColoringEngine,
+    outpoint: OutPoint,
+    provider: &'a P,
+    path: HashSet<sha256d::Hash>,
+) -> BoxFuture<'a, Result<ColoredOutput, Error>> {
+    provider
+        .get_transaction(&outpoint.txid)
+        .and_then(move |prev_tx| resolve(engine, prev_tx, provider, path))
+        .and_then(move |colors| {
+            future::ready(
+                colors
+                    .get(outpoint.vout as usize)
+                    .cloned()
+                    .ok_or(Error::OutputNotFound(outpoint)),
+            )
+        })
+        .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::network::constants::Network;
+    use bitcoin::OutPoint;
+    use futures::executor::block_on;
+    use openassets::asset_id::AssetId;
+    use openassets::coloring::{ColoredOutput, ColoringEngine, OutputKind};
+    use openassets::error::Error;
+    use openassets::test_helpers::{build_spending_tx, script};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_resolve_transaction_async() {
+        let issuer = "76a914010966776006953d5567439e5e39f86a0d273bee88ac";
+        let p2pkh = "76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac";
+        let funding = build_spending_tx(vec![OutPoint::default()], vec![issuer]);
+        let issuance = build_spending_tx(
+            vec![OutPoint {
+                txid: funding.txid(),
+                vout: 0,
+            }],
+            vec![p2pkh, "6a074f410100016400"],
+        );
+        let transfer = build_spending_tx(
+            vec![OutPoint {
+                txid: issuance.txid(),
+                vout: 0,
+            }],
+            vec!["6a084f410100023c2800", p2pkh, p2pkh],
+        );
+        let mut provider = HashMap::new();
+        provider.insert(funding.txid(), funding);
+        provider.insert(issuance.txid(), issuance.clone());
+
+        let engine = ColoringEngine::new(Network::Bitcoin);
+        let asset_id = AssetId::new(&script(issuer), Network::Bitcoin);
+        assert_eq!(
+            vec![
+                ColoredOutput::marker(),
+                ColoredOutput::new(asset_id.clone(), 60, OutputKind::Transfer),
+                ColoredOutput::new(asset_id.clone(), 40, OutputKind::Transfer),
+            ],
+            block_on(engine.resolve_transaction_async(&transfer, &provider)).unwrap()
+        );
+        let outpoint = OutPoint {
+            txid: issuance.txid(),
+            vout: 0,
+        };
+        assert_eq!(
+            ColoredOutput::new(asset_id, 100, OutputKind::Issuance),
+            block_on(engine.resolve_output_async(&outpoint, &provider)).unwrap()
+        );
+
+        provider.remove(&issuance.txid());
+        match block_on(engine.resolve_transaction_async(&transfer, &provider)) {
+            Err(Error::TransactionNotFound(txid)) => assert_eq!(issuance.txid(), txid),
+            _ => panic!("expected TransactionNotFound"),
+        }
+    }
+}
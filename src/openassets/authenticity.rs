@@ -0,0 +1,165 @@
+use openassets::definition::AssetDefinition;
+
+/// Access to the TLS certificates asset definition files were served with, provided by the
+/// HTTP client which fetched them.
+pub trait CertificateCheck {
+    /// Returns the subject of the certificate `host` served the file with, if the file was
+    /// served over a TLS connection whose certificate is valid for `host`.
+    fn certificate_subject(&self, host: &str) -> Option<String>;
+}
+
+impl<F: Fn(&str) -> Option<String>> CertificateCheck for F {
+    fn certificate_subject(&self, host: &str) -> Option<String> {
+        self(host)
+    }
+}
+
+/// The outcome of the proof of authenticity of an asset definition, which links the issuer of
+/// an asset to the domain serving its definition file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthenticityStatus {
+    /// The definition does not set `link_to_website`, so its issuer is not vouched for.
+    NotClaimed,
+    /// The definition is served by `domain`, whose certificate names `issuer`.
+    Verified { domain: String, issuer: String },
+    /// The definition is not served over HTTPS.
+    InsecureUrl,
+    /// The certificate of `domain` could not be verified.
+    UnverifiedCertificate { domain: String },
+    /// The definition claims to be issued by `claimed`, while the certificate of the domain
+    /// serving it names `certified`.
+    IssuerMismatch { claimed: String, certified: String },
+}
+
+impl AuthenticityStatus {
+    /// Returns true if the issuer is verified, e.g. to show a verified issuer badge.
+    pub fn is_verified(&self) -> bool {
+        matches!(*self, AuthenticityStatus::Verified { .. })
+    }
+}
+
+impl AssetDefinition {
+    /// Runs the proof of authenticity of the definition, fetched from `url`.
+    ///
+    /// If the definition sets `link_to_website`, its issuer is the subject of the certificate
+    /// of the domain serving it, as returned by `check`. A definition naming another issuer is
+    /// not authentic.
+    pub fn authenticity<C: CertificateCheck>(&self, url: &str, check: &C) -> AuthenticityStatus {
+        if !self.link_to_website {
+            return AuthenticityStatus::NotClaimed;
+        }
+        let domain = match https_host(url) {
+            Some(domain) => domain,
+            None => return AuthenticityStatus::InsecureUrl,
+        };
+        let subject = match check.certificate_subject(&domain) {
+            Some(subject) => subject,
+            None => return AuthenticityStatus::UnverifiedCertificate { domain },
+        };
+        match self.issuer {
+            Some(ref issuer) if *issuer != subject => AuthenticityStatus::IssuerMismatch {
+                claimed: issuer.clone(),
+                certified: subject,
+            },
+            _ => AuthenticityStatus::Verified {
+                domain,
+                issuer: subject,
+            },
+        }
+    }
+}
+
+/// Returns the lowercase host of an `https://` URL, without user info nor port.
+fn https_host(url: &str) -> Option<String> {
+    let scheme_len = "https://".len();
+    if url.len() < scheme_len || !url[..scheme_len].eq_ignore_ascii_case("https://") {
+        return None;
+    }
+    let rest = &url[scheme_len..];
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    let host = if host.starts_with('[') {
+        &host[..=host.find(']')?]
+    } else {
+        host.split(':').next()?
+    };
+    if host.is_empty() {
+        return None;
+    }
+    Some(host.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use openassets::authenticity::{https_host, AuthenticityStatus};
+    use openassets::definition::AssetDefinition;
+
+    fn certificates(host: &str) -> Option<String> {
+        match host {
+            "example.com" => Some("Example Inc.".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_authenticity() {
+        let url = "https://example.com/asset.json";
+        let mut definition = AssetDefinition::new();
+        assert_eq!(
+            AuthenticityStatus::NotClaimed,
+            definition.authenticity(url, &certificates)
+        );
+
+        definition.link_to_website = true;
+        let verified = AuthenticityStatus::Verified {
+            domain: "example.com".to_string(),
+            issuer: "Example Inc.".to_string(),
+        };
+        assert_eq!(verified, definition.authenticity(url, &certificates));
+        assert!(definition.authenticity(url, &certificates).is_verified());
+        definition.issuer = Some("Example Inc.".to_string());
+        assert_eq!(verified, definition.authenticity(url, &certificates));
+
+        definition.issuer = Some("Other Inc.".to_string());
+        assert_eq!(
+            AuthenticityStatus::IssuerMismatch {
+                claimed: "Other Inc.".to_string(),
+                certified: "Example Inc.".to_string(),
+            },
+            definition.authenticity(url, &certificates)
+        );
+        assert_eq!(
+            AuthenticityStatus::InsecureUrl,
+            definition.authenticity("http://example.com/asset.json", &certificates)
+        );
+        let status = definition.authenticity("https://other.com/asset.json", &certificates);
+        assert_eq!(
+            AuthenticityStatus::UnverifiedCertificate {
+                domain: "other.com".to_string()
+            },
+            status
+        );
+        assert!(!status.is_verified());
+    }
+
+    #[test]
+    fn test_https_host() {
+        for &(url, host) in &[
+            ("https://example.com/asset.json", "example.com"),
+            ("HTTPS://Example.COM", "example.com"),
+            ("https://user@example.com:8443/a?b#c", "example.com"),
+            ("https://example.com?a=b", "example.com"),
+            ("https://[::1]:8443/asset.json", "[::1]"),
+        ] {
+            assert_eq!(Some(host.to_string()), https_host(url), "{}", url);
+        }
+        for url in &[
+            "http://example.com",
+            "example.com",
+            "https://",
+            "https:///a",
+        ] {
+            assert_eq!(None, https_host(url), "{}", url);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use bitcoin::{Block, OutPoint, Transaction};
+use bitcoin_hashes::sha256d;
+use openassets::coloring::{ColorCache, ColoredOutput, ColoringEngine, OutputProvider};
+use openassets::error::Error;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+/// An `OutputProvider` serving the transactions of a block before falling back to `inner`.
+struct BlockProvider<'a, P: 'a> {
+    txs: HashMap<sha256d::Hash, &'a Transaction>,
+    inner: &'a P,
+}
+
+impl<'a, P: OutputProvider> OutputProvider for BlockProvider<'a, P> {
+    fn get_transaction(&self, txid: &sha256d::Hash) -> Result<Transaction, Error> {
+        match self.txs.get(txid) {
+            Some(tx) => Ok((*tx).clone()),
+            None => self.inner.get_transaction(txid),
+        }
+    }
+}
+
+/// A `ColorCache` serving the colors known before the block and those already resolved for
+/// earlier transactions of the block.
+struct BlockCache<'a> {
+    utxos: &'a HashMap<OutPoint, ColoredOutput>,
+    known: &'a HashMap<OutPoint, ColoredOutput>,
+    local: HashMap<OutPoint, ColoredOutput>,
+}
+
+impl<'a> ColorCache for BlockCache<'a> {
+    fn get(&mut self, outpoint: &OutPoint) -> Option<ColoredOutput> {
+        self.known
+            .get(outpoint)
+            .or_else(|| self.utxos.get(outpoint))
+            .or_else(|| self.local.get(outpoint))
+            .cloned()
+    }
+
+    fn insert(&mut self, outpoint: OutPoint, color: ColoredOutput) {
+        self.local.insert(outpoint, color);
+    }
+}
+
+impl ColoringEngine {
+    /// Colors every output of every transaction in `block`.
+    ///
+    /// Transactions are grouped by their depth in the chains of transactions spending each
+    /// other within the block. Groups are colored in order, and the transactions of a group,
+    /// which are independent of each other, are colored in parallel when the `rayon` feature
+    /// is enabled.
+    pub fn color_block<P: OutputProvider + Sync>(
+        &self,
+        block: &Block,
+        provider: &P,
+    ) -> Result<HashMap<OutPoint, ColoredOutput>, Error> {
+        self.color_block_with_utxos(block, provider, &HashMap::new())
+    }
+
+    /// Same as `color_block`, looking up the colors of outputs created before the block in
+    /// `utxos` before resolving them from `provider`.
+    pub(crate) fn color_block_with_utxos<P: OutputProvider + Sync>(
+        &self,
+        block: &Block,
+        provider: &P,
+        utxos: &HashMap<OutPoint, ColoredOutput>,
+    ) -> Result<HashMap<OutPoint, ColoredOutput>, Error> {
+        let txids: Vec<sha256d::Hash> = block.txdata.iter().map(|tx| tx.txid()).collect();
+        let provider = BlockProvider {
+            txs: txids.iter().cloned().zip(block.txdata.iter()).collect(),
+            inner: provider,
+        };
+
+        let mut colors = HashMap::new();
+        for level in dependency_levels(&block.txdata, &txids) {
+            let results = {
+                let color = |&index: &usize| {
+                    let mut cache = BlockCache {
+                        utxos,
+                        known: &colors,
+                        local: HashMap::new(),
+                    };
+                    self.resolve_transaction_with_cache(&block.txdata[index], &provider, &mut cache)
+                        .map(|outputs| (index, outputs))
+                };
+                #[cfg(feature = "rayon")]
+                let results: Vec<_> = level.par_iter().map(color).collect();
+                #[cfg(not(feature = "rayon"))]
+                let results: Vec<_> = level.iter().map(color).collect();
+                results
+            };
+            for result in results {
+                let (index, outputs) = result?;
+                for (vout, output) in outputs.into_iter().enumerate() {
+                    let outpoint = OutPoint {
+                        txid: txids[index],
+                        vout: vout as u32,
+                    };
+                    colors.insert(outpoint, output);
+                }
+            }
+        }
+        Ok(colors)
+    }
+}
+
+/// Groups the indices of `txs` so that every transaction comes after the transactions of the
+/// same block it spends.
+fn dependency_levels(txs: &[Transaction], txids: &[sha256d::Hash]) -> Vec<Vec<usize>> {
+    let mut depths: HashMap<sha256d::Hash, usize> = HashMap::new();
+    let mut levels: Vec<Vec<usize>> = vec![];
+    for (index, tx) in txs.iter().enumerate() {
+        let depth = tx
+            .input
+            .iter()
+            .filter_map(|input| depths.get(&input.previous_output.txid))
+            .map(|depth| depth + 1)
+            .max()
+            .unwrap_or(0);
+        depths.insert(txids[index], depth);
+        if levels.len() <= depth {
+            levels.resize(depth + 1, vec![]);
+        }
+        levels[depth].push(index);
+    }
+    levels
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::blockdata::block::BlockHeader;
+    use bitcoin::network::constants::Network;
+    use bitcoin::{Block, OutPoint};
+    use openassets::asset_id::AssetId;
+    use openassets::coloring::{ColoredOutput, ColoringEngine, OutputKind};
+    use openassets::test_helpers::{build_spending_tx, script};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_color_block() {
+        let issuer = "76a914010966776006953d5567439e5e39f86a0d273bee88ac";
+        let p2pkh = "76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac";
+        let funding = build_spending_tx(vec![OutPoint::default()], vec![issuer]);
+        let coinbase = build_spending_tx(vec![OutPoint::null()], vec![p2pkh]);
+        let issuance = build_spending_tx(
+            vec![OutPoint {
+                txid: funding.txid(),
+                vout: 0,
+            }],
+            vec![p2pkh, "6a074f410100016400"],
+        );
+        let transfer = build_spending_tx(
+            vec![OutPoint {
+                txid: issuance.txid(),
+                vout: 0,
+            }],
+            vec!["6a084f410100023c2800", p2pkh, p2pkh],
+        );
+        let block = Block {
+            header: BlockHeader {
+                version: 1,
+                prev_blockhash: Default::default(),
+                merkle_root: Default::default(),
+                time: 0,
+                bits: 0,
+                nonce: 0,
+            },
+            txdata: vec![coinbase.clone(), issuance.clone(), transfer.clone()],
+        };
+        let mut provider = HashMap::new();
+        provider.insert(funding.txid(), funding);
+
+        let engine = ColoringEngine::new(Network::Bitcoin);
+        let colors = engine.color_block(&block, &provider).unwrap();
+        let asset_id = AssetId::new(&script(issuer), Network::Bitcoin);
+        let color = |txid, vout| colors[&OutPoint { txid, vout }].clone();
+        assert_eq!(6, colors.len());
+        assert_eq!(ColoredOutput::uncolored(), color(coinbase.txid(), 0));
+        assert_eq!(
+            ColoredOutput::new(asset_id.clone(), 100, OutputKind::Issuance),
+            color(issuance.txid(), 0)
+        );
+        assert_eq!(ColoredOutput::marker(), color(transfer.txid(), 0));
+        assert_eq!(
+            ColoredOutput::new(asset_id.clone(), 60, OutputKind::Transfer),
+            color(transfer.txid(), 1)
+        );
+        assert_eq!(
+            ColoredOutput::new(asset_id, 40, OutputKind::Transfer),
+            color(transfer.txid(), 2)
+        );
+
+        // ancestors outside of the block must be available
+        assert!(engine.color_block(&block, &HashMap::new()).is_err());
+    }
+}
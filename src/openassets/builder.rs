@@ -0,0 +1,1200 @@
+use bitcoin::blockdata::transaction::SigHashType;
+use bitcoin::network::constants::Network;
+use bitcoin::{OutPoint, Script, Transaction, TxIn, TxOut};
+use openassets::address::ToScriptPubkey;
+use openassets::asset_id::AssetId;
+use openassets::coloring::{find_marker, OutputKind};
+use openassets::dust::DustPolicy;
+use openassets::error::Error;
+use openassets::fee::{estimate_fee, estimate_vsize};
+use openassets::marker_output::{
+    CarrierPolicy, Metadata, Payload, StandardnessError, MAX_OP_RETURN_SIZE,
+};
+use openassets::quantity::checked_sum;
+use openassets::sighash::{signing_inputs, SigningInput};
+use openassets::summary::AssetSummary;
+use openassets::unsigned::UnsignedColoredTransaction;
+use openassets::utxo::ColoredUtxo;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::error;
+use std::fmt::{self, Display, Formatter};
+
+/// The sequence number of inputs opting out of replacement and of the lock time.
+pub const SEQUENCE_FINAL: u32 = 0xFFFF_FFFF;
+
+/// The sequence number of inputs opting out of replacement and enforcing the lock time.
+pub const SEQUENCE_LOCKTIME: u32 = 0xFFFF_FFFE;
+
+/// The sequence number of inputs signaling replaceability, as defined by BIP 125.
+pub const SEQUENCE_RBF: u32 = 0xFFFF_FFFD;
+
+/// Reasons for failing to build a transaction.
+#[derive(Debug)]
+pub enum BuildError {
+    /// Asset quantities could not be computed.
+    Coloring(Error),
+    /// The inputs hold fewer units of `asset_id` than the recipients are sent.
+    InsufficientAssetUnits {
+        asset_id: AssetId,
+        required: u64,
+        available: u64,
+    },
+    /// The inputs hold fewer satoshis than the outputs and the fee require.
+    InsufficientFunds { required: u64, available: u64 },
+    /// Units of more than one asset are burned, which can not all be left unassigned.
+    MultipleBurnedAssets,
+    /// Output `index` holds less than the dust threshold of its script.
+    DustOutput {
+        index: usize,
+        value: u64,
+        threshold: u64,
+    },
+    /// The output spent to issue units of `expected` is locked by the issuance script of
+    /// `actual`.
+    AssetIdMismatch { expected: AssetId, actual: AssetId },
+    /// Output `index` would play the role `actual` rather than `expected` once colored.
+    InvalidLayout {
+        index: usize,
+        expected: OutputKind,
+        actual: OutputKind,
+    },
+    /// The script of the marker output would not be relayed by default.
+    NonStandardMarker(StandardnessError),
+    /// A signed transaction differs from the unsigned transaction by more than its signatures.
+    TransactionMismatch,
+    /// The transaction would weigh `weight` once signed, over the limit of `limit`.
+    TransactionTooLarge { weight: u64, limit: u64 },
+    /// An output would hold `quantity` units, over the maximum of `limit` allowed by the Open
+    /// Assets specification.
+    QuantityTooLarge { quantity: u64, limit: u64 },
+}
+
+impl Display for BuildError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            BuildError::Coloring(ref e) => Display::fmt(e, f),
+            BuildError::InsufficientAssetUnits {
+                ref asset_id,
+                required,
+                available,
+            } => write!(
+                f,
+                "insufficient units of {}: {} required, {} available",
+                asset_id, required, available
+            ),
+            BuildError::InsufficientFunds {
+                required,
+                available,
+            } => write!(
+                f,
+                "insufficient funds: {} satoshis required, {} available",
+                required, available
+            ),
+            BuildError::MultipleBurnedAssets => {
+                write!(f, "units of more than one asset are burned")
+            }
+            BuildError::DustOutput {
+                index,
+                value,
+                threshold,
+            } => write!(
+                f,
+                "output #{} holds {} satoshis, below the dust threshold of {}",
+                index, value, threshold
+            ),
+            BuildError::AssetIdMismatch {
+                ref expected,
+                ref actual,
+            } => write!(
+                f,
+                "issuance script of {} spent to issue units of {}",
+                actual, expected
+            ),
+            BuildError::InvalidLayout {
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "output #{} is laid out as {:?} instead of {:?}",
+                index, actual, expected
+            ),
+            BuildError::NonStandardMarker(ref e) => Display::fmt(e, f),
+            BuildError::TransactionMismatch => {
+                write!(
+                    f,
+                    "signed transaction does not match the unsigned transaction"
+                )
+            }
+            BuildError::TransactionTooLarge { weight, limit } => write!(
+                f,
+                "transaction weight of {} exceeds the limit of {}",
+                weight, limit
+            ),
+            BuildError::QuantityTooLarge { quantity, limit } => write!(
+                f,
+                "asset quantity of {} exceeds the maximum of {}",
+                quantity, limit
+            ),
+        }
+    }
+}
+
+impl error::Error for BuildError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            BuildError::Coloring(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for BuildError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::QuantityTooLarge { quantity, limit } => {
+                BuildError::QuantityTooLarge { quantity, limit }
+            }
+            Error::NonStandardMarker(e) => BuildError::NonStandardMarker(e),
+            e => BuildError::Coloring(e),
+        }
+    }
+}
+
+/// What a transaction would look like, computed without handing it out.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Preview {
+    /// The estimated virtual size of the transaction once signed.
+    pub vsize: u64,
+    /// The fee paid by the transaction, in satoshis.
+    pub fee: u64,
+    /// The quantities of each asset consumed, issued, transferred and burned.
+    pub summary: AssetSummary,
+    /// The payload of the marker output.
+    pub payload: Payload,
+}
+
+#[derive(Debug, Clone)]
+struct Recipient {
+    asset_id: AssetId,
+    quantity: u64,
+    script: Script,
+    /// Overrides the value given by the dust policy.
+    value: Option<u64>,
+}
+
+/// The lock time of a transaction and the sequence numbers of its inputs.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Sequencing {
+    pub(crate) lock_time: u32,
+    pub(crate) rbf: bool,
+    pub(crate) sequences: HashMap<OutPoint, u32>,
+}
+
+impl Sequencing {
+    /// Returns the unsigned input spending `utxo`.
+    ///
+    /// Inputs without a sequence number of their own signal replaceability if enabled, and
+    /// otherwise enforce the lock time if set.
+    pub(crate) fn txin(&self, utxo: &ColoredUtxo) -> TxIn {
+        let sequence = match self.sequences.get(&utxo.outpoint) {
+            Some(&sequence) => sequence,
+            None if self.rbf => SEQUENCE_RBF,
+            None if self.lock_time != 0 => SEQUENCE_LOCKTIME,
+            None => SEQUENCE_FINAL,
+        };
+        TxIn {
+            previous_output: utxo.outpoint,
+            script_sig: Script::new(),
+            sequence,
+            witness: vec![],
+        }
+    }
+}
+
+/// Builds transactions transferring assets from colored UTXOs to recipients.
+///
+/// Every input added to the builder is spent. Units of each asset not sent to a recipient are
+/// sent back to the change script in a colored change output per asset. The remaining
+/// satoshis once the fee is paid are sent to the bitcoin change script, which defaults to the
+/// change script, in a separate uncolored output.
+#[derive(Debug, Clone)]
+pub struct TransferBuilder {
+    inputs: Vec<ColoredUtxo>,
+    recipients: Vec<Recipient>,
+    burns: Vec<(AssetId, u64)>,
+    extra_outputs: Vec<TxOut>,
+    change_script: Script,
+    btc_change_script: Option<Script>,
+    merge_btc_change: bool,
+    fee: u64,
+    fee_rate: Option<u64>,
+    dust: DustPolicy,
+    sequencing: Sequencing,
+    metadata: Metadata,
+    max_marker_size: usize,
+}
+
+impl TransferBuilder {
+    pub fn new(change_script: Script) -> TransferBuilder {
+        TransferBuilder {
+            inputs: vec![],
+            recipients: vec![],
+            burns: vec![],
+            extra_outputs: vec![],
+            change_script,
+            btc_change_script: None,
+            merge_btc_change: false,
+            fee: 0,
+            fee_rate: None,
+            dust: DustPolicy::default(),
+            sequencing: Sequencing::default(),
+            metadata: Metadata::Empty,
+            max_marker_size: MAX_OP_RETURN_SIZE,
+        }
+    }
+
+    /// Adds a colored or uncolored UTXO to spend.
+    pub fn add_input(mut self, utxo: ColoredUtxo) -> Self {
+        self.inputs.push(utxo);
+        self
+    }
+
+    /// Sends `quantity` units of `asset_id` to `recipient`, a script, an Open Assets address or
+    /// a bitcoin address.
+    pub fn add_recipient<R: ToScriptPubkey>(
+        mut self,
+        asset_id: AssetId,
+        quantity: u64,
+        recipient: R,
+    ) -> Self {
+        self.recipients.push(Recipient {
+            asset_id,
+            quantity,
+            script: recipient.to_script_pubkey(),
+            value: None,
+        });
+        self
+    }
+
+    /// Same as `add_recipient`, giving the output `value` satoshis instead of the value of the
+    /// dust policy.
+    pub fn add_recipient_with_value<R: ToScriptPubkey>(
+        mut self,
+        asset_id: AssetId,
+        quantity: u64,
+        recipient: R,
+        value: u64,
+    ) -> Self {
+        self.recipients.push(Recipient {
+            asset_id,
+            quantity,
+            script: recipient.to_script_pubkey(),
+            value: Some(value),
+        });
+        self
+    }
+
+    /// Destroys `quantity` units of `asset_id` held by the inputs.
+    ///
+    /// The units are left out of the marker output, so they are consumed by no output. Only
+    /// the units of the last asset laid out can be left unassigned, so a transaction can only
+    /// burn units of one asset.
+    pub fn burn(mut self, asset_id: AssetId, quantity: u64) -> Self {
+        self.burns.push((asset_id, quantity));
+        self
+    }
+
+    /// Adds an uncolored output, e.g. a data output, laid out after the outputs carrying assets
+    /// so that it does not shift the quantities of the marker output.
+    pub fn add_output(mut self, output: TxOut) -> Self {
+        self.extra_outputs.push(output);
+        self
+    }
+
+    /// Sends the remaining satoshis to `script` rather than to the change script.
+    pub fn btc_change_script(mut self, script: Script) -> Self {
+        self.btc_change_script = Some(script);
+        self
+    }
+
+    /// Adds the remaining satoshis to the value of the last colored change output, if any,
+    /// rather than sending them to a separate output.
+    pub fn merge_btc_change(mut self, merge: bool) -> Self {
+        self.merge_btc_change = merge;
+        self
+    }
+
+    /// Sets the fee paid by the transaction, in satoshis.
+    pub fn fee(mut self, fee: u64) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    /// Sets the fee rate, in satoshis per virtual byte, the fee is estimated from once the
+    /// transaction is signed. Overrides the fee set by `fee`.
+    pub fn fee_rate(mut self, fee_rate: u64) -> Self {
+        self.fee_rate = Some(fee_rate);
+        self
+    }
+
+    /// Sets the policy giving the value of the outputs carrying assets.
+    pub fn dust_policy(mut self, dust: DustPolicy) -> Self {
+        self.dust = dust;
+        self
+    }
+
+    /// Sets the metadata of the marker output to `url`, following the `u=` convention of
+    /// asset definition pointers.
+    pub fn with_metadata_url(mut self, url: &str) -> Self {
+        self.metadata = Metadata::Url(url.to_string());
+        self
+    }
+
+    /// Sets the size, in bytes, the payload of the marker output must not exceed. Defaults to
+    /// `MAX_OP_RETURN_SIZE`, the limit of the relay policy of Bitcoin Core.
+    ///
+    /// Larger payloads fail with `BuildError::NonStandardMarker`, reporting the size of the
+    /// marker output script against the size of the script pushing `size` bytes.
+    pub fn max_marker_size(mut self, size: usize) -> Self {
+        self.max_marker_size = size;
+        self
+    }
+
+    /// Signals that the transaction can be replaced, as defined by BIP 125, by giving the
+    /// inputs without a sequence number of their own the sequence number `SEQUENCE_RBF`.
+    pub fn enable_rbf(mut self) -> Self {
+        self.sequencing.rbf = true;
+        self
+    }
+
+    /// Sets the lock time of the transaction. Unless replaceability is enabled, the inputs
+    /// without a sequence number of their own get the sequence number `SEQUENCE_LOCKTIME` so
+    /// that the lock time is enforced.
+    pub fn set_locktime(mut self, lock_time: u32) -> Self {
+        self.sequencing.lock_time = lock_time;
+        self
+    }
+
+    /// Sets the sequence number of the input spending `outpoint`.
+    pub fn set_sequence(mut self, outpoint: OutPoint, sequence: u32) -> Self {
+        self.sequencing.sequences.insert(outpoint, sequence);
+        self
+    }
+
+    /// Builds the unsigned transaction.
+    ///
+    /// The marker output comes first, as the transaction issues nothing. Colored inputs are
+    /// grouped by asset, and the transfer outputs of each asset, followed by its colored
+    /// change, are laid out in the same order so that each output only receives units of its
+    /// own asset. The burned asset, if any, is laid out after the others so that its burned
+    /// units are left over. Uncolored inputs come last. The extra outputs follow the outputs
+    /// carrying assets, and the bitcoin change output, omitted when it would be dust, comes
+    /// last. Outputs below the dust threshold of their script are rejected, as is a layout
+    /// which does not color as intended.
+    pub fn build(&self) -> Result<Transaction, BuildError> {
+        self.build_with_payload().map(|(tx, _)| tx)
+    }
+
+    /// Returns the size, fee and asset quantities of the transaction `build` would return,
+    /// along with the payload of its marker output.
+    pub fn preview(&self) -> Result<Preview, BuildError> {
+        let (tx, payload) = self.build_with_payload()?;
+        preview(tx, payload, &self.inputs, self.network())
+    }
+
+    /// Builds the unsigned transaction, bundled with the outputs it spends and the colors of
+    /// its outputs for an external signer.
+    pub fn build_unsigned(&self) -> Result<UnsignedColoredTransaction, BuildError> {
+        let tx = self.build()?;
+        Ok(UnsignedColoredTransaction::new(
+            tx,
+            &self.inputs,
+            self.network(),
+        )?)
+    }
+
+    /// Returns the network of the assets held by the inputs. The transaction issues nothing,
+    /// so the network does not affect its colors.
+    fn network(&self) -> Network {
+        self.inputs
+            .iter()
+            .filter_map(|u| u.colored.asset_id.as_ref())
+            .map(|asset_id| asset_id.network)
+            .next()
+            .unwrap_or(Network::Bitcoin)
+    }
+
+    /// Same as `build`, also returning the payload of the marker output.
+    pub(crate) fn build_with_payload(&self) -> Result<(Transaction, Payload), BuildError> {
+        let mut assets: Vec<&AssetId> = vec![];
+        let input_assets = self
+            .inputs
+            .iter()
+            .filter_map(|u| u.colored.asset_id.as_ref());
+        for asset_id in self
+            .recipients
+            .iter()
+            .map(|r| &r.asset_id)
+            .chain(input_assets)
+        {
+            if !assets.contains(&asset_id) {
+                assets.push(asset_id);
+            }
+        }
+        let burned = |asset_id: &AssetId| {
+            checked_sum(self.burns.iter().filter(|b| &b.0 == asset_id).map(|b| b.1))
+        };
+        let mut burned_assets: Vec<&AssetId> = vec![];
+        for asset_id in self.burns.iter().filter(|b| b.1 > 0).map(|b| &b.0) {
+            if !burned_assets.contains(&asset_id) {
+                burned_assets.push(asset_id);
+            }
+        }
+        if burned_assets.len() > 1 {
+            return Err(BuildError::MultipleBurnedAssets);
+        }
+        assets.retain(|asset_id| !burned_assets.contains(asset_id));
+        assets.extend(burned_assets);
+
+        let mut inputs: Vec<&ColoredUtxo> = vec![];
+        // quantity, script and value of each output carrying assets
+        let mut colored_outputs: Vec<(u64, &Script, u64)> = vec![];
+        let mut colored_change: Option<usize> = None;
+        for asset_id in assets {
+            let utxos: Vec<&ColoredUtxo> = self
+                .inputs
+                .iter()
+                .filter(|u| u.colored.asset_id.as_ref() == Some(asset_id))
+                .collect();
+            let recipients: Vec<&Recipient> = self
+                .recipients
+                .iter()
+                .filter(|r| &r.asset_id == asset_id)
+                .collect();
+            let available = checked_sum(utxos.iter().map(|u| u.colored.asset_quantity))?;
+            let required = checked_sum(
+                recipients
+                    .iter()
+                    .map(|r| r.quantity)
+                    .chain(Some(burned(asset_id)?)),
+            )?;
+            if required > available {
+                return Err(BuildError::InsufficientAssetUnits {
+                    asset_id: asset_id.clone(),
+                    required,
+                    available,
+                });
+            }
+            inputs.extend(utxos);
+            colored_outputs.extend(
+                recipients
+                    .iter()
+                    .map(|r| (r.quantity, &r.script, r.value.unwrap_or(self.dust.value))),
+            );
+            if available > required {
+                colored_outputs.push((available - required, &self.change_script, self.dust.value));
+                // the marker output comes first
+                colored_change = Some(colored_outputs.len());
+            }
+        }
+        inputs.extend(self.inputs.iter().filter(|u| u.colored.asset_id.is_none()));
+
+        let payload = Payload::new(
+            colored_outputs.iter().map(|o| o.0).collect(),
+            self.metadata.clone(),
+        );
+        let mut output = vec![marker_txout(&payload, self.max_marker_size)?];
+        output.extend(colored_outputs.iter().map(|&(_, script, value)| TxOut {
+            value,
+            script_pubkey: script.clone(),
+        }));
+        output.extend(self.extra_outputs.iter().cloned());
+        for (index, o) in output.iter().enumerate().skip(1) {
+            if self.dust.is_dust(o) {
+                return Err(BuildError::DustOutput {
+                    index,
+                    value: o.value,
+                    threshold: self.dust.threshold(&o.script_pubkey),
+                });
+            }
+        }
+        let mut tx = Transaction {
+            version: 1,
+            lock_time: self.sequencing.lock_time,
+            input: inputs.iter().map(|u| self.sequencing.txin(u)).collect(),
+            output,
+        };
+
+        let prev_scripts: Vec<&Script> = inputs.iter().map(|u| &u.txout.script_pubkey).collect();
+        let available = checked_sum(inputs.iter().map(|u| u.txout.value))?;
+        let fee = |tx: &Transaction| self.estimate_fee(tx, &prev_scripts);
+        match (self.merge_btc_change, colored_change) {
+            (true, Some(index)) => tx.output[index].value += remaining(&tx, available, fee(&tx))?,
+            _ => {
+                let btc_change_script = self
+                    .btc_change_script
+                    .as_ref()
+                    .unwrap_or(&self.change_script);
+                push_change(&mut tx, available, btc_change_script, &self.dust, fee)?;
+            }
+        }
+
+        let mut layout = vec![OutputKind::Marker];
+        layout.extend(colored_outputs.iter().map(|o| match o.0 {
+            0 => OutputKind::Uncolored,
+            _ => OutputKind::Transfer,
+        }));
+        validate_layout(&tx, &layout)?;
+        Ok((tx, payload))
+    }
+
+    /// Returns, for each input of `tx` built by this builder, what its signer needs to know to
+    /// sign with `sighash_type`.
+    pub fn signing_inputs(
+        &self,
+        tx: &Transaction,
+        sighash_type: SigHashType,
+    ) -> Result<Vec<SigningInput>, BuildError> {
+        Ok(signing_inputs(tx, &self.inputs, sighash_type)?)
+    }
+
+    /// Returns the fee of `tx`, estimated from the fee rate if set.
+    fn estimate_fee(&self, tx: &Transaction, prev_scripts: &[&Script]) -> u64 {
+        match self.fee_rate {
+            Some(fee_rate) => estimate_fee(tx, prev_scripts, fee_rate),
+            None => self.fee,
+        }
+    }
+}
+
+/// Returns the preview of `tx`, built with `payload` from `utxos`.
+pub(crate) fn preview(
+    tx: Transaction,
+    payload: Payload,
+    utxos: &[ColoredUtxo],
+    network: Network,
+) -> Result<Preview, BuildError> {
+    let unsigned = UnsignedColoredTransaction::new(tx, utxos, network)?;
+    let tx = &unsigned.tx;
+    let available = checked_sum(unsigned.inputs.iter().map(|u| u.txout.value))?;
+    Ok(Preview {
+        vsize: estimate_vsize(tx, &unsigned.prev_scripts()),
+        fee: remaining(tx, available, 0)?,
+        summary: AssetSummary::from_transaction(
+            tx,
+            &unsigned.input_colors(),
+            &unsigned.output_colors,
+        )?,
+        payload,
+    })
+}
+
+/// Returns the role each output of `tx` plays once colored, as laid out by the marker output:
+/// the issuance outputs, the marker output, the transfer outputs, then the uncolored outputs.
+///
+/// Outputs with a zero quantity, or past the quantities of the marker output, are uncolored.
+/// Every output is uncolored if there is no marker output.
+pub fn output_layout(tx: &Transaction) -> Vec<OutputKind> {
+    let mut layout = vec![OutputKind::Uncolored; tx.output.len()];
+    if let Some((marker_index, payload)) = find_marker(tx) {
+        let quantity = |i: usize| payload.quantities.get(i).cloned().unwrap_or(0);
+        for (i, kind) in layout.iter_mut().enumerate() {
+            *kind = match i.cmp(&marker_index) {
+                Ordering::Equal => OutputKind::Marker,
+                Ordering::Less if quantity(i) > 0 => OutputKind::Issuance,
+                Ordering::Greater if quantity(i - 1) > 0 => OutputKind::Transfer,
+                _ => OutputKind::Uncolored,
+            };
+        }
+    }
+    layout
+}
+
+/// Checks that the outputs of `tx` play the roles in `layout` once colored, outputs past the
+/// end of `layout` being uncolored.
+pub(crate) fn validate_layout(tx: &Transaction, layout: &[OutputKind]) -> Result<(), BuildError> {
+    let actual = output_layout(tx);
+    let expected = layout
+        .iter()
+        .cloned()
+        .chain(std::iter::repeat(OutputKind::Uncolored));
+    for (index, (actual, expected)) in actual.into_iter().zip(expected).enumerate() {
+        if actual != expected {
+            return Err(BuildError::InvalidLayout {
+                index,
+                expected,
+                actual,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Returns the satoshis left from `available` once the outputs of `tx` and `fee` are paid.
+pub(crate) fn remaining(tx: &Transaction, available: u64, fee: u64) -> Result<u64, BuildError> {
+    let outputs_value = checked_sum(tx.output.iter().map(|o| o.value))?;
+    let required = checked_sum(vec![outputs_value, fee])?;
+    if required > available {
+        return Err(BuildError::InsufficientFunds {
+            required,
+            available,
+        });
+    }
+    Ok(available - required)
+}
+
+/// Appends to `tx` an output sending to `script` the satoshis left from `available` once the
+/// outputs and the fee are paid, unless they would be dust and are left to the fee.
+pub(crate) fn push_change<F: Fn(&Transaction) -> u64>(
+    tx: &mut Transaction,
+    available: u64,
+    script: &Script,
+    dust: &DustPolicy,
+    fee: F,
+) -> Result<(), BuildError> {
+    remaining(tx, available, fee(tx))?;
+    tx.output.push(TxOut {
+        value: 0,
+        script_pubkey: script.clone(),
+    });
+    match remaining(tx, available, fee(tx)) {
+        Ok(change) if change >= dust.threshold(script) => {
+            tx.output.last_mut().unwrap().value = change;
+        }
+        _ => {
+            tx.output.pop();
+        }
+    }
+    Ok(())
+}
+
+/// Returns the marker output carrying `payload`, failing if a quantity exceeds
+/// `MAX_QUANTITY` or if the payload is longer than `max_size` bytes.
+pub(crate) fn marker_txout(payload: &Payload, max_size: usize) -> Result<TxOut, BuildError> {
+    let carrier = CarrierPolicy::Standard;
+    Ok(payload.to_standard_txout_with(&carrier, carrier.script_len(max_size))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::blockdata::transaction::SigHashType;
+    use bitcoin::network::constants::Network;
+    use bitcoin::{OutPoint, Script, Transaction, TxOut};
+    use openassets::address::OAAddressConverter;
+    use openassets::asset_id::AssetId;
+    use openassets::builder::{
+        output_layout, validate_layout, BuildError, TransferBuilder, SEQUENCE_FINAL,
+        SEQUENCE_LOCKTIME, SEQUENCE_RBF,
+    };
+    use openassets::burn::burned_quantity_per_asset;
+    use openassets::coloring::{ColoredOutput, ColoringEngine, ColoringPolicy, OutputKind};
+    use openassets::dust::DustPolicy;
+    use openassets::fee::{estimate_fee, estimate_vsize};
+    use openassets::marker_output::{
+        Metadata, StandardnessError, TxOutExt, MAX_OP_RETURN_RELAY, MAX_QUANTITY,
+    };
+    use openassets::test_helpers::{script, utxo};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_build() {
+        let change = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let alice = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+        let bob = script("a914f9d499817e88ef7b10a88673296c6d6df2f4292d87");
+        let asset_a = AssetId::new(&alice, Network::Bitcoin);
+        let asset_b = AssetId::new(&bob, Network::Bitcoin);
+        let inputs = [
+            utxo(
+                0,
+                600,
+                ColoredOutput::new(asset_b.clone(), 5, OutputKind::Transfer),
+            ),
+            utxo(
+                1,
+                600,
+                ColoredOutput::new(asset_a.clone(), 40, OutputKind::Transfer),
+            ),
+            utxo(2, 10_000, ColoredOutput::uncolored()),
+            utxo(
+                3,
+                600,
+                ColoredOutput::new(asset_a.clone(), 20, OutputKind::Issuance),
+            ),
+        ];
+        let builder = inputs
+            .iter()
+            .fold(TransferBuilder::new(change.clone()), |b, u| {
+                b.add_input(u.clone())
+            })
+            .add_recipient(asset_a.clone(), 30, alice.clone())
+            .add_recipient(asset_b.clone(), 5, bob.clone())
+            .add_recipient(asset_a.clone(), 20, bob.clone())
+            .fee(1_000);
+        let tx = builder.build().unwrap();
+
+        let vouts: Vec<u32> = tx.input.iter().map(|i| i.previous_output.vout).collect();
+        assert_eq!(vec![1, 3, 0, 2], vouts);
+        assert_eq!(
+            vec![30, 20, 10, 5],
+            tx.output[0].get_oa_payload().unwrap().quantities
+        );
+        let scripts: Vec<&Script> = tx.output[1..].iter().map(|o| &o.script_pubkey).collect();
+        assert_eq!(vec![&alice, &bob, &change, &bob, &change], scripts);
+        assert_eq!(11_800 - 4 * 600 - 1_000, tx.output[5].value);
+
+        let input_colors: Vec<ColoredOutput> = [1, 3, 0, 2]
+            .iter()
+            .map(|&i| inputs[i].colored.clone())
+            .collect();
+        let colors = ColoringEngine::with_policy(Network::Bitcoin, ColoringPolicy::Strict)
+            .color_transaction(&tx, &Script::new(), &input_colors)
+            .unwrap();
+        assert_eq!(
+            vec![
+                ColoredOutput::marker(),
+                ColoredOutput::new(asset_a.clone(), 30, OutputKind::Transfer),
+                ColoredOutput::new(asset_a.clone(), 20, OutputKind::Transfer),
+                ColoredOutput::new(asset_a.clone(), 10, OutputKind::Transfer),
+                ColoredOutput::new(asset_b.clone(), 5, OutputKind::Transfer),
+                ColoredOutput::uncolored(),
+            ],
+            colors
+        );
+
+        match builder
+            .clone()
+            .add_recipient(asset_b.clone(), 1, bob.clone())
+            .build()
+        {
+            Err(BuildError::InsufficientAssetUnits {
+                asset_id,
+                required: 6,
+                available: 5,
+            }) => assert_eq!(asset_b, asset_id),
+            _ => panic!("expected InsufficientAssetUnits"),
+        }
+        match builder.fee(10_000).build() {
+            Err(BuildError::InsufficientFunds {
+                required: 12_400,
+                available: 11_800,
+            }) => {}
+            _ => panic!("expected InsufficientFunds"),
+        }
+    }
+
+    #[test]
+    fn test_burn() {
+        let change = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let alice = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+        let bob = script("a914f9d499817e88ef7b10a88673296c6d6df2f4292d87");
+        let asset_a = AssetId::new(&alice, Network::Bitcoin);
+        let asset_b = AssetId::new(&bob, Network::Bitcoin);
+        let builder = TransferBuilder::new(change.clone())
+            .add_input(utxo(
+                0,
+                600,
+                ColoredOutput::new(asset_a.clone(), 40, OutputKind::Transfer),
+            ))
+            .add_input(utxo(
+                1,
+                600,
+                ColoredOutput::new(asset_b.clone(), 5, OutputKind::Transfer),
+            ))
+            .add_input(utxo(2, 10_000, ColoredOutput::uncolored()))
+            .add_recipient(asset_a.clone(), 20, alice.clone())
+            .add_recipient(asset_b.clone(), 5, bob.clone())
+            .burn(asset_a.clone(), 15);
+        let tx = builder.build().unwrap();
+
+        // the burned asset is laid out last
+        let vouts: Vec<u32> = tx.input.iter().map(|i| i.previous_output.vout).collect();
+        assert_eq!(vec![1, 0, 2], vouts);
+        assert_eq!(
+            vec![5, 20, 5],
+            tx.output[0].get_oa_payload().unwrap().quantities
+        );
+        let input_colors = vec![
+            ColoredOutput::new(asset_b.clone(), 5, OutputKind::Transfer),
+            ColoredOutput::new(asset_a.clone(), 40, OutputKind::Transfer),
+            ColoredOutput::uncolored(),
+        ];
+        let burned = burned_quantity_per_asset(&tx, &input_colors).unwrap();
+        assert_eq!(1, burned.len());
+        assert_eq!(15, burned[&asset_a]);
+
+        match builder.clone().burn(asset_b.clone(), 1).build() {
+            Err(BuildError::MultipleBurnedAssets) => {}
+            _ => panic!("expected MultipleBurnedAssets"),
+        }
+        match builder.burn(asset_a, 10).build() {
+            Err(BuildError::InsufficientAssetUnits {
+                required: 45,
+                available: 40,
+                ..
+            }) => {}
+            _ => panic!("expected InsufficientAssetUnits"),
+        }
+    }
+
+    #[test]
+    fn test_dust_policy() {
+        let change = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let alice = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+        let asset_id = AssetId::new(&alice, Network::Bitcoin);
+        let builder = TransferBuilder::new(change.clone())
+            .add_input(utxo(
+                0,
+                600,
+                ColoredOutput::new(asset_id.clone(), 10, OutputKind::Transfer),
+            ))
+            .add_input(utxo(1, 2_000, ColoredOutput::uncolored()))
+            .dust_policy(DustPolicy::new(700));
+        let tx = builder
+            .clone()
+            .add_recipient_with_value(asset_id.clone(), 4, alice.clone(), 1_000)
+            .build()
+            .unwrap();
+        let values: Vec<u64> = tx.output.iter().map(|o| o.value).collect();
+        assert_eq!(vec![0, 1_000, 700, 900], values);
+
+        // the bitcoin change is dust
+        let tx = builder
+            .clone()
+            .add_recipient(asset_id.clone(), 4, alice.clone())
+            .fee(1_000)
+            .build()
+            .unwrap();
+        assert_eq!(3, tx.output.len());
+
+        match builder
+            .add_recipient_with_value(asset_id, 4, alice, 500)
+            .build()
+        {
+            Err(BuildError::DustOutput {
+                index: 1,
+                value: 500,
+                threshold: 546,
+            }) => {}
+            _ => panic!("expected DustOutput"),
+        }
+    }
+
+    #[test]
+    fn test_fee_rate() {
+        let change = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let alice = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+        let asset_id = AssetId::new(&alice, Network::Bitcoin);
+        let builder = TransferBuilder::new(change.clone())
+            .add_input(utxo(
+                0,
+                600,
+                ColoredOutput::new(asset_id.clone(), 10, OutputKind::Transfer),
+            ))
+            .add_input(utxo(1, 10_000, ColoredOutput::uncolored()))
+            .add_recipient(asset_id.clone(), 4, alice.clone())
+            .fee_rate(10);
+        let tx = builder.build().unwrap();
+        assert_eq!(4, tx.output.len());
+        let fee = 10_600 - tx.output.iter().map(|o| o.value).sum::<u64>();
+        let empty = Script::new();
+        assert_eq!(estimate_fee(&tx, &[&empty, &empty], 10), fee);
+        // 2 signed inputs of 149 bytes, a marker output of 19 bytes and 3 outputs of 34 bytes
+        assert_eq!(10 * (10 + 2 * 149 + 19 + 3 * 34), fee);
+
+        // the change is left to the fee when it would be dust
+        let tx = builder.clone().fee_rate(21).build().unwrap();
+        assert_eq!(3, tx.output.len());
+
+        // the P2WPKH input weighs 4 * 41 weight units, 2 weight units of segwit marker and
+        // flag and 109 weight units of witness, for a total of 4 * 321 + 111 weight units
+        let p2wpkh = script("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1");
+        let mut funding = utxo(1, 10_000, ColoredOutput::uncolored());
+        funding.txout.script_pubkey = p2wpkh;
+        let tx = TransferBuilder::new(change)
+            .add_input(utxo(
+                0,
+                600,
+                ColoredOutput::new(asset_id.clone(), 10, OutputKind::Transfer),
+            ))
+            .add_input(funding)
+            .add_recipient(asset_id, 4, alice)
+            .fee_rate(10)
+            .build()
+            .unwrap();
+        let fee = 10_600 - tx.output.iter().map(|o| o.value).sum::<u64>();
+        assert_eq!(10 * 349, fee);
+    }
+
+    #[test]
+    fn test_btc_change() {
+        let change = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let btc_change = script("a914f9d499817e88ef7b10a88673296c6d6df2f4292d87");
+        let alice = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+        let asset_id = AssetId::new(&alice, Network::Bitcoin);
+        let builder = TransferBuilder::new(change.clone())
+            .add_input(utxo(
+                0,
+                600,
+                ColoredOutput::new(asset_id.clone(), 10, OutputKind::Transfer),
+            ))
+            .add_input(utxo(1, 10_000, ColoredOutput::uncolored()))
+            .add_recipient(asset_id.clone(), 4, alice.clone())
+            .btc_change_script(btc_change.clone())
+            .fee(1_000);
+        let tx = builder.build().unwrap();
+        let outputs: Vec<(u64, &Script)> = tx
+            .output
+            .iter()
+            .map(|o| (o.value, &o.script_pubkey))
+            .collect();
+        assert_eq!(
+            vec![(600, &alice), (600, &change), (8_400, &btc_change)],
+            outputs[1..].to_vec()
+        );
+
+        let tx = builder.clone().merge_btc_change(true).build().unwrap();
+        let outputs: Vec<(u64, &Script)> = tx
+            .output
+            .iter()
+            .map(|o| (o.value, &o.script_pubkey))
+            .collect();
+        assert_eq!(vec![(600, &alice), (9_000, &change)], outputs[1..].to_vec());
+        assert_eq!(
+            vec![4, 6],
+            tx.output[0].get_oa_payload().unwrap().quantities
+        );
+
+        // without colored change, the bitcoin change has its own output
+        let tx = builder
+            .add_recipient(asset_id, 6, alice)
+            .merge_btc_change(true)
+            .build()
+            .unwrap();
+        assert_eq!(4, tx.output.len());
+        assert_eq!(btc_change, tx.output[3].script_pubkey);
+    }
+
+    #[test]
+    fn test_output_layout() {
+        let change = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let alice = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+        let asset_id = AssetId::new(&alice, Network::Bitcoin);
+        let data = TxOut {
+            value: 0,
+            script_pubkey: script("6a0568656c6c6f"),
+        };
+        let tx = TransferBuilder::new(change)
+            .add_input(utxo(
+                0,
+                10_000,
+                ColoredOutput::new(asset_id.clone(), 10, OutputKind::Transfer),
+            ))
+            .add_output(data.clone())
+            .add_recipient(asset_id, 4, alice)
+            .build()
+            .unwrap();
+        assert_eq!(data, tx.output[3]);
+        assert_eq!(
+            vec![
+                OutputKind::Marker,
+                OutputKind::Transfer,
+                OutputKind::Transfer,
+                OutputKind::Uncolored,
+                OutputKind::Uncolored,
+            ],
+            output_layout(&tx)
+        );
+
+        // a marker output in the issuance section
+        let mut tx = tx;
+        tx.output.insert(0, tx.output[1].clone());
+        assert_eq!(
+            vec![
+                OutputKind::Issuance,
+                OutputKind::Marker,
+                OutputKind::Transfer,
+                OutputKind::Uncolored,
+                OutputKind::Uncolored,
+                OutputKind::Uncolored,
+            ],
+            output_layout(&tx)
+        );
+        match validate_layout(&tx, &[OutputKind::Uncolored, OutputKind::Marker]) {
+            Err(BuildError::InvalidLayout {
+                index: 0,
+                expected: OutputKind::Uncolored,
+                actual: OutputKind::Issuance,
+            }) => {}
+            _ => panic!("expected InvalidLayout"),
+        }
+    }
+
+    #[test]
+    fn test_sequencing() {
+        let change = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let alice = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+        let asset_id = AssetId::new(&alice, Network::Bitcoin);
+        let colored = utxo(
+            0,
+            600,
+            ColoredOutput::new(asset_id.clone(), 10, OutputKind::Transfer),
+        );
+        let funding = utxo(1, 10_000, ColoredOutput::uncolored());
+        let builder = TransferBuilder::new(change)
+            .add_input(colored)
+            .add_input(funding.clone())
+            .add_recipient(asset_id, 10, alice)
+            .fee(1_000);
+        let sequences =
+            |tx: &Transaction| -> Vec<u32> { tx.input.iter().map(|i| i.sequence).collect() };
+
+        let tx = builder.build().unwrap();
+        assert_eq!(0, tx.lock_time);
+        assert_eq!(vec![SEQUENCE_FINAL, SEQUENCE_FINAL], sequences(&tx));
+
+        let tx = builder.clone().set_locktime(500_000).build().unwrap();
+        assert_eq!(500_000, tx.lock_time);
+        assert_eq!(vec![SEQUENCE_LOCKTIME, SEQUENCE_LOCKTIME], sequences(&tx));
+
+        let tx = builder
+            .clone()
+            .enable_rbf()
+            .set_locktime(500_000)
+            .set_sequence(funding.outpoint, 144)
+            .build()
+            .unwrap();
+        assert_eq!(vec![SEQUENCE_RBF, 144], sequences(&tx));
+
+        // the builder knows the outputs spent by the transaction it built
+        let signing = builder.signing_inputs(&tx, SigHashType::All).unwrap();
+        let outpoints: Vec<OutPoint> = signing.iter().map(|i| i.outpoint).collect();
+        let prev_outpoints: Vec<OutPoint> = tx.input.iter().map(|i| i.previous_output).collect();
+        assert_eq!(prev_outpoints, outpoints);
+        assert_eq!(
+            vec![600, 10_000],
+            signing.iter().map(|i| i.value).collect::<Vec<u64>>()
+        );
+    }
+
+    #[test]
+    fn test_metadata_url() {
+        let change = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let alice = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+        let asset_id = AssetId::new(&alice, Network::Bitcoin);
+        let builder = TransferBuilder::new(change)
+            .add_input(utxo(
+                0,
+                10_000,
+                ColoredOutput::new(asset_id.clone(), 10, OutputKind::Transfer),
+            ))
+            .add_recipient(asset_id, 4, alice)
+            .with_metadata_url("https://cpr.sm/5YgSU1Pg-q");
+        let tx = builder.build().unwrap();
+        match tx.output[0].get_oa_payload().unwrap().metadata {
+            Metadata::Url(ref url) => assert_eq!("https://cpr.sm/5YgSU1Pg-q", url),
+            ref m => panic!("expected Url, got {:?}", m),
+        }
+
+        // 4 bytes of marker and version, 3 bytes of quantities and 28 bytes of metadata, pushed
+        // after OP_RETURN
+        match builder.clone().max_marker_size(34).build() {
+            Err(BuildError::NonStandardMarker(StandardnessError {
+                script_size: 37,
+                limit: 36,
+            })) => {}
+            _ => panic!("expected NonStandardMarker"),
+        }
+        let url = format!("https://example.com/{}", "a".repeat(60));
+        match builder.clone().with_metadata_url(&url).build() {
+            Err(BuildError::NonStandardMarker(StandardnessError {
+                limit: MAX_OP_RETURN_RELAY,
+                ..
+            })) => {}
+            _ => panic!("expected NonStandardMarker"),
+        }
+        assert!(builder
+            .with_metadata_url(&url)
+            .max_marker_size(100)
+            .build()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_max_quantity() {
+        let change = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let alice = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+        let asset_id = AssetId::new(&alice, Network::Bitcoin);
+        let builder = TransferBuilder::new(change)
+            .add_input(utxo(
+                0,
+                10_000,
+                ColoredOutput::new(asset_id.clone(), u64::MAX, OutputKind::Transfer),
+            ))
+            .fee(1_000);
+        match builder
+            .clone()
+            .add_recipient(asset_id.clone(), 1, &alice)
+            .build()
+        {
+            Err(BuildError::QuantityTooLarge {
+                quantity,
+                limit: MAX_QUANTITY,
+            }) => assert_eq!(u64::MAX - 1, quantity),
+            _ => panic!("expected QuantityTooLarge"),
+        }
+        assert!(builder
+            .add_recipient(asset_id.clone(), MAX_QUANTITY, &alice)
+            .add_recipient(asset_id, MAX_QUANTITY, &alice)
+            .build()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_recipient_address() {
+        let change = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let addr = bitcoin::Address::from_str("1F2AQr6oqNtcJQ6p9SiCLQTrHuM9en44H8").unwrap();
+        let oa_addr = addr.to_oa_address().unwrap();
+        let asset_id = AssetId::new(&change, Network::Bitcoin);
+        let tx = TransferBuilder::new(change)
+            .add_input(utxo(
+                0,
+                10_000,
+                ColoredOutput::new(asset_id.clone(), 10, OutputKind::Transfer),
+            ))
+            .add_recipient(asset_id.clone(), 4, &oa_addr)
+            .add_recipient(asset_id, 6, addr.clone())
+            .build()
+            .unwrap();
+        assert_eq!(addr.script_pubkey(), tx.output[1].script_pubkey);
+        assert_eq!(addr.script_pubkey(), tx.output[2].script_pubkey);
+    }
+
+    #[test]
+    fn test_preview() {
+        let change = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let alice = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+        let asset_id = AssetId::new(&alice, Network::Bitcoin);
+        let builder = TransferBuilder::new(change)
+            .add_input(utxo(
+                0,
+                600,
+                ColoredOutput::new(asset_id.clone(), 10, OutputKind::Transfer),
+            ))
+            .add_input(utxo(1, 10_000, ColoredOutput::uncolored()))
+            .add_recipient(asset_id.clone(), 4, alice)
+            .burn(asset_id.clone(), 1)
+            .fee_rate(10);
+        let preview = builder.preview().unwrap();
+        let tx = builder.build().unwrap();
+
+        let empty = Script::new();
+        assert_eq!(estimate_vsize(&tx, &[&empty, &empty]), preview.vsize);
+        assert_eq!(10 * preview.vsize, preview.fee);
+        assert_eq!(tx.output[0].get_oa_payload().unwrap(), preview.payload);
+        assert_eq!(vec![4, 5], preview.payload.quantities);
+        let flow = preview.summary.get(&asset_id).unwrap();
+        assert_eq!((10, 9, 1), (flow.consumed, flow.transferred, flow.burned));
+    }
+}
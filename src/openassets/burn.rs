@@ -0,0 +1,100 @@
+use bitcoin::network::constants::Network;
+use bitcoin::{Script, Transaction, TxOut};
+use openassets::asset_id::AssetId;
+use openassets::coloring::{ColoredOutput, ColoringEngine, OutputKind};
+use openassets::error::Error;
+use std::collections::HashMap;
+
+/// Returns true if `output` carries assets to a provably unspendable script, e.g. OP_RETURN.
+pub fn is_burn(output: &TxOut, color: &ColoredOutput) -> bool {
+    color.is_colored()
+        && (output.script_pubkey.is_op_return() || output.script_pubkey.is_provably_unspendable())
+}
+
+/// Returns the quantity of each asset held by the inputs of `tx` which does not reach a
+/// spendable output.
+///
+/// This covers units sent to unspendable outputs as well as input units left unassigned by
+/// the marker, or by a transaction which is not a valid Open Assets transaction. Units issued
+/// by `tx` itself are not included.
+pub fn burned_quantity_per_asset(
+    tx: &Transaction,
+    input_colors: &[ColoredOutput],
+) -> Result<HashMap<AssetId, u64>, Error> {
+    let mut burned: HashMap<AssetId, u64> = HashMap::new();
+    if tx.is_coin_base() {
+        return Ok(burned);
+    }
+    for input in input_colors.iter() {
+        if let Some(ref asset_id) = input.asset_id {
+            let total = burned.entry(asset_id.clone()).or_insert(0);
+            *total = total
+                .checked_add(input.asset_quantity)
+                .ok_or(Error::QuantityOverflow)?;
+        }
+    }
+
+    // the issuance asset id is irrelevant, only transfer outputs are subtracted
+    let engine = ColoringEngine::new(Network::Bitcoin);
+    let outputs = engine.color_transaction(tx, &Script::new(), input_colors)?;
+    for (output, color) in tx.output.iter().zip(outputs.iter()) {
+        if color.kind != OutputKind::Transfer || is_burn(output, color) {
+            continue;
+        }
+        if let Some(ref asset_id) = color.asset_id {
+            if let Some(total) = burned.get_mut(asset_id) {
+                *total -= color.asset_quantity;
+            }
+        }
+    }
+    burned.retain(|_, &mut quantity| quantity > 0);
+    Ok(burned)
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::network::constants::Network;
+    use openassets::asset_id::AssetId;
+    use openassets::burn::{burned_quantity_per_asset, is_burn};
+    use openassets::coloring::{ColoredOutput, OutputKind};
+    use openassets::test_helpers::{build_tx, script};
+
+    #[test]
+    fn test_burned_quantity_per_asset() {
+        let p2pkh = "76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac";
+        let asset_a = AssetId::new(
+            &script("76a914010966776006953d5567439e5e39f86a0d273bee88ac"),
+            Network::Bitcoin,
+        );
+        let asset_b = AssetId::new(
+            &script("a914f9d499817e88ef7b10a88673296c6d6df2f4292d87"),
+            Network::Bitcoin,
+        );
+        let inputs = vec![
+            ColoredOutput::new(asset_a.clone(), 5, OutputKind::Transfer),
+            ColoredOutput::new(asset_b.clone(), 3, OutputKind::Transfer),
+        ];
+
+        // 4 of A to OP_RETURN, 1 of A and 2 of B transferred, 1 of B left over
+        let tx = build_tx(2, vec!["6a094f4101000304010200", "6a", p2pkh, p2pkh]);
+        assert!(is_burn(
+            &tx.output[1],
+            &ColoredOutput::new(asset_a.clone(), 4, OutputKind::Transfer)
+        ));
+        assert!(!is_burn(&tx.output[1], &ColoredOutput::uncolored()));
+        let burned = burned_quantity_per_asset(&tx, &inputs).unwrap();
+        assert_eq!(2, burned.len());
+        assert_eq!(4, burned[&asset_a]);
+        assert_eq!(1, burned[&asset_b]);
+
+        // transactions without a marker output burn every input unit
+        let tx = build_tx(2, vec![p2pkh]);
+        let burned = burned_quantity_per_asset(&tx, &inputs).unwrap();
+        assert_eq!(5, burned[&asset_a]);
+        assert_eq!(3, burned[&asset_b]);
+
+        // nothing burned
+        let tx = build_tx(2, vec!["6a084f41010002050300", p2pkh, p2pkh]);
+        assert!(burned_quantity_per_asset(&tx, &inputs).unwrap().is_empty());
+    }
+}
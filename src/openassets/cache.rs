@@ -0,0 +1,144 @@
+use bitcoin::{OutPoint, Transaction};
+use lru::LruCache;
+use openassets::coloring::{ColorCache, ColoredOutput, ColoringEngine, OutputProvider};
+use openassets::error::Error;
+use std::num::NonZeroUsize;
+
+impl ColorCache for LruCache<OutPoint, ColoredOutput> {
+    fn get(&mut self, outpoint: &OutPoint) -> Option<ColoredOutput> {
+        LruCache::get(self, outpoint).cloned()
+    }
+
+    fn insert(&mut self, outpoint: OutPoint, color: ColoredOutput) {
+        self.put(outpoint, color);
+    }
+}
+
+/// A `ColoringEngine` which keeps the most recently resolved output colors in an LRU cache,
+/// so coloring related transactions (e.g. a whole block) does not re-walk the same ancestry.
+pub struct CachedColoringEngine {
+    pub engine: ColoringEngine,
+    cache: LruCache<OutPoint, ColoredOutput>,
+}
+
+impl CachedColoringEngine {
+    /// Creates an engine caching the colors of up to `capacity` outputs.
+    pub fn new(engine: ColoringEngine, capacity: NonZeroUsize) -> CachedColoringEngine {
+        CachedColoringEngine {
+            engine,
+            cache: LruCache::new(capacity),
+        }
+    }
+
+    pub fn resolve_transaction<P: OutputProvider>(
+        &mut self,
+        tx: &Transaction,
+        provider: &P,
+    ) -> Result<Vec<ColoredOutput>, Error> {
+        self.engine
+            .resolve_transaction_with_cache(tx, provider, &mut self.cache)
+    }
+
+    pub fn resolve_output<P: OutputProvider>(
+        &mut self,
+        outpoint: &OutPoint,
+        provider: &P,
+    ) -> Result<ColoredOutput, Error> {
+        self.engine
+            .resolve_output_with_cache(outpoint, provider, &mut self.cache)
+    }
+
+    /// Returns the number of cached output colors.
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::network::constants::Network;
+    use bitcoin::{OutPoint, Transaction};
+    use bitcoin_hashes::sha256d;
+    use openassets::asset_id::AssetId;
+    use openassets::cache::CachedColoringEngine;
+    use openassets::coloring::{ColoredOutput, ColoringEngine, OutputKind, OutputProvider};
+    use openassets::error::Error;
+    use openassets::test_helpers::{build_spending_tx, script};
+    use std::cell::Cell;
+    use std::collections::HashMap;
+    use std::num::NonZeroUsize;
+
+    struct CountingProvider {
+        txs: HashMap<sha256d::Hash, Transaction>,
+        calls: Cell<usize>,
+    }
+
+    impl OutputProvider for CountingProvider {
+        fn get_transaction(&self, txid: &sha256d::Hash) -> Result<Transaction, Error> {
+            self.calls.set(self.calls.get() + 1);
+            self.txs.get_transaction(txid)
+        }
+    }
+
+    #[test]
+    fn test_cached_coloring_engine() {
+        let issuer = "76a914010966776006953d5567439e5e39f86a0d273bee88ac";
+        let p2pkh = "76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac";
+        let funding = build_spending_tx(vec![OutPoint::default()], vec![issuer]);
+        let issuance = build_spending_tx(
+            vec![OutPoint {
+                txid: funding.txid(),
+                vout: 0,
+            }],
+            vec![p2pkh, "6a074f410100016400"],
+        );
+        let transfer = build_spending_tx(
+            vec![OutPoint {
+                txid: issuance.txid(),
+                vout: 0,
+            }],
+            vec!["6a074f410100016400", p2pkh],
+        );
+        let mut txs = HashMap::new();
+        txs.insert(funding.txid(), funding);
+        txs.insert(issuance.txid(), issuance);
+        let provider = CountingProvider {
+            txs,
+            calls: Cell::new(0),
+        };
+
+        let mut engine = CachedColoringEngine::new(
+            ColoringEngine::new(Network::Bitcoin),
+            NonZeroUsize::new(10).unwrap(),
+        );
+        let asset_id = AssetId::new(&script(issuer), Network::Bitcoin);
+        let expected = vec![
+            ColoredOutput::marker(),
+            ColoredOutput::new(asset_id, 100, OutputKind::Transfer),
+        ];
+        assert_eq!(
+            expected,
+            engine.resolve_transaction(&transfer, &provider).unwrap()
+        );
+        let calls = provider.calls.get();
+        assert_eq!(4, engine.len());
+
+        // the colors of the inputs are served from the cache
+        assert_eq!(
+            expected,
+            engine.resolve_transaction(&transfer, &provider).unwrap()
+        );
+        assert_eq!(1, provider.calls.get() - calls);
+
+        engine.clear();
+        assert!(engine.is_empty());
+    }
+}
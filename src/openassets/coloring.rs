@@ -0,0 +1,1045 @@
+use bitcoin::network::constants::Network;
+use bitcoin::{OutPoint, Script, Transaction, TxOut};
+use bitcoin_hashes::sha256d;
+use openassets::asset_id::AssetId;
+use openassets::error::Error;
+use openassets::marker_output::Payload;
+use openassets::quantity::checked_sum;
+use openassets::trace::{record, ColoringTrace, TraceStep};
+use openassets::transaction::TransactionExt;
+use std::collections::{HashMap, HashSet};
+
+/// The role an output plays in an Open Assets transaction.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub enum OutputKind {
+    /// An output placed before the marker output which issues new units.
+    Issuance,
+    /// An output placed after the marker output which receives transferred units.
+    Transfer,
+    /// The marker output itself.
+    Marker,
+    /// An output which carries no asset.
+    Uncolored,
+}
+
+/// The asset carried by a transaction output.
+#[derive(PartialEq, Eq, Debug, Clone, Hash)]
+pub struct ColoredOutput {
+    pub asset_id: Option<AssetId>,
+    pub asset_quantity: u64,
+    pub kind: OutputKind,
+}
+
+impl ColoredOutput {
+    pub fn new(asset_id: AssetId, asset_quantity: u64, kind: OutputKind) -> ColoredOutput {
+        ColoredOutput {
+            asset_id: Some(asset_id),
+            asset_quantity,
+            kind,
+        }
+    }
+
+    pub fn uncolored() -> ColoredOutput {
+        ColoredOutput {
+            asset_id: None,
+            asset_quantity: 0,
+            kind: OutputKind::Uncolored,
+        }
+    }
+
+    pub fn marker() -> ColoredOutput {
+        ColoredOutput {
+            asset_id: None,
+            asset_quantity: 0,
+            kind: OutputKind::Marker,
+        }
+    }
+
+    pub fn is_colored(&self) -> bool {
+        self.asset_id.is_some()
+    }
+}
+
+/// Source of previous transactions used to resolve the colors of inputs.
+pub trait OutputProvider {
+    fn get_transaction(&self, txid: &sha256d::Hash) -> Result<Transaction, Error>;
+
+    fn get_output(&self, outpoint: &OutPoint) -> Result<TxOut, Error> {
+        let tx = self.get_transaction(&outpoint.txid)?;
+        tx.output
+            .get(outpoint.vout as usize)
+            .cloned()
+            .ok_or(Error::OutputNotFound(*outpoint))
+    }
+}
+
+impl OutputProvider for HashMap<sha256d::Hash, Transaction> {
+    fn get_transaction(&self, txid: &sha256d::Hash) -> Result<Transaction, Error> {
+        self.get(txid)
+            .cloned()
+            .ok_or(Error::TransactionNotFound(*txid))
+    }
+}
+
+/// How the coloring engine treats transactions deviating from the specification.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash, Default)]
+pub enum ColoringPolicy {
+    /// Deviations are reported as errors.
+    Strict,
+    /// Deviations make every output of the transaction uncolored, like openassets-ruby.
+    #[default]
+    Lenient,
+}
+
+/// Applies the Open Assets order-based coloring rules to transactions.
+#[derive(Debug, Clone)]
+pub struct ColoringEngine {
+    pub network: Network,
+    pub policy: ColoringPolicy,
+}
+
+impl ColoringEngine {
+    pub fn new(network: Network) -> ColoringEngine {
+        ColoringEngine {
+            network,
+            policy: ColoringPolicy::default(),
+        }
+    }
+
+    pub fn with_policy(network: Network, policy: ColoringPolicy) -> ColoringEngine {
+        ColoringEngine { network, policy }
+    }
+
+    /// Colors each output of `tx`.
+    ///
+    /// `issuance_script` is the script_pubkey of the output spent by the first input and
+    /// `input_colors` holds the color of each input, in input order.
+    /// If the transaction deviates from the specification all outputs are uncolored, or an
+    /// error is returned under `ColoringPolicy::Strict`.
+    /// Only the first valid marker output is used, later marker outputs are regular outputs.
+    /// For a coinbase transaction `issuance_script` and `input_colors` are ignored.
+    pub fn color_transaction(
+        &self,
+        tx: &Transaction,
+        issuance_script: &Script,
+        input_colors: &[ColoredOutput],
+    ) -> Result<Vec<ColoredOutput>, Error> {
+        self.color(tx, issuance_script, input_colors, None)
+    }
+
+    /// Same as `color_transaction`, looking up the color of each input in `inputs`, e.g. colors
+    /// precomputed by an indexer, instead of resolving ancestors.
+    pub fn color_transaction_with_inputs(
+        &self,
+        tx: &Transaction,
+        issuance_script: &Script,
+        inputs: &HashMap<OutPoint, ColoredOutput>,
+    ) -> Result<Vec<ColoredOutput>, Error> {
+        if tx.is_coin_base() {
+            return Ok(color_coinbase(tx));
+        }
+        let input_colors = tx
+            .input
+            .iter()
+            .map(|input| {
+                inputs
+                    .get(&input.previous_output)
+                    .cloned()
+                    .ok_or(Error::OutputNotFound(input.previous_output))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.color_transaction(tx, issuance_script, &input_colors)
+    }
+
+    /// Same as `color_transaction`, also returning a trace of every assignment step.
+    pub fn color_transaction_traced(
+        &self,
+        tx: &Transaction,
+        issuance_script: &Script,
+        input_colors: &[ColoredOutput],
+    ) -> Result<(Vec<ColoredOutput>, ColoringTrace), Error> {
+        let mut trace = ColoringTrace::new();
+        let colors = self.color(tx, issuance_script, input_colors, Some(&mut trace))?;
+        Ok((colors, trace))
+    }
+
+    fn color(
+        &self,
+        tx: &Transaction,
+        issuance_script: &Script,
+        input_colors: &[ColoredOutput],
+        mut trace: Option<&mut ColoringTrace>,
+    ) -> Result<Vec<ColoredOutput>, Error> {
+        if tx.is_coin_base() {
+            record(&mut trace, || TraceStep::Coinbase);
+            return Ok(color_coinbase(tx));
+        }
+        if input_colors.len() != tx.input.len() {
+            return Err(Error::InputCountMismatch {
+                expected: tx.input.len(),
+                actual: input_colors.len(),
+            });
+        }
+        match find_marker(tx) {
+            Some((marker_index, payload)) => {
+                let colors = self.assign(
+                    tx,
+                    issuance_script,
+                    input_colors,
+                    marker_index,
+                    &payload,
+                    trace,
+                );
+                self.apply_policy(colors, tx.output.len())
+            }
+            None => {
+                record(&mut trace, || TraceStep::NoMarker);
+                Ok(vec![ColoredOutput::uncolored(); tx.output.len()])
+            }
+        }
+    }
+
+    /// Colors each output of `tx`, resolving the colors of its inputs from `provider`.
+    ///
+    /// Ancestors are colored recursively until transactions which do not need the colors
+    /// of their inputs are reached: coinbase transactions, transactions without a marker
+    /// output and transactions which only issue assets.
+    pub fn resolve_transaction<P: OutputProvider>(
+        &self,
+        tx: &Transaction,
+        provider: &P,
+    ) -> Result<Vec<ColoredOutput>, Error> {
+        self.resolve_transaction_with_cache(tx, provider, &mut NoCache)
+    }
+
+    /// Resolves the color of the output referenced by `outpoint`.
+    pub fn resolve_output<P: OutputProvider>(
+        &self,
+        outpoint: &OutPoint,
+        provider: &P,
+    ) -> Result<ColoredOutput, Error> {
+        self.resolve_output_with_cache(outpoint, provider, &mut NoCache)
+    }
+
+    /// Same as `resolve_transaction`, consulting and filling `cache` for every ancestor output.
+    pub fn resolve_transaction_with_cache<P: OutputProvider, C: ColorCache>(
+        &self,
+        tx: &Transaction,
+        provider: &P,
+        cache: &mut C,
+    ) -> Result<Vec<ColoredOutput>, Error> {
+        Resolver::new(self, provider, cache).resolve(tx)
+    }
+
+    /// Same as `resolve_output`, consulting and filling `cache` for every ancestor output.
+    pub fn resolve_output_with_cache<P: OutputProvider, C: ColorCache>(
+        &self,
+        outpoint: &OutPoint,
+        provider: &P,
+        cache: &mut C,
+    ) -> Result<ColoredOutput, Error> {
+        Resolver::new(self, provider, cache).resolve_outpoint(outpoint)
+    }
+
+    /// Replaces the result of `assign` for a transaction deviating from the specification with
+    /// uncolored outputs under `ColoringPolicy::Lenient`.
+    pub(crate) fn apply_policy(
+        &self,
+        colors: Result<Vec<ColoredOutput>, Error>,
+        outputs: usize,
+    ) -> Result<Vec<ColoredOutput>, Error> {
+        match colors {
+            Err(ref e) if self.policy == ColoringPolicy::Lenient && e.is_spec_violation() => {
+                Ok(vec![ColoredOutput::uncolored(); outputs])
+            }
+            colors => colors,
+        }
+    }
+
+    /// Assigns asset ids and quantities to outputs, fails if the marker is invalid.
+    ///
+    /// * The marker must not list more quantities than there are non-marker outputs.
+    /// * Outputs with a zero quantity, or beyond the end of the quantity list, are uncolored.
+    /// * Transfer outputs consume input units in order, an output must not mix two assets
+    ///   and the inputs must hold enough units to cover every output.
+    /// * Input units left over after the last transfer output are destroyed.
+    /// * Uncolored inputs carry no units, whatever their `asset_quantity`.
+    ///
+    /// Fails with `Error::QuantityOverflow`, regardless of the policy, if the input units or
+    /// the transferred quantities add up to more than `u64::MAX`.
+    pub(crate) fn assign(
+        &self,
+        tx: &Transaction,
+        issuance_script: &Script,
+        input_colors: &[ColoredOutput],
+        marker_index: usize,
+        payload: &Payload,
+        trace: Option<&mut ColoringTrace>,
+    ) -> Result<Vec<ColoredOutput>, Error> {
+        let (colors, _) = self.assign_inputs(
+            tx,
+            issuance_script,
+            input_colors.iter().map(Some),
+            marker_index,
+            &payload.quantities,
+            trace,
+        )?;
+        Ok(colors
+            .into_iter()
+            .map(|color| color.expect("every input is known"))
+            .collect())
+    }
+
+    /// Same as `assign`, for inputs which are `None` when their color is unknown.
+    ///
+    /// Transfer outputs stop consuming input units at the first unknown input: the color of
+    /// an output still missing units is `None`, as well as the colors of the following
+    /// outputs with a non-zero quantity. Also returns whether an unknown input was reached.
+    pub(crate) fn assign_inputs<'a, I>(
+        &self,
+        tx: &Transaction,
+        issuance_script: &Script,
+        inputs: I,
+        marker_index: usize,
+        quantities: &[u64],
+        mut trace: Option<&mut ColoringTrace>,
+    ) -> Result<(Vec<Option<ColoredOutput>>, bool), Error>
+    where
+        I: IntoIterator<Item = Option<&'a ColoredOutput>>,
+        I::IntoIter: Clone,
+    {
+        record(&mut trace, || TraceStep::MarkerFound {
+            index: marker_index,
+            quantities: quantities.to_vec(),
+        });
+        if quantities.len() > tx.output.len() - 1 {
+            record(&mut trace, || TraceStep::TooManyQuantities {
+                quantities: quantities.len(),
+                outputs: tx.output.len() - 1,
+            });
+            return Err(Error::TooManyQuantities {
+                quantities: quantities.len(),
+                outputs: tx.output.len() - 1,
+            });
+        }
+        let mut result = Vec::with_capacity(tx.output.len());
+
+        // issuance outputs
+        let issuance_asset_id = AssetId::new(issuance_script, self.network);
+        for i in 0..marker_index {
+            result.push(Some(match quantities.get(i) {
+                Some(&q) if q > 0 => {
+                    record(&mut trace, || TraceStep::Issued {
+                        output: i,
+                        asset_id: issuance_asset_id.clone(),
+                        quantity: q,
+                    });
+                    ColoredOutput::new(issuance_asset_id.clone(), q, OutputKind::Issuance)
+                }
+                _ => ColoredOutput::uncolored(),
+            }));
+        }
+
+        // marker output
+        result.push(Some(ColoredOutput::marker()));
+
+        // transfer outputs, the totals of both sides must be representable
+        let inputs = inputs.into_iter();
+        checked_sum(
+            inputs
+                .clone()
+                .flatten()
+                .filter(|c| c.is_colored())
+                .map(|c| c.asset_quantity),
+        )?;
+        checked_sum(quantities.iter().skip(marker_index).cloned())?;
+        let mut inputs = inputs.enumerate();
+        let mut current: Option<(usize, &AssetId)> = None;
+        let mut input_units_left: u64 = 0;
+        let mut unknown_reached = false;
+        for i in (marker_index + 1)..tx.output.len() {
+            let quantity = quantities.get(i - 1).cloned().unwrap_or(0);
+            if unknown_reached && quantity > 0 {
+                result.push(None);
+                continue;
+            }
+            let mut output_units_left = quantity;
+            let mut asset_id: Option<&AssetId> = None;
+            while output_units_left > 0 {
+                if input_units_left == 0 {
+                    let (index, input) = match inputs.next() {
+                        Some(input) => input,
+                        None => {
+                            record(&mut trace, || TraceStep::Underflow {
+                                output: i,
+                                missing: output_units_left,
+                            });
+                            return Err(Error::InsufficientInputUnits {
+                                output: i,
+                                missing: output_units_left,
+                            });
+                        }
+                    };
+                    let input = match input {
+                        Some(input) => input,
+                        None => {
+                            unknown_reached = true;
+                            break;
+                        }
+                    };
+                    current = input.asset_id.as_ref().map(|id| (index, id));
+                    input_units_left = match current {
+                        Some(_) => input.asset_quantity,
+                        None => 0,
+                    };
+                }
+                if let Some((index, id)) = current {
+                    match asset_id {
+                        None => asset_id = Some(id),
+                        Some(a) if a != id => {
+                            record(&mut trace, || TraceStep::MixedAssets {
+                                input: index,
+                                output: i,
+                            });
+                            return Err(Error::MixedAssets {
+                                input: index,
+                                output: i,
+                            });
+                        }
+                        _ => {}
+                    }
+                    let progress = input_units_left.min(output_units_left);
+                    output_units_left -= progress;
+                    input_units_left -= progress;
+                    record(&mut trace, || TraceStep::Transferred {
+                        input: index,
+                        output: i,
+                        asset_id: id.clone(),
+                        quantity: progress,
+                    });
+                }
+            }
+            result.push(match asset_id {
+                Some(id) => Some(ColoredOutput::new(
+                    id.clone(),
+                    quantity,
+                    OutputKind::Transfer,
+                )),
+                None if output_units_left > 0 => None,
+                None => Some(ColoredOutput::uncolored()),
+            });
+        }
+        Ok((result, unknown_reached))
+    }
+}
+
+/// Storage for output colors which have already been resolved.
+pub trait ColorCache {
+    fn get(&mut self, outpoint: &OutPoint) -> Option<ColoredOutput>;
+
+    fn insert(&mut self, outpoint: OutPoint, color: ColoredOutput);
+}
+
+/// A `ColorCache` which does not store anything.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoCache;
+
+impl ColorCache for NoCache {
+    fn get(&mut self, _: &OutPoint) -> Option<ColoredOutput> {
+        None
+    }
+
+    fn insert(&mut self, _: OutPoint, _: ColoredOutput) {}
+}
+
+/// Walks back through the ancestors of a transaction to resolve the colors of its inputs.
+struct Resolver<'a, P: 'a, C: 'a> {
+    engine: &'a ColoringEngine,
+    provider: &'a P,
+    cache: &'a mut C,
+    visiting: HashSet<sha256d::Hash>,
+}
+
+impl<'a, P: OutputProvider, C: ColorCache> Resolver<'a, P, C> {
+    fn new(engine: &'a ColoringEngine, provider: &'a P, cache: &'a mut C) -> Self {
+        Resolver {
+            engine,
+            provider,
+            cache,
+            visiting: HashSet::new(),
+        }
+    }
+
+    fn resolve(&mut self, tx: &Transaction) -> Result<Vec<ColoredOutput>, Error> {
+        let colors = self.color(tx)?;
+        let txid = tx.txid();
+        for (vout, color) in colors.iter().enumerate() {
+            let outpoint = OutPoint {
+                txid,
+                vout: vout as u32,
+            };
+            self.cache.insert(outpoint, color.clone());
+        }
+        Ok(colors)
+    }
+
+    fn color(&mut self, tx: &Transaction) -> Result<Vec<ColoredOutput>, Error> {
+        if tx.is_coin_base() {
+            return Ok(color_coinbase(tx));
+        }
+        let uncolored = vec![ColoredOutput::uncolored(); tx.output.len()];
+        let (marker_index, payload) = match find_marker(tx) {
+            Some(marker) => marker,
+            None => return Ok(uncolored),
+        };
+        let issuance_script = self
+            .provider
+            .get_output(&tx.input[0].previous_output)?
+            .script_pubkey;
+        let txid = tx.txid();
+        if !self.visiting.insert(txid) {
+            return Err(Error::CyclicReference(txid));
+        }
+        let input_colors = if payload.quantities.len() > marker_index {
+            tx.input
+                .iter()
+                .map(|input| self.resolve_outpoint(&input.previous_output))
+                .collect::<Result<Vec<_>, _>>()
+        } else {
+            // only issuance, the colors of inputs are irrelevant
+            Ok(vec![ColoredOutput::uncolored(); tx.input.len()])
+        };
+        self.visiting.remove(&txid);
+        let colors = self.engine.assign(
+            tx,
+            &issuance_script,
+            &input_colors?,
+            marker_index,
+            &payload,
+            None,
+        );
+        self.engine.apply_policy(colors, tx.output.len())
+    }
+
+    fn resolve_outpoint(&mut self, outpoint: &OutPoint) -> Result<ColoredOutput, Error> {
+        if let Some(color) = self.cache.get(outpoint) {
+            return Ok(color);
+        }
+        let prev_tx = self.provider.get_transaction(&outpoint.txid)?;
+        self.resolve(&prev_tx)?
+            .get(outpoint.vout as usize)
+            .cloned()
+            .ok_or(Error::OutputNotFound(*outpoint))
+    }
+}
+
+/// Returns the index and payload of the first valid marker output of `tx`.
+pub(crate) fn find_marker(tx: &Transaction) -> Option<(usize, Payload)> {
+    tx.find_marker_output()
+}
+
+/// Colors the outputs of a coinbase transaction.
+///
+/// A coinbase transaction does not spend any output, so there is neither an issuance script
+/// to derive an asset id from nor input units to transfer. A marker output is recognized but
+/// its quantities are ignored and every other output is uncolored.
+pub(crate) fn color_coinbase(tx: &Transaction) -> Vec<ColoredOutput> {
+    let mut result = vec![ColoredOutput::uncolored(); tx.output.len()];
+    if let Some(marker_index) = tx.marker_index() {
+        result[marker_index] = ColoredOutput::marker();
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::network::constants::Network;
+    use bitcoin::{OutPoint, Transaction};
+    use bitcoin_hashes::{sha256d, Hash};
+    use openassets::asset_id::AssetId;
+    use openassets::coloring::{
+        ColoredOutput, ColoringEngine, ColoringPolicy, OutputKind, OutputProvider,
+    };
+    use openassets::error::Error;
+    use openassets::test_helpers::{build_spending_tx, build_tx, script};
+    use openassets::trace::TraceStep;
+    use std::collections::HashMap;
+
+    fn outpoint(txid: sha256d::Hash, vout: u32) -> OutPoint {
+        OutPoint { txid, vout }
+    }
+
+    #[test]
+    fn test_color_transaction() {
+        let engine = ColoringEngine::new(Network::Bitcoin);
+        let issuer = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let issued = AssetId::new(&issuer, Network::Bitcoin);
+        let other = AssetId::new(
+            &script("a914f9d499817e88ef7b10a88673296c6d6df2f4292d87"),
+            Network::Bitcoin,
+        );
+        let p2pkh = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+
+        // issue 100 units, transfer 5 and 3 units
+        let marker = script("6a094f4101000364050300");
+        let tx = build_tx(
+            2,
+            vec![p2pkh.clone(), marker.clone(), p2pkh.clone(), p2pkh.clone()],
+        );
+        let inputs = vec![
+            ColoredOutput::new(other.clone(), 5, OutputKind::Transfer),
+            ColoredOutput::new(other.clone(), 3, OutputKind::Issuance),
+        ];
+        assert_eq!(
+            vec![
+                ColoredOutput::new(issued.clone(), 100, OutputKind::Issuance),
+                ColoredOutput::marker(),
+                ColoredOutput::new(other.clone(), 5, OutputKind::Transfer),
+                ColoredOutput::new(other.clone(), 3, OutputKind::Transfer),
+            ],
+            engine.color_transaction(&tx, &issuer, &inputs).unwrap()
+        );
+
+        // an output mixing two assets makes the whole transaction uncolored
+        let marker = script("6a094f4101000300060200");
+        let tx = build_tx(2, vec![p2pkh.clone(), marker, p2pkh.clone(), p2pkh.clone()]);
+        let inputs = vec![
+            ColoredOutput::new(other.clone(), 5, OutputKind::Transfer),
+            ColoredOutput::new(issued.clone(), 3, OutputKind::Transfer),
+        ];
+        assert_eq!(
+            vec![ColoredOutput::uncolored(); 4],
+            engine.color_transaction(&tx, &issuer, &inputs).unwrap()
+        );
+
+        // no marker output
+        let tx = build_tx(1, vec![p2pkh.clone(), p2pkh.clone()]);
+        assert_eq!(
+            vec![ColoredOutput::uncolored(); 2],
+            engine
+                .color_transaction(
+                    &tx,
+                    &issuer,
+                    &[ColoredOutput::new(other.clone(), 5, OutputKind::Transfer)]
+                )
+                .unwrap()
+        );
+
+        // later marker outputs are regular outputs
+        let tx = build_tx(
+            1,
+            vec![
+                script("6a094f4101000305000300"),
+                p2pkh.clone(),
+                script("6a074f410100016400"),
+                p2pkh.clone(),
+            ],
+        );
+        assert_eq!(
+            vec![
+                ColoredOutput::marker(),
+                ColoredOutput::new(other.clone(), 5, OutputKind::Transfer),
+                ColoredOutput::uncolored(),
+                ColoredOutput::new(other.clone(), 3, OutputKind::Transfer),
+            ],
+            engine
+                .color_transaction(
+                    &tx,
+                    &issuer,
+                    &[ColoredOutput::new(other, 8, OutputKind::Transfer)]
+                )
+                .unwrap()
+        );
+
+        // input colors must cover every input
+        assert!(engine.color_transaction(&tx, &issuer, &[]).is_err());
+    }
+
+    #[test]
+    fn test_resolve_transaction() {
+        let engine = ColoringEngine::new(Network::Bitcoin);
+        let issuer = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let p2pkh = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+
+        // funding -> issuance of 100 units -> transfer of 60 + 40 units
+        let funding = build_tx(1, vec![issuer.clone()]);
+        let issuance = build_spending_tx(
+            vec![outpoint(funding.txid(), 0)],
+            vec![p2pkh.clone(), script("6a074f410100016400")],
+        );
+        let transfer = build_spending_tx(
+            vec![outpoint(issuance.txid(), 0)],
+            vec![script("6a084f410100023c2800"), p2pkh.clone(), p2pkh.clone()],
+        );
+        let mut provider = HashMap::new();
+        provider.insert(funding.txid(), funding.clone());
+        provider.insert(issuance.txid(), issuance.clone());
+
+        let asset_id = AssetId::new(&issuer, Network::Bitcoin);
+        assert_eq!(
+            vec![
+                ColoredOutput::marker(),
+                ColoredOutput::new(asset_id.clone(), 60, OutputKind::Transfer),
+                ColoredOutput::new(asset_id.clone(), 40, OutputKind::Transfer),
+            ],
+            engine.resolve_transaction(&transfer, &provider).unwrap()
+        );
+        assert_eq!(
+            ColoredOutput::new(asset_id, 100, OutputKind::Issuance),
+            engine
+                .resolve_output(&outpoint(issuance.txid(), 0), &provider)
+                .unwrap()
+        );
+
+        // missing ancestor
+        provider.remove(&issuance.txid());
+        match engine.resolve_transaction(&transfer, &provider) {
+            Err(Error::TransactionNotFound(txid)) => assert_eq!(issuance.txid(), txid),
+            _ => panic!("expected TransactionNotFound"),
+        }
+
+        // a provider returning the transaction itself for its inputs
+        struct Looping(Transaction);
+        impl OutputProvider for Looping {
+            fn get_transaction(&self, _: &sha256d::Hash) -> Result<Transaction, Error> {
+                Ok(self.0.clone())
+            }
+        }
+        match engine.resolve_transaction(&transfer, &Looping(transfer.clone())) {
+            Err(Error::CyclicReference(txid)) => assert_eq!(transfer.txid(), txid),
+            _ => panic!("expected CyclicReference"),
+        }
+    }
+
+    #[test]
+    fn test_leftover_rules() {
+        let engine = ColoringEngine::new(Network::Bitcoin);
+        let issuer = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let asset_id = AssetId::new(&issuer, Network::Bitcoin);
+        let p2pkh = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+        let inputs = vec![
+            ColoredOutput::new(asset_id.clone(), 3, OutputKind::Transfer),
+            ColoredOutput::uncolored(),
+            ColoredOutput::new(asset_id.clone(), 4, OutputKind::Transfer),
+        ];
+        let color = |marker: &str, outputs: usize| {
+            let mut scripts = vec![script(marker)];
+            scripts.extend(vec![p2pkh.clone(); outputs]);
+            engine
+                .color_transaction(&build_tx(3, scripts), &issuer, &inputs)
+                .unwrap()
+        };
+
+        // units flow across inputs, skipping uncolored ones, leftover units are destroyed
+        assert_eq!(
+            vec![
+                ColoredOutput::marker(),
+                ColoredOutput::new(asset_id.clone(), 2, OutputKind::Transfer),
+                ColoredOutput::new(asset_id.clone(), 4, OutputKind::Transfer),
+                ColoredOutput::uncolored(),
+            ],
+            color("6a084f41010002020400", 3)
+        );
+
+        // zero quantities and outputs beyond the quantity list are uncolored
+        assert_eq!(
+            vec![
+                ColoredOutput::marker(),
+                ColoredOutput::uncolored(),
+                ColoredOutput::new(asset_id.clone(), 7, OutputKind::Transfer),
+                ColoredOutput::uncolored(),
+            ],
+            color("6a084f41010002000700", 3)
+        );
+
+        // exhausted inputs make every output uncolored
+        assert_eq!(
+            vec![ColoredOutput::uncolored(); 3],
+            color("6a084f41010002050300", 2)
+        );
+
+        // more quantities than outputs make every output uncolored
+        assert_eq!(
+            vec![ColoredOutput::uncolored(); 3],
+            color("6a094f4101000301010100", 2)
+        );
+    }
+
+    #[test]
+    fn test_uncolored_input_quantity() {
+        let engine = ColoringEngine::with_policy(Network::Bitcoin, ColoringPolicy::Strict);
+        let issuer = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let asset_id = AssetId::new(&issuer, Network::Bitcoin);
+        let p2pkh = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+        let txid = sha256d::Hash::hash(b"previous");
+        let tx = build_spending_tx(
+            vec![outpoint(txid, 0), outpoint(txid, 1)],
+            vec![script("6a074f410100010500"), p2pkh],
+        );
+        let uncolored = ColoredOutput {
+            asset_id: None,
+            asset_quantity: 10,
+            kind: OutputKind::Uncolored,
+        };
+
+        // the quantity of an uncolored input is not transferred
+        let inputs = vec![
+            uncolored.clone(),
+            ColoredOutput::new(asset_id.clone(), 5, OutputKind::Transfer),
+        ];
+        assert_eq!(
+            vec![
+                ColoredOutput::marker(),
+                ColoredOutput::new(asset_id.clone(), 5, OutputKind::Transfer),
+            ],
+            engine.color_transaction(&tx, &issuer, &inputs).unwrap()
+        );
+        match engine.color_transaction(&tx, &issuer, &[uncolored.clone(), uncolored]) {
+            Err(Error::InsufficientInputUnits {
+                output: 1,
+                missing: 5,
+            }) => {}
+            r => panic!("expected InsufficientInputUnits, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_coinbase() {
+        let engine = ColoringEngine::new(Network::Bitcoin);
+        let issuer = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let p2pkh = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+        let coinbase = build_spending_tx(
+            vec![OutPoint::null()],
+            vec![p2pkh.clone(), script("6a084f41010002640500"), p2pkh.clone()],
+        );
+        assert!(coinbase.is_coin_base());
+        let expected = vec![
+            ColoredOutput::uncolored(),
+            ColoredOutput::marker(),
+            ColoredOutput::uncolored(),
+        ];
+        assert_eq!(
+            expected,
+            engine.color_transaction(&coinbase, &issuer, &[]).unwrap()
+        );
+        let provider: HashMap<sha256d::Hash, Transaction> = HashMap::new();
+        assert_eq!(
+            expected,
+            engine.resolve_transaction(&coinbase, &provider).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_color_transaction_traced() {
+        let engine = ColoringEngine::new(Network::Bitcoin);
+        let issuer = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let asset_id = AssetId::new(&issuer, Network::Bitcoin);
+        let p2pkh = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+        let inputs = vec![
+            ColoredOutput::new(asset_id.clone(), 3, OutputKind::Transfer),
+            ColoredOutput::new(asset_id.clone(), 4, OutputKind::Transfer),
+        ];
+
+        let tx = build_tx(2, vec![script("6a074f410100010500"), p2pkh.clone()]);
+        let (colors, trace) = engine
+            .color_transaction_traced(&tx, &issuer, &inputs)
+            .unwrap();
+        assert_eq!(
+            ColoredOutput::new(asset_id.clone(), 5, OutputKind::Transfer),
+            colors[1]
+        );
+        assert_eq!(
+            vec![
+                TraceStep::MarkerFound {
+                    index: 0,
+                    quantities: vec![5]
+                },
+                TraceStep::Transferred {
+                    input: 0,
+                    output: 1,
+                    asset_id: asset_id.clone(),
+                    quantity: 3
+                },
+                TraceStep::Transferred {
+                    input: 1,
+                    output: 1,
+                    asset_id: asset_id.clone(),
+                    quantity: 2
+                },
+            ],
+            trace.steps
+        );
+        assert_eq!(3, trace.to_string().lines().count());
+
+        // underflow
+        let tx = build_tx(2, vec![script("6a074f410100010800"), p2pkh.clone()]);
+        let (_, trace) = engine
+            .color_transaction_traced(&tx, &issuer, &inputs)
+            .unwrap();
+        assert_eq!(
+            Some(&TraceStep::Underflow {
+                output: 1,
+                missing: 1
+            }),
+            trace.steps.last()
+        );
+
+        // no marker
+        let tx = build_tx(2, vec![p2pkh]);
+        let (_, trace) = engine
+            .color_transaction_traced(&tx, &issuer, &inputs)
+            .unwrap();
+        assert_eq!(vec![TraceStep::NoMarker], trace.steps);
+    }
+
+    #[test]
+    fn test_coloring_policy() {
+        let strict = ColoringEngine::with_policy(Network::Bitcoin, ColoringPolicy::Strict);
+        let lenient = ColoringEngine::new(Network::Bitcoin);
+        assert_eq!(ColoringPolicy::Lenient, lenient.policy);
+        let issuer = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let asset_id = AssetId::new(&issuer, Network::Bitcoin);
+        let p2pkh = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+        let inputs = vec![ColoredOutput::new(asset_id, 3, OutputKind::Transfer)];
+
+        // insufficient input units
+        let tx = build_tx(1, vec![script("6a074f410100010500"), p2pkh.clone()]);
+        assert_eq!(
+            vec![ColoredOutput::uncolored(); 2],
+            lenient.color_transaction(&tx, &issuer, &inputs).unwrap()
+        );
+        match strict.color_transaction(&tx, &issuer, &inputs) {
+            Err(Error::InsufficientInputUnits { output, missing }) => {
+                assert_eq!(1, output);
+                assert_eq!(2, missing);
+            }
+            _ => panic!("expected InsufficientInputUnits"),
+        }
+
+        // trailing quantities
+        let tx = build_tx(1, vec![script("6a084f41010002010100"), p2pkh.clone()]);
+        assert_eq!(
+            vec![ColoredOutput::uncolored(); 2],
+            lenient.color_transaction(&tx, &issuer, &inputs).unwrap()
+        );
+        match strict.color_transaction(&tx, &issuer, &inputs) {
+            Err(ref e @ Error::TooManyQuantities { .. }) => assert!(e.is_spec_violation()),
+            _ => panic!("expected TooManyQuantities"),
+        }
+
+        // valid transactions color the same under both policies
+        let tx = build_tx(1, vec![script("6a074f410100010300"), p2pkh]);
+        assert_eq!(
+            lenient.color_transaction(&tx, &issuer, &inputs).unwrap(),
+            strict.color_transaction(&tx, &issuer, &inputs).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_quantity_overflow() {
+        let engine = ColoringEngine::new(Network::Bitcoin);
+        let issuer = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let asset_id = AssetId::new(&issuer, Network::Bitcoin);
+        let p2pkh = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+
+        // quantities [u64::MAX, 1]
+        let marker = script("6a114f41010002ffffffffffffffffff010100");
+        let tx = build_tx(1, vec![marker.clone(), p2pkh.clone(), p2pkh.clone()]);
+        let inputs = vec![ColoredOutput::new(
+            asset_id.clone(),
+            u64::MAX,
+            OutputKind::Transfer,
+        )];
+        match engine.color_transaction(&tx, &issuer, &inputs) {
+            Err(Error::QuantityOverflow) => {}
+            _ => panic!("expected QuantityOverflow"),
+        }
+
+        // input units
+        let tx = build_tx(2, vec![script("6a074f410100010100"), p2pkh.clone()]);
+        let inputs = vec![
+            ColoredOutput::new(asset_id.clone(), u64::MAX, OutputKind::Transfer),
+            ColoredOutput::new(asset_id.clone(), 1, OutputKind::Transfer),
+        ];
+        match engine.color_transaction(&tx, &issuer, &inputs) {
+            Err(Error::QuantityOverflow) => {}
+            _ => panic!("expected QuantityOverflow"),
+        }
+
+        // a single output may hold u64::MAX units
+        let marker = script("6a104f41010001ffffffffffffffffff0100");
+        let tx = build_tx(1, vec![marker, p2pkh]);
+        let inputs = vec![ColoredOutput::new(
+            asset_id.clone(),
+            u64::MAX,
+            OutputKind::Transfer,
+        )];
+        assert_eq!(
+            ColoredOutput::new(asset_id, u64::MAX, OutputKind::Transfer),
+            engine.color_transaction(&tx, &issuer, &inputs).unwrap()[1]
+        );
+    }
+
+    #[test]
+    fn test_color_transaction_with_inputs() {
+        let engine = ColoringEngine::new(Network::Bitcoin);
+        let issuer = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let asset_id = AssetId::new(&issuer, Network::Bitcoin);
+        let p2pkh = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+        let tx = build_tx(
+            2,
+            vec![script("6a084f41010002030400"), p2pkh.clone(), p2pkh],
+        );
+
+        let mut inputs = HashMap::new();
+        inputs.insert(
+            tx.input[0].previous_output,
+            ColoredOutput::new(asset_id.clone(), 3, OutputKind::Transfer),
+        );
+        match engine.color_transaction_with_inputs(&tx, &issuer, &inputs) {
+            Err(Error::OutputNotFound(outpoint)) => {
+                assert_eq!(tx.input[1].previous_output, outpoint)
+            }
+            _ => panic!("expected OutputNotFound"),
+        }
+
+        inputs.insert(
+            tx.input[1].previous_output,
+            ColoredOutput::new(asset_id.clone(), 4, OutputKind::Issuance),
+        );
+        assert_eq!(
+            vec![
+                ColoredOutput::marker(),
+                ColoredOutput::new(asset_id.clone(), 3, OutputKind::Transfer),
+                ColoredOutput::new(asset_id.clone(), 4, OutputKind::Transfer),
+            ],
+            engine
+                .color_transaction_with_inputs(&tx, &issuer, &inputs)
+                .unwrap()
+        );
+
+        // supplied uncolored inputs carry no units, whatever their quantity
+        inputs.insert(
+            tx.input[0].previous_output,
+            ColoredOutput {
+                asset_id: None,
+                asset_quantity: 3,
+                kind: OutputKind::Uncolored,
+            },
+        );
+        let strict = ColoringEngine::with_policy(Network::Bitcoin, ColoringPolicy::Strict);
+        match strict.color_transaction_with_inputs(&tx, &issuer, &inputs) {
+            Err(Error::InsufficientInputUnits {
+                output: 2,
+                missing: 3,
+            }) => {}
+            r => panic!("expected InsufficientInputUnits, got {:?}", r),
+        }
+        assert_eq!(
+            vec![ColoredOutput::uncolored(); 3],
+            engine
+                .color_transaction_with_inputs(&tx, &issuer, &inputs)
+                .unwrap()
+        );
+    }
+}
@@ -0,0 +1,602 @@
+use bitcoin_hashes::{sha256, Hash};
+use openassets::asset_id::AssetId;
+use openassets::marker_output::Metadata;
+use openassets::quantity::{self, QuantityError};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "json")]
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt::{self, Display, Formatter};
+use std::str;
+
+/// The version of the asset definition format of Coinprism, assumed for files without a
+/// version.
+pub const DEFINITION_VERSION: &str = "1.0";
+
+/// The versions of the asset definition format which are read, oldest first.
+pub const SUPPORTED_DEFINITION_VERSIONS: &[&str] = &[DEFINITION_VERSION];
+
+/// The maximum length of the ticker of an asset, in characters.
+pub const MAX_NAME_SHORT_LEN: usize = 10;
+
+/// The maximum divisibility of an asset, for which a unit of the asset still fits in a u64.
+pub const MAX_DIVISIBILITY: u8 = 19;
+
+/// An asset definition file, which describes the assets listed in `asset_ids` and is
+/// referenced by the metadata of their issuance markers.
+///
+/// Fields beyond those of Coinprism, such as `issuer_url`, are optional extensions of the
+/// format, left out of files which do not set them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AssetDefinition {
+    /// The assets described by the file, which must list the asset being resolved.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub asset_ids: Vec<AssetId>,
+    /// The ticker of the asset, e.g. `USD`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub name_short: Option<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub name: Option<String>,
+    /// The URL of the contract binding the issuer to holders.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub contract_url: Option<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub issuer: Option<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub description: Option<String>,
+    /// The MIME type of `description`, e.g. `text/x-markdown; charset=UTF-8`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub description_mime: Option<String>,
+    /// The kind of asset, e.g. `Currency`, `Share` or `Smart Property`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, rename = "type", skip_serializing_if = "Option::is_none")
+    )]
+    pub asset_type: Option<String>,
+    /// The number of decimal places of the asset, e.g. 2 to display 1234 units as 12.34.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub divisibility: u8,
+    /// Whether the file is served from the website of the issuer, who vouches for it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub link_to_website: bool,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub icon_url: Option<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub image_url: Option<String>,
+    /// The website of the issuer, an extension to the format of Coinprism.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub issuer_url: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default = "default_version"))]
+    pub version: String,
+    /// The fields unknown to this crate, kept as is so that they are written back.
+    #[cfg(feature = "json")]
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+#[cfg(feature = "serde")]
+fn default_version() -> String {
+    DEFINITION_VERSION.to_string()
+}
+
+/// Errors raised while reading asset definition files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DefinitionError {
+    /// The file is not a valid JSON asset definition.
+    Json(String),
+    /// The file is of an unsupported version of the format.
+    UnsupportedVersion(String),
+    /// `name_short` exceeds `MAX_NAME_SHORT_LEN` characters.
+    NameShortTooLong(usize),
+    /// `divisibility` exceeds `MAX_DIVISIBILITY`.
+    DivisibilityTooLarge(u8),
+    /// The file does not describe the asset being resolved.
+    AssetNotListed(AssetId),
+    /// The metadata holds no asset definition pointer.
+    NoPointer,
+    /// The file could not be fetched.
+    Fetch(String),
+    /// The signature does not sign the definition with the key of the expected issuer.
+    InvalidSignature,
+    /// The file does not match the hash committed to by its pointer.
+    HashMismatch {
+        expected: sha256::Hash,
+        actual: sha256::Hash,
+    },
+}
+
+impl Display for DefinitionError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            DefinitionError::Json(ref e) => write!(f, "invalid asset definition: {}", e),
+            DefinitionError::UnsupportedVersion(ref v) => {
+                write!(f, "unsupported asset definition version: {}", v)
+            }
+            DefinitionError::NameShortTooLong(len) => write!(
+                f,
+                "name_short too long: {} characters, at most {}",
+                len, MAX_NAME_SHORT_LEN
+            ),
+            DefinitionError::DivisibilityTooLarge(d) => write!(
+                f,
+                "divisibility too large: {}, at most {}",
+                d, MAX_DIVISIBILITY
+            ),
+            DefinitionError::AssetNotListed(ref asset_id) => {
+                write!(f, "asset not listed in its definition: {}", asset_id)
+            }
+            DefinitionError::NoPointer => write!(f, "no asset definition pointer"),
+            DefinitionError::Fetch(ref e) => write!(f, "asset definition fetch failed: {}", e),
+            DefinitionError::InvalidSignature => write!(f, "invalid asset definition signature"),
+            DefinitionError::HashMismatch {
+                ref expected,
+                ref actual,
+            } => write!(
+                f,
+                "asset definition hash mismatch: expected {}, actual {}",
+                hex::encode(&expected[..]),
+                hex::encode(&actual[..])
+            ),
+        }
+    }
+}
+
+impl error::Error for DefinitionError {}
+
+impl Default for AssetDefinition {
+    fn default() -> AssetDefinition {
+        AssetDefinition {
+            asset_ids: vec![],
+            name_short: None,
+            name: None,
+            contract_url: None,
+            issuer: None,
+            description: None,
+            description_mime: None,
+            asset_type: None,
+            divisibility: 0,
+            link_to_website: false,
+            icon_url: None,
+            image_url: None,
+            issuer_url: None,
+            version: DEFINITION_VERSION.to_string(),
+            #[cfg(feature = "json")]
+            extra: BTreeMap::new(),
+        }
+    }
+}
+
+impl AssetDefinition {
+    /// Returns an empty definition of version `DEFINITION_VERSION`.
+    pub fn new() -> AssetDefinition {
+        AssetDefinition::default()
+    }
+
+    /// Checks the fields of the definition against the limits of the format.
+    pub fn validate(&self) -> Result<(), DefinitionError> {
+        if !SUPPORTED_DEFINITION_VERSIONS.contains(&self.version.as_str()) {
+            return Err(DefinitionError::UnsupportedVersion(self.version.clone()));
+        }
+        if let Some(ref name_short) = self.name_short {
+            let len = name_short.chars().count();
+            if len > MAX_NAME_SHORT_LEN {
+                return Err(DefinitionError::NameShortTooLong(len));
+            }
+        }
+        if self.divisibility > MAX_DIVISIBILITY {
+            return Err(DefinitionError::DivisibilityTooLarge(self.divisibility));
+        }
+        Ok(())
+    }
+
+    /// Returns whether the definition describes `asset_id`, regardless of network.
+    pub fn describes(&self, asset_id: &AssetId) -> bool {
+        self.asset_ids.iter().any(|id| id.same_asset(asset_id))
+    }
+
+    /// Formats the raw `quantity` of the asset with its divisibility, e.g. 1234 as `12.34`,
+    /// failing if the divisibility exceeds `MAX_DIVISIBILITY`.
+    pub fn format_quantity(&self, quantity: u64) -> Result<String, QuantityError> {
+        quantity::format_quantity(quantity, self.divisibility)
+    }
+
+    /// Parses a decimal number into a raw quantity of the asset, e.g. `12.34` into 1234.
+    pub fn parse_quantity(&self, s: &str) -> Result<u64, QuantityError> {
+        quantity::parse_quantity(s, self.divisibility)
+    }
+
+    /// Parses and validates an asset definition file.
+    #[cfg(feature = "json")]
+    pub fn from_json(data: &[u8]) -> Result<AssetDefinition, DefinitionError> {
+        let definition: AssetDefinition =
+            serde_json::from_slice(data).map_err(|e| DefinitionError::Json(e.to_string()))?;
+        definition.validate()?;
+        Ok(definition)
+    }
+
+    /// Parses and validates the definition file of `asset_id`, failing if the file does not
+    /// list it.
+    #[cfg(feature = "json")]
+    pub fn from_json_for(
+        data: &[u8],
+        asset_id: &AssetId,
+    ) -> Result<AssetDefinition, DefinitionError> {
+        let definition = AssetDefinition::from_json(data)?;
+        if !definition.describes(asset_id) {
+            return Err(DefinitionError::AssetNotListed(asset_id.clone()));
+        }
+        Ok(definition)
+    }
+
+    /// Returns the JSON encoding of the definition.
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("asset definitions serialize to JSON")
+    }
+}
+
+/// The marker metadata locating the asset definition file of an asset, written
+/// `u=<url>`, or `u=<url>#sha256=<hex>` to commit to the SHA-256 of the file.
+///
+/// The commitment is a URL fragment, which is not sent to the server serving the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetDefinitionPointer {
+    pub url: String,
+    pub sha256: Option<sha256::Hash>,
+}
+
+const COMMITMENT_PREFIX: &str = "#sha256=";
+
+impl AssetDefinitionPointer {
+    pub fn new(url: &str) -> AssetDefinitionPointer {
+        AssetDefinitionPointer {
+            url: url.to_string(),
+            sha256: None,
+        }
+    }
+
+    /// Returns a pointer to `url` committing to the hash of `definition`.
+    pub fn committed(url: &str, definition: &[u8]) -> AssetDefinitionPointer {
+        AssetDefinitionPointer {
+            url: url.to_string(),
+            sha256: Some(sha256::Hash::hash(definition)),
+        }
+    }
+
+    /// Parses the pointer held by `metadata`, if any, decompressing it first under the
+    /// `compression` feature.
+    ///
+    /// A fragment which is not a well-formed commitment is kept as part of the URL.
+    pub fn from_metadata(metadata: &Metadata) -> Option<AssetDefinitionPointer> {
+        #[cfg(feature = "compression")]
+        let metadata = &Metadata::new(metadata.content().ok()?.into_owned());
+        let pointer = match *metadata {
+            Metadata::Url(ref url) => url.as_str(),
+            _ => return None,
+        };
+        if let Some(i) = pointer.rfind(COMMITMENT_PREFIX) {
+            let commitment = hex::decode(&pointer[i + COMMITMENT_PREFIX.len()..])
+                .ok()
+                .and_then(|h| sha256::Hash::from_slice(&h).ok());
+            if commitment.is_some() {
+                return Some(AssetDefinitionPointer {
+                    url: pointer[..i].to_string(),
+                    sha256: commitment,
+                });
+            }
+        }
+        Some(AssetDefinitionPointer::new(pointer))
+    }
+
+    /// Returns the metadata holding the pointer.
+    pub fn to_metadata(&self) -> Metadata {
+        Metadata::Url(match self.sha256 {
+            Some(ref hash) => format!(
+                "{}{}{}",
+                self.url,
+                COMMITMENT_PREFIX,
+                hex::encode(&hash[..])
+            ),
+            None => self.url.clone(),
+        })
+    }
+
+    /// Checks the downloaded definition file against the commitment of the pointer, if any.
+    pub fn verify(&self, data: &[u8]) -> Result<(), DefinitionError> {
+        match self.sha256 {
+            Some(expected) => {
+                let actual = sha256::Hash::hash(data);
+                if actual != expected {
+                    return Err(DefinitionError::HashMismatch { expected, actual });
+                }
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin_hashes::{sha256, Hash};
+    use openassets::asset_id::AssetId;
+    use openassets::definition::{
+        AssetDefinition, AssetDefinitionPointer, DefinitionError, DEFINITION_VERSION,
+    };
+    use openassets::marker_output::Metadata;
+    use openassets::quantity::QuantityError;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_validate() {
+        let mut definition = AssetDefinition::new();
+        assert_eq!(DEFINITION_VERSION, definition.version);
+        assert_eq!(Ok(()), definition.validate());
+        assert_eq!(definition, AssetDefinition::default());
+
+        definition.name_short = Some("MYASSET".to_string());
+        definition.divisibility = 2;
+        assert_eq!(Ok(()), definition.validate());
+
+        definition.name_short = Some("MYASSETNAME".to_string());
+        assert_eq!(
+            Err(DefinitionError::NameShortTooLong(11)),
+            definition.validate()
+        );
+        definition.name_short = Some("ÉÉÉÉÉÉÉÉÉÉ".to_string());
+        assert_eq!(Ok(()), definition.validate());
+
+        definition.divisibility = 20;
+        assert_eq!(
+            Err(DefinitionError::DivisibilityTooLarge(20)),
+            definition.validate()
+        );
+        definition.divisibility = 0;
+
+        definition.issuer_url = Some("https://example.com".to_string());
+        assert_eq!(Ok(()), definition.validate());
+
+        for version in &["", "2.0"] {
+            definition.version = version.to_string();
+            assert_eq!(
+                Err(DefinitionError::UnsupportedVersion(version.to_string())),
+                definition.validate()
+            );
+        }
+    }
+
+    #[test]
+    fn test_pointer() {
+        let metadata = Metadata::new(b"u=https://cpr.sm/5YgSU1Pg-q".to_vec());
+        let pointer = AssetDefinitionPointer::from_metadata(&metadata).unwrap();
+        assert_eq!(
+            AssetDefinitionPointer::new("https://cpr.sm/5YgSU1Pg-q"),
+            pointer
+        );
+        assert_eq!(metadata, pointer.to_metadata());
+        assert_eq!(Ok(()), pointer.verify(b"anything"));
+
+        let definition = br#"{"name": "MyAsset"}"#;
+        let pointer =
+            AssetDefinitionPointer::committed("https://example.com/asset.json", definition);
+        let metadata = pointer.to_metadata();
+        match metadata {
+            Metadata::Url(ref url) => assert!(url.starts_with("https://example.com/asset.json#")),
+            ref m => panic!("expected Url, got {:?}", m),
+        }
+        assert_eq!(
+            format!(
+                "u=https://example.com/asset.json#sha256={}",
+                hex::encode(&sha256::Hash::hash(definition)[..])
+            ),
+            metadata.as_str().unwrap()
+        );
+        assert_eq!(
+            pointer,
+            AssetDefinitionPointer::from_metadata(&metadata).unwrap()
+        );
+        assert_eq!(Ok(()), pointer.verify(definition));
+        match pointer.verify(br#"{"name": "Forged"}"#) {
+            Err(DefinitionError::HashMismatch { expected, .. }) => {
+                assert_eq!(pointer.sha256.unwrap(), expected)
+            }
+            r => panic!("expected HashMismatch, got {:?}", r),
+        }
+
+        // fragments other than commitments are part of the URL
+        let metadata = Metadata::new(b"u=https://example.com/a.json#sha256=zz".to_vec());
+        assert_eq!(
+            AssetDefinitionPointer::new("https://example.com/a.json#sha256=zz"),
+            AssetDefinitionPointer::from_metadata(&metadata).unwrap()
+        );
+
+        for metadata in &[&b""[..], b"u=", b"https://example.com", b"\xff\xfe"] {
+            assert!(
+                AssetDefinitionPointer::from_metadata(&Metadata::new(metadata.to_vec())).is_none()
+            );
+        }
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_compressed_pointer() {
+        let pointer = AssetDefinitionPointer::committed("https://example.com/asset.json", b"{}");
+        let metadata = Metadata::compressed(&pointer.to_metadata().as_bytes());
+        assert_eq!(
+            pointer,
+            AssetDefinitionPointer::from_metadata(&metadata).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_quantity() {
+        let mut definition = AssetDefinition::new();
+        definition.divisibility = 2;
+        assert_eq!(Ok("12.34".to_string()), definition.format_quantity(1234));
+        assert_eq!(Ok(1234), definition.parse_quantity("12.34"));
+        assert!(definition.parse_quantity("12.345").is_err());
+
+        // e.g. read from a hostile definition file
+        definition.divisibility = 20;
+        assert_eq!(
+            Err(QuantityError::DivisibilityTooLarge(20)),
+            definition.format_quantity(1234)
+        );
+        assert_eq!(
+            Err(QuantityError::DivisibilityTooLarge(20)),
+            definition.parse_quantity("12.34")
+        );
+    }
+
+    #[test]
+    fn test_describes() {
+        let asset_id = AssetId::from_str("ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC").unwrap();
+        let mut definition = AssetDefinition::new();
+        assert!(!definition.describes(&asset_id));
+        definition.asset_ids.push(asset_id.clone());
+        assert!(definition.describes(&asset_id));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json() {
+        let json = br#"{
+            "asset_ids": ["ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC"],
+            "name_short": "HAWK",
+            "name": "MyAsset",
+            "contract_url": "http://www.openassets.org/my-asset/contract",
+            "issuer": "Open Assets",
+            "description": "Asset description",
+            "description_mime": "text/x-markdown; charset=UTF-8",
+            "type": "Currency",
+            "divisibility": 2,
+            "link_to_website": false,
+            "icon_url": "http://www.openassets.org/my-asset/icon.png",
+            "image_url": "http://www.openassets.org/my-asset/image.png",
+            "version": "1.0"
+        }"#;
+        let asset_id = AssetId::from_str("ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC").unwrap();
+        let definition = AssetDefinition::from_json_for(json, &asset_id).unwrap();
+        assert_eq!(vec![asset_id], definition.asset_ids);
+        assert_eq!(Some("HAWK".to_string()), definition.name_short);
+        assert_eq!(Some("Currency".to_string()), definition.asset_type);
+        assert_eq!(2, definition.divisibility);
+        assert!(!definition.link_to_website);
+        assert_eq!(
+            definition,
+            AssetDefinition::from_json(definition.to_json().as_bytes()).unwrap()
+        );
+
+        // optional fields default
+        let definition = AssetDefinition::from_json(br#"{"name": "MyAsset"}"#).unwrap();
+        assert_eq!(Some("MyAsset".to_string()), definition.name);
+        assert!(definition.asset_ids.is_empty());
+        assert_eq!(0, definition.divisibility);
+        assert_eq!("1.0", definition.version);
+        assert_eq!(
+            r#"{
+  "asset_ids": [],
+  "name": "MyAsset",
+  "divisibility": 0,
+  "link_to_website": false,
+  "version": "1.0"
+}"#,
+            definition.to_json()
+        );
+
+        let other = AssetId::from_str("AJk2Gx5V67S2wNuwTK5hef3TpHunfbjcmX").unwrap();
+        assert_eq!(
+            Err(DefinitionError::AssetNotListed(other.clone())),
+            AssetDefinition::from_json_for(json, &other)
+        );
+        match AssetDefinition::from_json(b"{\"divisibility\": -1}") {
+            Err(DefinitionError::Json(_)) => {}
+            r => panic!("expected Json, got {:?}", r),
+        }
+        assert_eq!(
+            Err(DefinitionError::NameShortTooLong(11)),
+            AssetDefinition::from_json(br#"{"name_short": "MYASSETNAME"}"#)
+        );
+        match AssetDefinition::from_json(br#"{"asset_ids": ["xxx"]}"#) {
+            Err(DefinitionError::Json(_)) => {}
+            r => panic!("expected Json, got {:?}", r),
+        }
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_versions() {
+        // extensions are read, unknown fields are written back
+        let json = br#"{"name": "MyAsset", "issuer_url": "https://example.com", "x_rating": {"score": 5}}"#;
+        let legacy = AssetDefinition::from_json(json).unwrap();
+        assert_eq!(DEFINITION_VERSION, legacy.version);
+        assert_eq!(Some("https://example.com".to_string()), legacy.issuer_url);
+        assert_eq!(vec!["x_rating"], legacy.extra.keys().collect::<Vec<_>>());
+        assert_eq!(
+            r#"{
+  "asset_ids": [],
+  "name": "MyAsset",
+  "divisibility": 0,
+  "link_to_website": false,
+  "issuer_url": "https://example.com",
+  "version": "1.0",
+  "x_rating": {
+    "score": 5
+  }
+}"#,
+            legacy.to_json()
+        );
+        assert_eq!(
+            legacy,
+            AssetDefinition::from_json(legacy.to_json().as_bytes()).unwrap()
+        );
+
+        // new definitions are of version 1.0 and leave out unset extensions
+        assert_eq!(
+            r#"{
+  "asset_ids": [],
+  "divisibility": 0,
+  "link_to_website": false,
+  "version": "1.0"
+}"#,
+            AssetDefinition::new().to_json()
+        );
+        assert_eq!(
+            AssetDefinition::new(),
+            AssetDefinition::from_json(br#"{"version": "1.0"}"#).unwrap()
+        );
+        assert_eq!(
+            Err(DefinitionError::UnsupportedVersion("2.0".to_string())),
+            AssetDefinition::from_json(br#"{"version": "2.0"}"#)
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use bitcoin::consensus::serialize;
+use bitcoin::{Script, TxOut};
+
+/// The value traditionally given to the outputs carrying assets.
+pub const DEFAULT_DUST_VALUE: u64 = 600;
+
+/// The fee rate, in satoshis per 1000 virtual bytes, Bitcoin Core defines dust with.
+pub const DEFAULT_DUST_RELAY_FEE: u64 = 3_000;
+
+/// The values given to the outputs built for assets, and the threshold below which relay
+/// nodes reject an output as dust.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct DustPolicy {
+    /// The value of the outputs carrying assets, unless overridden per output.
+    pub value: u64,
+    /// The fee rate, in satoshis per 1000 virtual bytes, defining dust.
+    pub relay_fee: u64,
+}
+
+impl Default for DustPolicy {
+    fn default() -> Self {
+        DustPolicy::new(DEFAULT_DUST_VALUE)
+    }
+}
+
+impl DustPolicy {
+    pub fn new(value: u64) -> DustPolicy {
+        DustPolicy {
+            value,
+            relay_fee: DEFAULT_DUST_RELAY_FEE,
+        }
+    }
+
+    /// Returns the minimum value of an output to `script` to be relayed, i.e. the fee to pay
+    /// at `relay_fee` for the output and an input spending it, as Bitcoin Core computes it.
+    pub fn threshold(&self, script: &Script) -> u64 {
+        if script.is_op_return() || script.is_provably_unspendable() {
+            return 0;
+        }
+        let output = TxOut {
+            value: 0,
+            script_pubkey: script.clone(),
+        };
+        // outpoint, sequence, and a signature and public key in the script or the witness
+        let input_size = if is_witness_program(script) {
+            32 + 4 + 1 + 107 / 4 + 4
+        } else {
+            32 + 4 + 1 + 107 + 4
+        };
+        (serialize(&output).len() as u64 + input_size) * self.relay_fee / 1000
+    }
+
+    /// Returns true if `output` is below the dust threshold of its script.
+    pub fn is_dust(&self, output: &TxOut) -> bool {
+        output.value < self.threshold(&output.script_pubkey)
+    }
+}
+
+/// Returns true if `script` is a segwit output script, i.e. a version byte followed by a
+/// single push of 2 to 40 bytes.
+fn is_witness_program(script: &Script) -> bool {
+    let bytes = script.as_bytes();
+    bytes.len() >= 4
+        && bytes.len() <= 42
+        && (bytes[0] == 0x00 || (0x51..=0x60).contains(&bytes[0]))
+        && bytes[1] as usize + 2 == bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::TxOut;
+    use openassets::dust::DustPolicy;
+    use openassets::test_helpers::script;
+
+    #[test]
+    fn test_threshold() {
+        let policy = DustPolicy::default();
+        let p2pkh = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+        let p2sh = script("a914f9d499817e88ef7b10a88673296c6d6df2f4292d87");
+        let p2wpkh = script("001446c2fbfbecc99a63148fa076de58cf29b0bcf0b0");
+        let p2wsh = script("0020f9d499817e88ef7b10a88673296c6d6df2f4292d87f9d499817e88ef7b10a800");
+        assert_eq!(546, policy.threshold(&p2pkh));
+        assert_eq!(540, policy.threshold(&p2sh));
+        assert_eq!(294, policy.threshold(&p2wpkh));
+        assert_eq!(330, policy.threshold(&p2wsh));
+        assert_eq!(0, policy.threshold(&script("6a00")));
+
+        assert!(!policy.is_dust(&TxOut {
+            value: 546,
+            script_pubkey: p2pkh.clone(),
+        }));
+        assert!(policy.is_dust(&TxOut {
+            value: 545,
+            script_pubkey: p2pkh.clone(),
+        }));
+        let policy = DustPolicy {
+            relay_fee: 1_000,
+            ..policy
+        };
+        assert_eq!(182, policy.threshold(&p2pkh));
+    }
+}
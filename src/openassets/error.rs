@@ -0,0 +1,102 @@
+use bitcoin::consensus::encode;
+use bitcoin::OutPoint;
+use bitcoin_hashes::sha256d;
+use openassets::marker_output::StandardnessError;
+use std::error;
+use std::fmt::{self, Display, Formatter};
+
+/// Errors raised while interpreting Open Assets transactions.
+#[derive(Debug)]
+pub enum Error {
+    /// Bitcoin data could not be encoded or decoded.
+    Encode(encode::Error),
+    /// The number of input colors does not match the number of transaction inputs.
+    InputCountMismatch { expected: usize, actual: usize },
+    /// The transaction could not be found.
+    TransactionNotFound(sha256d::Hash),
+    /// The output could not be found.
+    OutputNotFound(OutPoint),
+    /// The transaction spends one of its own descendants.
+    CyclicReference(sha256d::Hash),
+    /// The marker lists more quantities than there are non-marker outputs.
+    TooManyQuantities { quantities: usize, outputs: usize },
+    /// An output would receive units of different assets.
+    MixedAssets { input: usize, output: usize },
+    /// The inputs do not hold enough units to cover the transfer outputs.
+    InsufficientInputUnits { output: usize, missing: u64 },
+    /// A sum of asset quantities exceeds `u64::MAX`.
+    QuantityOverflow,
+    /// An asset quantity exceeds the maximum of `limit` allowed by the Open Assets
+    /// specification, so that it can not be encoded in a marker output.
+    QuantityTooLarge { quantity: u64, limit: u64 },
+    /// The script of a marker output would not be relayed.
+    NonStandardMarker(StandardnessError),
+}
+
+impl Error {
+    /// Returns true if the error is a deviation from the Open Assets specification, which makes
+    /// a transaction uncolored rather than failing under a lenient coloring policy.
+    pub fn is_spec_violation(&self) -> bool {
+        matches!(
+            *self,
+            Error::TooManyQuantities { .. }
+                | Error::MixedAssets { .. }
+                | Error::InsufficientInputUnits { .. }
+        )
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            Error::Encode(ref e) => Display::fmt(e, f),
+            Error::InputCountMismatch { expected, actual } => write!(
+                f,
+                "input color count mismatch: expected {}, actual {}",
+                expected, actual
+            ),
+            Error::TransactionNotFound(ref txid) => write!(f, "transaction not found: {}", txid),
+            Error::OutputNotFound(ref outpoint) => write!(f, "output not found: {}", outpoint),
+            Error::CyclicReference(ref txid) => write!(f, "cyclic reference: {}", txid),
+            Error::TooManyQuantities {
+                quantities,
+                outputs,
+            } => write!(
+                f,
+                "too many asset quantities: {} quantities for {} outputs",
+                quantities, outputs
+            ),
+            Error::MixedAssets { input, output } => {
+                write!(f, "input #{} mixes assets into output #{}", input, output)
+            }
+            Error::InsufficientInputUnits { output, missing } => write!(
+                f,
+                "insufficient input units: {} units missing for output #{}",
+                missing, output
+            ),
+            Error::QuantityOverflow => write!(f, "asset quantity overflow"),
+            Error::QuantityTooLarge { quantity, limit } => write!(
+                f,
+                "asset quantity of {} exceeds the maximum of {}",
+                quantity, limit
+            ),
+            Error::NonStandardMarker(ref e) => Display::fmt(e, f),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Encode(ref e) => Some(e),
+            Error::NonStandardMarker(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<encode::Error> for Error {
+    fn from(e: encode::Error) -> Self {
+        Error::Encode(e)
+    }
+}
@@ -0,0 +1,155 @@
+use bitcoin::blockdata::script::Builder;
+use bitcoin::{Script, Transaction};
+
+/// The size of a DER signature with its sighash type, at most.
+const SIGNATURE_SIZE: u64 = 73;
+
+/// The size of a compressed public key.
+const PUBKEY_SIZE: u64 = 33;
+
+/// The size of a P2WPKH redeem script, i.e. a version byte and a push of a public key hash.
+const P2WPKH_SIZE: usize = 22;
+
+/// Returns a script_sig of the size of the one satisfying `script`, filled with zeros.
+///
+/// P2WPKH outputs are satisfied by the witness alone, and P2SH outputs are assumed to wrap a
+/// P2WPKH script, pushed by the script_sig. Scripts other than P2PK are assumed to be
+/// satisfied like P2PKH, by a signature and a public key.
+pub fn script_sig_placeholder(script: &Script) -> Script {
+    if script.is_v0_p2wpkh() {
+        Script::new()
+    } else if script.is_p2sh() {
+        Builder::new().push_slice(&[0; P2WPKH_SIZE]).into_script()
+    } else if script.is_p2pk() {
+        Builder::new()
+            .push_slice(&[0; SIGNATURE_SIZE as usize])
+            .into_script()
+    } else {
+        Builder::new()
+            .push_slice(&[0; SIGNATURE_SIZE as usize])
+            .push_slice(&[0; PUBKEY_SIZE as usize])
+            .into_script()
+    }
+}
+
+/// Returns a witness of the size of the one satisfying `script`, filled with zeros.
+///
+/// P2WPKH outputs, and P2SH outputs assumed to wrap a P2WPKH script, are satisfied by a
+/// signature and a public key. Other outputs have an empty witness.
+pub fn witness_placeholder(script: &Script) -> Vec<Vec<u8>> {
+    if script.is_v0_p2wpkh() || script.is_p2sh() {
+        vec![
+            vec![0; SIGNATURE_SIZE as usize],
+            vec![0; PUBKEY_SIZE as usize],
+        ]
+    } else {
+        vec![]
+    }
+}
+
+/// Returns the estimated virtual size of the unsigned transaction `tx` once signed.
+///
+/// `prev_scripts` holds the script spent by each input, in input order. Each input is
+/// measured with the placeholders of its script in place of its script_sig and witness.
+pub fn estimate_vsize(tx: &Transaction, prev_scripts: &[&Script]) -> u64 {
+    let mut signed = tx.clone();
+    for (input, script) in signed.input.iter_mut().zip(prev_scripts) {
+        input.script_sig = script_sig_placeholder(script);
+        input.witness = witness_placeholder(script);
+    }
+    signed.get_weight().div_ceil(4)
+}
+
+/// Returns the fee paying `fee_rate` satoshis per virtual byte for `tx` once signed.
+pub fn estimate_fee(tx: &Transaction, prev_scripts: &[&Script], fee_rate: u64) -> u64 {
+    estimate_vsize(tx, prev_scripts) * fee_rate
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::consensus::deserialize;
+    use bitcoin::{OutPoint, Script, Transaction, TxIn, TxOut};
+    use hex::decode as hex_decode;
+    use openassets::fee::{estimate_fee, estimate_vsize};
+    use openassets::test_helpers::script;
+
+    #[test]
+    fn test_estimate_vsize() {
+        let p2pkh = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+        let marker = script("6a084f410100023c2800");
+        let tx = Transaction {
+            version: 1,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: OutPoint::default(),
+                script_sig: Script::new(),
+                sequence: 0xFFFF_FFFF,
+                witness: vec![],
+            }],
+            output: vec![
+                TxOut {
+                    value: 0,
+                    script_pubkey: marker,
+                },
+                TxOut {
+                    value: 600,
+                    script_pubkey: p2pkh.clone(),
+                },
+                TxOut {
+                    value: 600,
+                    script_pubkey: p2pkh.clone(),
+                },
+            ],
+        };
+        // 10 bytes of header, 41 bytes of unsigned input, 19 bytes of marker output, 2 colored
+        // outputs of 34 bytes, and 108 bytes of signature and public key
+        assert_eq!(246, estimate_vsize(&tx, &[&p2pkh]));
+        assert_eq!(492, estimate_fee(&tx, &[&p2pkh], 2));
+
+        // a signed transaction spending a P2PKH output
+        let signed: Transaction = deserialize(&hex_decode("0100000001a15d57094aa7a21a28cb20b59aab8fc7d1149a3bdbcddba9c622e4f5f6a99ece010000006c493046022100f93bb0e7d8db7bd46e40132d1f8242026e045f03a0efe71bbb8e3f475e970d790221009337cd7f1f929f00cc6ff01f03729b069a7c21b59b1736ddfee5db5946c5da8c0121033b9b137ee87d5a812d6f506efdd37f0affa7ffc310711c06c7f3e097c9447c52ffffffff0100e1f505000000001976a9140389035a9225b3839e2bbf32d826a1e222031fd888ac00000000").unwrap()).unwrap();
+        let unsigned = Transaction {
+            input: vec![TxIn {
+                script_sig: Script::new(),
+                ..signed.input[0].clone()
+            }],
+            ..signed.clone()
+        };
+        assert!(estimate_vsize(&unsigned, &[&p2pkh]) >= signed.get_weight() / 4);
+    }
+
+    #[test]
+    fn test_estimate_vsize_segwit() {
+        let p2wpkh = script("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1");
+        let p2sh = script("a9144733f37cf4db86fbc2efed2500b4f4e49f31202387");
+        let p2pkh = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+        let input = TxIn {
+            previous_output: OutPoint::default(),
+            script_sig: Script::new(),
+            sequence: 0xFFFF_FFFF,
+            witness: vec![],
+        };
+        let tx = Transaction {
+            version: 1,
+            lock_time: 0,
+            input: vec![input.clone()],
+            output: vec![TxOut {
+                value: 600,
+                script_pubkey: p2pkh.clone(),
+            }],
+        };
+        // 10 bytes of header and 34 bytes of output, 41 bytes of input, 2 weight units of
+        // segwit marker and flag, and 109 weight units of witness
+        assert_eq!(113, estimate_vsize(&tx, &[&p2wpkh]));
+        // the script_sig pushes the 22 bytes of the redeem script
+        assert_eq!(136, estimate_vsize(&tx, &[&p2sh]));
+        assert_eq!(193, estimate_vsize(&tx, &[&p2pkh]));
+
+        // the segwit marker and flag are counted once
+        let tx = Transaction {
+            input: vec![input.clone(), input],
+            ..tx
+        };
+        assert_eq!(181, estimate_vsize(&tx, &[&p2wpkh, &p2wpkh]));
+    }
+}
@@ -0,0 +1,414 @@
+use openassets::asset_id::AssetId;
+#[cfg(feature = "json")]
+use openassets::definition::AssetDefinitionPointer;
+use openassets::definition::{AssetDefinition, DefinitionError};
+#[cfg(feature = "json")]
+use openassets::marker_output::Metadata;
+#[cfg(feature = "http")]
+use reqwest::blocking::Client;
+#[cfg(feature = "http")]
+use reqwest::redirect::Policy;
+use std::collections::HashMap;
+#[cfg(feature = "http")]
+use std::io::Read;
+use std::time::{Duration, Instant};
+
+/// Source of asset definition files, e.g. an HTTP client or a local mirror.
+pub trait DefinitionFetcher {
+    /// Returns the content of the file at `url`.
+    fn fetch(&self, url: &str) -> Result<Vec<u8>, DefinitionError>;
+
+    /// Returns the definition of `asset_id` pointed to by the metadata of its issuance marker,
+    /// checking the file against the hash the pointer commits to, if any.
+    #[cfg(feature = "json")]
+    fn resolve(
+        &self,
+        metadata: &Metadata,
+        asset_id: &AssetId,
+    ) -> Result<AssetDefinition, DefinitionError> {
+        let pointer =
+            AssetDefinitionPointer::from_metadata(metadata).ok_or(DefinitionError::NoPointer)?;
+        let data = self.fetch(&pointer.url)?;
+        pointer.verify(&data)?;
+        AssetDefinition::from_json_for(&data, asset_id)
+    }
+
+    /// Same as `resolve`, returning the definition held by `cache` if any, and caching the
+    /// definition resolved otherwise.
+    #[cfg(feature = "json")]
+    fn resolve_cached<C: DefinitionCache>(
+        &self,
+        metadata: &Metadata,
+        asset_id: &AssetId,
+        cache: &mut C,
+    ) -> Result<AssetDefinition, DefinitionError>
+    where
+        Self: Sized,
+    {
+        if let Some(definition) = cache.get(asset_id) {
+            return Ok(definition);
+        }
+        let definition = self.resolve(metadata, asset_id)?;
+        cache.insert(asset_id.clone(), definition.clone());
+        Ok(definition)
+    }
+}
+
+/// Storage for asset definitions which have already been resolved, by asset id.
+pub trait DefinitionCache {
+    fn get(&mut self, asset_id: &AssetId) -> Option<AssetDefinition>;
+
+    fn insert(&mut self, asset_id: AssetId, definition: AssetDefinition);
+}
+
+/// A `DefinitionCache` in memory, whose definitions expire `ttl` after being cached so that
+/// updated definition files are eventually picked up.
+#[derive(Debug, Clone)]
+pub struct MemoryDefinitionCache {
+    pub ttl: Duration,
+    entries: HashMap<AssetId, (Instant, AssetDefinition)>,
+}
+
+impl MemoryDefinitionCache {
+    pub fn new(ttl: Duration) -> MemoryDefinitionCache {
+        MemoryDefinitionCache {
+            ttl,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Drops the expired definitions.
+    pub fn purge(&mut self) {
+        let ttl = self.ttl;
+        self.entries
+            .retain(|_, &mut (cached_at, _)| cached_at.elapsed() < ttl);
+    }
+
+    /// Returns the number of definitions cached, including expired ones not purged yet.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl DefinitionCache for MemoryDefinitionCache {
+    fn get(&mut self, asset_id: &AssetId) -> Option<AssetDefinition> {
+        let expired = match self.entries.get(asset_id) {
+            Some(&(cached_at, ref definition)) if cached_at.elapsed() < self.ttl => {
+                return Some(definition.clone())
+            }
+            Some(_) => true,
+            None => false,
+        };
+        if expired {
+            self.entries.remove(asset_id);
+        }
+        None
+    }
+
+    fn insert(&mut self, asset_id: AssetId, definition: AssetDefinition) {
+        self.entries.insert(asset_id, (Instant::now(), definition));
+    }
+}
+
+/// A blocking HTTP client fetching definition files, backed by `reqwest`.
+///
+/// Both `http://` and `https://` URLs are fetched, following up to 10 redirects.
+#[cfg(feature = "http")]
+#[derive(Debug, Clone)]
+pub struct HttpFetcher {
+    client: Client,
+    /// The size, in bytes, a definition file must not exceed.
+    pub max_size: usize,
+}
+
+#[cfg(feature = "http")]
+impl HttpFetcher {
+    /// Returns a fetcher whose requests time out after `timeout`.
+    pub fn new(timeout: Duration) -> Result<HttpFetcher, DefinitionError> {
+        let client = Client::builder()
+            .timeout(timeout)
+            .redirect(Policy::limited(10))
+            .build()
+            .map_err(|e| DefinitionError::Fetch(e.to_string()))?;
+        Ok(HttpFetcher::with_client(client))
+    }
+
+    /// Returns a fetcher sending its requests with `client`, e.g. a client going through a
+    /// proxy.
+    pub fn with_client(client: Client) -> HttpFetcher {
+        HttpFetcher {
+            client,
+            max_size: 1 << 20,
+        }
+    }
+}
+
+/// A fetcher whose requests time out after 10 seconds.
+///
+/// # Panics
+/// Panics if the TLS backend cannot be initialized, like `reqwest::blocking::Client::new`.
+#[cfg(feature = "http")]
+impl Default for HttpFetcher {
+    fn default() -> HttpFetcher {
+        HttpFetcher::new(Duration::from_secs(10)).expect("HTTP client")
+    }
+}
+
+#[cfg(feature = "http")]
+impl DefinitionFetcher for HttpFetcher {
+    fn fetch(&self, url: &str) -> Result<Vec<u8>, DefinitionError> {
+        let too_large =
+            || DefinitionError::Fetch(format!("definition larger than {} bytes", self.max_size));
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .map_err(|e| DefinitionError::Fetch(e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(DefinitionError::Fetch(format!(
+                "HTTP status {}",
+                status.as_u16()
+            )));
+        }
+        if response
+            .content_length()
+            .is_some_and(|len| len > self.max_size as u64)
+        {
+            return Err(too_large());
+        }
+        let mut body = Vec::new();
+        response
+            .take(self.max_size as u64 + 1)
+            .read_to_end(&mut body)
+            .map_err(|e| DefinitionError::Fetch(e.to_string()))?;
+        if body.len() > self.max_size {
+            return Err(too_large());
+        }
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use openassets::asset_id::AssetId;
+    use openassets::definition::{AssetDefinition, DefinitionError};
+    use openassets::fetch::{DefinitionCache, DefinitionFetcher, MemoryDefinitionCache};
+    use std::cell::Cell;
+    use std::collections::HashMap;
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    struct MapFetcher(HashMap<String, Vec<u8>>, Cell<usize>);
+
+    impl MapFetcher {
+        fn new(files: HashMap<String, Vec<u8>>) -> MapFetcher {
+            MapFetcher(files, Cell::new(0))
+        }
+    }
+
+    impl DefinitionFetcher for MapFetcher {
+        fn fetch(&self, url: &str) -> Result<Vec<u8>, DefinitionError> {
+            self.1.set(self.1.get() + 1);
+            self.0
+                .get(url)
+                .cloned()
+                .ok_or_else(|| DefinitionError::Fetch(format!("not found: {}", url)))
+        }
+    }
+
+    #[test]
+    fn test_fetch() {
+        let mut files = HashMap::new();
+        files.insert("https://example.com/a.json".to_string(), b"{}".to_vec());
+        let fetcher = MapFetcher::new(files);
+        assert_eq!(
+            b"{}".to_vec(),
+            fetcher.fetch("https://example.com/a.json").unwrap()
+        );
+        assert!(fetcher.fetch("https://example.com/b.json").is_err());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_resolve() {
+        use openassets::definition::AssetDefinitionPointer;
+        use openassets::marker_output::Metadata;
+
+        let asset_id = AssetId::from_str("ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC").unwrap();
+        let definition =
+            br#"{"asset_ids": ["ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC"], "name": "MyAsset"}"#;
+        let mut files = HashMap::new();
+        files.insert(
+            "https://example.com/a.json".to_string(),
+            definition.to_vec(),
+        );
+        files.insert("https://example.com/b.json".to_string(), b"{}".to_vec());
+        let fetcher = MapFetcher::new(files);
+
+        let metadata = Metadata::new(b"u=https://example.com/a.json".to_vec());
+        assert_eq!(
+            Some("MyAsset".to_string()),
+            fetcher.resolve(&metadata, &asset_id).unwrap().name
+        );
+        let metadata = AssetDefinitionPointer::committed("https://example.com/a.json", definition)
+            .to_metadata();
+        assert!(fetcher.resolve(&metadata, &asset_id).is_ok());
+
+        let metadata =
+            AssetDefinitionPointer::committed("https://example.com/a.json", b"{}").to_metadata();
+        match fetcher.resolve(&metadata, &asset_id) {
+            Err(DefinitionError::HashMismatch { .. }) => {}
+            r => panic!("expected HashMismatch, got {:?}", r),
+        }
+        let metadata = Metadata::new(b"u=https://example.com/b.json".to_vec());
+        assert_eq!(
+            Err(DefinitionError::AssetNotListed(asset_id.clone())),
+            fetcher.resolve(&metadata, &asset_id)
+        );
+        assert_eq!(
+            Err(DefinitionError::NoPointer),
+            fetcher.resolve(&Metadata::new(vec![]), &asset_id)
+        );
+        match fetcher.resolve(
+            &Metadata::new(b"u=https://example.com/c.json".to_vec()),
+            &asset_id,
+        ) {
+            Err(DefinitionError::Fetch(_)) => {}
+            r => panic!("expected Fetch, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_memory_cache() {
+        let asset_id = AssetId::from_str("ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC").unwrap();
+        let mut definition = AssetDefinition::new();
+        definition.name = Some("MyAsset".to_string());
+
+        let mut cache = MemoryDefinitionCache::new(Duration::from_secs(3600));
+        assert!(cache.get(&asset_id).is_none());
+        cache.insert(asset_id.clone(), definition.clone());
+        assert_eq!(Some(definition.clone()), cache.get(&asset_id));
+        cache.purge();
+        assert_eq!(1, cache.len());
+
+        // definitions expire once the ttl has elapsed
+        let mut cache = MemoryDefinitionCache::new(Duration::from_secs(0));
+        cache.insert(asset_id.clone(), definition.clone());
+        assert_eq!(1, cache.len());
+        cache.purge();
+        assert!(cache.is_empty());
+        cache.insert(asset_id.clone(), definition);
+        assert!(cache.get(&asset_id).is_none());
+        assert!(cache.is_empty());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_resolve_cached() {
+        use bitcoin::network::constants::Network;
+        use bitcoin::Script;
+        use openassets::marker_output::Metadata;
+
+        let asset_id = AssetId::from_str("ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC").unwrap();
+        let mut files = HashMap::new();
+        files.insert(
+            "https://example.com/a.json".to_string(),
+            br#"{"asset_ids": ["ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC"]}"#.to_vec(),
+        );
+        let fetcher = MapFetcher::new(files);
+        let metadata = Metadata::new(b"u=https://example.com/a.json".to_vec());
+
+        let mut cache = MemoryDefinitionCache::new(Duration::from_secs(3600));
+        let definition = fetcher
+            .resolve_cached(&metadata, &asset_id, &mut cache)
+            .unwrap();
+        assert_eq!(
+            definition,
+            fetcher
+                .resolve_cached(&metadata, &asset_id, &mut cache)
+                .unwrap()
+        );
+        assert_eq!(1, fetcher.1.get());
+
+        // failures are not cached
+        let other = AssetId::new(&Script::new(), Network::Bitcoin);
+        let metadata = Metadata::new(b"u=https://example.com/b.json".to_vec());
+        assert!(fetcher
+            .resolve_cached(&metadata, &other, &mut cache)
+            .is_err());
+        assert!(fetcher
+            .resolve_cached(&metadata, &other, &mut cache)
+            .is_err());
+        assert_eq!(3, fetcher.1.get());
+        assert_eq!(1, cache.len());
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_http_fetcher() {
+        use openassets::fetch::HttpFetcher;
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+        use std::thread;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in &[
+                format!(
+                    "HTTP/1.0 302 Found\r\nLocation: http://127.0.0.1:{}/moved.json\r\nContent-Length: 0\r\n\r\n",
+                    port
+                ),
+                "HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n{\"name\": \"MyAsset\"}"
+                    .to_string(),
+                "HTTP/1.0 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string(),
+                "HTTP/1.0 200 OK\r\nContent-Length: 10\r\n\r\n0123456789".to_string(),
+                "HTTP/1.0 200 OK\r\n\r\n0123456789".to_string(),
+            ] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = vec![];
+                let mut buf = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let len = stream.read(&mut buf).unwrap();
+                    assert!(len > 0);
+                    request.extend_from_slice(&buf[..len]);
+                }
+                requests.push(String::from_utf8_lossy(&request).to_string());
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+            requests
+        });
+
+        // redirects are followed
+        let fetcher = HttpFetcher::default();
+        let url = format!("http://127.0.0.1:{}/asset.json#sha256=00", port);
+        assert_eq!(
+            br#"{"name": "MyAsset"}"#.to_vec(),
+            fetcher.fetch(&url).unwrap()
+        );
+        match fetcher.fetch(&url) {
+            Err(DefinitionError::Fetch(ref e)) => assert_eq!("HTTP status 404", e),
+            r => panic!("expected Fetch, got {:?}", r),
+        }
+        // files too large, with and without Content-Length
+        let fetcher = HttpFetcher {
+            max_size: 5,
+            ..HttpFetcher::default()
+        };
+        assert!(fetcher.fetch(&url).is_err());
+        assert!(fetcher.fetch(&url).is_err());
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("GET /asset.json HTTP/1.1\r\n"));
+        assert!(requests[0].contains(&format!("host: 127.0.0.1:{}\r\n", port)));
+        assert!(requests[1].starts_with("GET /moved.json HTTP/1.1\r\n"));
+
+        assert!(fetcher.fetch("ftp://example.com/asset.json").is_err());
+    }
+}
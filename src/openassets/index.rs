@@ -0,0 +1,206 @@
+use bitcoin::{Block, OutPoint};
+use openassets::coloring::{ColoredOutput, ColoringEngine, OutputProvider};
+use openassets::error::Error;
+use std::collections::HashMap;
+
+/// The changes made to a `ColorIndex` by a block.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct ColorDiff {
+    /// The outputs created by the block and left unspent, in block order.
+    pub added: Vec<(OutPoint, ColoredOutput)>,
+    /// The outputs created before the block and spent by it, in block order, along with their
+    /// colors so that the diff can be undone.
+    pub spent: Vec<(OutPoint, ColoredOutput)>,
+}
+
+/// The colors of the unspent outputs of a chain, updated block by block.
+pub struct ColorIndex {
+    pub engine: ColoringEngine,
+    utxos: HashMap<OutPoint, ColoredOutput>,
+}
+
+impl ColorIndex {
+    pub fn new(engine: ColoringEngine) -> ColorIndex {
+        ColorIndex {
+            engine,
+            utxos: HashMap::new(),
+        }
+    }
+
+    /// Colors the transactions of `block` and applies them to the index.
+    ///
+    /// `provider` must serve the transactions spent by the block, which the issuance asset ids
+    /// are derived from. The colors of the outputs they spend are taken from the index, and
+    /// only resolved from `provider` when missing, e.g. when indexing started after the genesis
+    /// block. On error, the index is left untouched.
+    pub fn apply_block<P: OutputProvider + Sync>(
+        &mut self,
+        block: &Block,
+        provider: &P,
+    ) -> Result<ColorDiff, Error> {
+        let mut colors = self
+            .engine
+            .color_block_with_utxos(block, provider, &self.utxos)?;
+
+        let mut spent = vec![];
+        for tx in block.txdata.iter().filter(|tx| !tx.is_coin_base()) {
+            for input in &tx.input {
+                let outpoint = input.previous_output;
+                if colors.remove(&outpoint).is_none() {
+                    if let Some(color) = self.utxos.remove(&outpoint) {
+                        spent.push((outpoint, color));
+                    }
+                }
+            }
+        }
+        let mut added = vec![];
+        for tx in &block.txdata {
+            let txid = tx.txid();
+            for vout in 0..tx.output.len() as u32 {
+                let outpoint = OutPoint { txid, vout };
+                if let Some(color) = colors.remove(&outpoint) {
+                    self.utxos.insert(outpoint, color.clone());
+                    added.push((outpoint, color));
+                }
+            }
+        }
+        Ok(ColorDiff { added, spent })
+    }
+
+    /// Rolls back the block which produced `diff`. Diffs must be undone in the reverse order of
+    /// the blocks they were produced by.
+    pub fn undo(&mut self, diff: ColorDiff) {
+        for (outpoint, _) in diff.added {
+            self.utxos.remove(&outpoint);
+        }
+        self.utxos.extend(diff.spent);
+    }
+
+    /// Returns the color of an unspent output.
+    pub fn get(&self, outpoint: &OutPoint) -> Option<&ColoredOutput> {
+        self.utxos.get(outpoint)
+    }
+
+    /// Returns the number of unspent outputs.
+    pub fn len(&self) -> usize {
+        self.utxos.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.utxos.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::blockdata::block::BlockHeader;
+    use bitcoin::network::constants::Network;
+    use bitcoin::{Block, OutPoint, Transaction};
+    use openassets::asset_id::AssetId;
+    use openassets::coloring::{ColoredOutput, ColoringEngine, OutputKind};
+    use openassets::index::ColorIndex;
+    use openassets::test_helpers::{build_spending_tx, script};
+    use std::collections::HashMap;
+
+    fn build_block(txdata: Vec<Transaction>) -> Block {
+        Block {
+            header: BlockHeader {
+                version: 1,
+                prev_blockhash: Default::default(),
+                merkle_root: Default::default(),
+                time: 0,
+                bits: 0,
+                nonce: 0,
+            },
+            txdata,
+        }
+    }
+
+    #[test]
+    fn test_apply_block_and_undo() {
+        let issuer = "76a914010966776006953d5567439e5e39f86a0d273bee88ac";
+        let p2pkh = "76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac";
+        let funding = build_spending_tx(vec![OutPoint::default()], vec![issuer]);
+        let issuance = build_spending_tx(
+            vec![OutPoint {
+                txid: funding.txid(),
+                vout: 0,
+            }],
+            vec![p2pkh, "6a074f410100016400"],
+        );
+        let transfer = build_spending_tx(
+            vec![OutPoint {
+                txid: issuance.txid(),
+                vout: 0,
+            }],
+            vec!["6a084f410100023c2800", p2pkh, p2pkh],
+        );
+        let spend = build_spending_tx(
+            vec![OutPoint {
+                txid: transfer.txid(),
+                vout: 2,
+            }],
+            vec![p2pkh],
+        );
+        let mut provider = HashMap::new();
+        provider.insert(funding.txid(), funding);
+
+        let mut index = ColorIndex::new(ColoringEngine::new(Network::Bitcoin));
+        let first = index
+            .apply_block(&build_block(vec![issuance.clone()]), &provider)
+            .unwrap();
+        assert_eq!(2, first.added.len());
+        assert!(first.spent.is_empty());
+        provider.insert(issuance.txid(), issuance.clone());
+
+        // the transfer spends an output of the previous block, the last output of the transfer
+        // is spent within the block
+        let second = index
+            .apply_block(
+                &build_block(vec![transfer.clone(), spend.clone()]),
+                &provider,
+            )
+            .unwrap();
+        let asset_id = AssetId::new(&script(issuer), Network::Bitcoin);
+        let issued = OutPoint {
+            txid: issuance.txid(),
+            vout: 0,
+        };
+        assert_eq!(
+            vec![(
+                issued,
+                ColoredOutput::new(asset_id.clone(), 100, OutputKind::Issuance)
+            )],
+            second.spent
+        );
+        assert_eq!(3, second.added.len());
+        assert_eq!(4, index.len());
+        assert_eq!(
+            Some(&ColoredOutput::new(
+                asset_id.clone(),
+                60,
+                OutputKind::Transfer
+            )),
+            index.get(&OutPoint {
+                txid: transfer.txid(),
+                vout: 1,
+            })
+        );
+        assert_eq!(
+            None,
+            index.get(&OutPoint {
+                txid: transfer.txid(),
+                vout: 2,
+            })
+        );
+
+        index.undo(second);
+        assert_eq!(2, index.len());
+        assert_eq!(
+            Some(&ColoredOutput::new(asset_id, 100, OutputKind::Issuance)),
+            index.get(&issued)
+        );
+        index.undo(first);
+        assert!(index.is_empty());
+    }
+}
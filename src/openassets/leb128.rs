@@ -0,0 +1,117 @@
+use bitcoin::consensus::encode::Error;
+use bitcoin::consensus::{Decodable, Decoder, Encodable, Encoder};
+
+/// The length, in bytes, of the longest LEB128 encoding of a u64.
+pub const MAX_LEN: usize = 10;
+
+/// Returns the length, in bytes, of the LEB128 encoding of `value`.
+pub fn encoded_len(value: u64) -> usize {
+    let bits = 64 - value.leading_zeros() as usize;
+    bits.max(1).div_ceil(7)
+}
+
+/// Writes the LEB128 encoding of `value` to `s`.
+pub fn encode_into<S: Encoder>(value: u64, s: &mut S) -> Result<(), Error> {
+    let mut value = value;
+    loop {
+        let mut byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value != 0 {
+            byte |= 0x80;
+        }
+        byte.consensus_encode(s)?;
+        if value == 0 {
+            return Ok(());
+        }
+    }
+}
+
+/// Returns the LEB128 encoding of `value`.
+pub fn encode(value: u64) -> Vec<u8> {
+    let mut data = Vec::with_capacity(encoded_len(value));
+    encode_into(value, &mut data).expect("in-memory writers don't error");
+    data
+}
+
+/// Reads a LEB128 encoded value from `d`, returning it along with the length of its encoding.
+///
+/// Encodings of values exceeding 64 bits are rejected rather than wrapped. Encodings padded with
+/// trailing zero groups are accepted, and are longer than `encoded_len` of their value.
+pub fn decode_from<D: Decoder>(d: &mut D) -> Result<(u64, usize), Error> {
+    let mut value: u64 = 0;
+    for length in 0..MAX_LEN {
+        let b: u8 = Decodable::consensus_decode(d)?;
+        // the last group holds the 64th bit only
+        if length == MAX_LEN - 1 && b > 1 {
+            break;
+        }
+        value |= u64::from(b & 0x7f) << (7 * length);
+        if b & 0x80 == 0 {
+            return Ok((value, length + 1));
+        }
+    }
+    Err(Error::ParseFailed("LEB128 value exceeds 64 bits."))
+}
+
+/// Decodes the LEB128 encoded value making up the whole of `data`.
+pub fn decode(data: &[u8]) -> Result<u64, Error> {
+    let mut d = data;
+    let (value, length) = decode_from(&mut d)?;
+    if length != data.len() {
+        return Err(Error::ParseFailed(
+            "data not consumed entirely when explicitly deserializing",
+        ));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use hex::decode as hex_decode;
+    use openassets::leb128::{decode, decode_from, encode, encoded_len, MAX_LEN};
+
+    #[test]
+    fn test_leb128() {
+        let cases: Vec<(u64, &str)> = vec![
+            (0, "00"),
+            (1, "01"),
+            (127, "7f"),
+            (128, "8001"),
+            (300, "ac02"),
+            (16_383, "ff7f"),
+            (16_384, "808001"),
+            (624_485, "e58e26"),
+            ((1 << 56) - 1, "ffffffffffffff7f"),
+            (1 << 56, "808080808080808001"),
+            ((1 << 63) - 1, "ffffffffffffffff7f"),
+            (1 << 63, "80808080808080808001"),
+            (u64::MAX, "ffffffffffffffffff01"),
+        ];
+        for (value, hex) in cases {
+            let data = hex_decode(hex).unwrap();
+            assert_eq!(data, encode(value));
+            assert_eq!(data.len(), encoded_len(value));
+            assert_eq!(value, decode(&data).unwrap());
+        }
+        assert_eq!(MAX_LEN, encoded_len(u64::MAX));
+
+        // non-canonical encodings are longer than the encoded length of their value
+        let data = hex_decode("808000").unwrap();
+        assert_eq!((0, 3), decode_from(&mut &data[..]).unwrap());
+        let data = hex_decode("ff8000").unwrap();
+        assert_eq!((127, 3), decode_from(&mut &data[..]).unwrap());
+
+        // past 64 bits
+        assert!(decode(&hex_decode("ffffffffffffffffff02").unwrap()).is_err());
+        assert!(decode(&hex_decode("8080808080808080808001").unwrap()).is_err());
+        // truncated
+        assert!(decode(&hex_decode("8080").unwrap()).is_err());
+        assert!(decode(&[]).is_err());
+        // trailing data
+        assert!(decode(&hex_decode("0100").unwrap()).is_err());
+        let data = hex_decode("ac02ff").unwrap();
+        let mut d = &data[..];
+        assert_eq!((300, 2), decode_from(&mut d).unwrap());
+        assert_eq!(&[0xff], d);
+    }
+}
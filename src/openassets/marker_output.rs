@@ -1,38 +1,539 @@
+use std::borrow::Cow;
+use std::cmp;
 use std::fmt;
+use std::io::Cursor;
+use std::str::{self, Utf8Error};
 
-use bitcoin::blockdata::script::Instruction;
+use bitcoin::blockdata::opcodes;
+use bitcoin::blockdata::script::{Builder, Instruction};
 use bitcoin::consensus::encode::Error;
-use bitcoin::consensus::{deserialize, Decodable, Decoder, Encodable, Encoder};
-use bitcoin::{TxOut, VarInt};
+use bitcoin::consensus::{deserialize, serialize, Decodable, Decoder, Encodable, Encoder};
+use bitcoin::{Script, Transaction, TxOut, VarInt};
+use bitcoin_hashes::{sha256, Hash};
+#[cfg(feature = "compression")]
+use miniz_oxide::{deflate, inflate};
+use openassets::error;
+use openassets::leb128;
+use openassets::metadata::{recognize_url, MetadataScheme, SchemeRegistry, URL_PREFIX};
+use openassets::quantity::checked_sum;
+#[cfg(feature = "serde")]
+use serde::de::{self, MapAccess, Visitor};
+#[cfg(feature = "serde")]
+use serde::ser::SerializeMap;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+#[cfg(feature = "url")]
+use url::Url;
 
 pub const MARKER: u16 = 0x4f41;
 pub const VERSION: u16 = 0x0100;
 
+/// The largest asset quantity allowed by the Open Assets specification, whose LEB128 encoding
+/// is 9 bytes long.
+pub const MAX_QUANTITY: u64 = (1 << 63) - 1;
+
+/// The size, in bytes, of the data carried by the OP_RETURN output of a transaction relayed
+/// by default.
+pub const MAX_OP_RETURN_SIZE: usize = 80;
+
+/// The size, in bytes, of the OP_RETURN script of a transaction relayed by default, i.e. the
+/// `-datacarriersize` of Bitcoin Core: OP_RETURN and a push of `MAX_OP_RETURN_SIZE` bytes.
+pub const MAX_OP_RETURN_RELAY: usize = MAX_OP_RETURN_SIZE + 3;
+
+/// The prefix of metadata compressed by `Metadata::compressed`, followed by the raw DEFLATE
+/// stream of its content.
+pub const COMPRESSED_PREFIX: &[u8] = b"z=";
+
+/// The prefix of metadata written by `Metadata::contract_hash`, followed by the 32 bytes of the
+/// SHA-256 digest of a contract document.
+pub const CONTRACT_HASH_PREFIX: &[u8] = b"h=";
+
+/// The size, in bytes, the content of compressed metadata must not exceed once decompressed.
+pub const MAX_DECOMPRESSED_SIZE: usize = 64 * 1024;
+
+/// How strictly marker output payloads are encoded and decoded.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash, Default)]
+pub enum PayloadPolicy {
+    /// Quantities above `MAX_QUANTITY` and non-canonical encodings are rejected.
+    Strict,
+    /// Non-canonical encodings are accepted, like openassets-ruby.
+    #[default]
+    Relaxed,
+}
+
+/// A problem found by `Payload::validate` in a payload with regard to its transaction.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum PayloadDiagnostic {
+    /// The marker output is at `index`, past the `outputs` outputs of the transaction.
+    MarkerOutOfRange { index: usize, outputs: usize },
+    /// The payload lists more quantities than there are non-marker outputs.
+    TooManyQuantities { quantities: usize, outputs: usize },
+    /// The `count` quantities from `start` onwards are all zero, and could be omitted.
+    TrailingZeroQuantities { start: usize, count: usize },
+}
+
+impl PayloadDiagnostic {
+    /// Returns true if the problem makes the transaction invalid under the Open Assets
+    /// specification, rather than merely suspicious.
+    pub fn is_spec_violation(&self) -> bool {
+        match *self {
+            PayloadDiagnostic::MarkerOutOfRange { .. }
+            | PayloadDiagnostic::TooManyQuantities { .. } => true,
+            PayloadDiagnostic::TrailingZeroQuantities { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for PayloadDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PayloadDiagnostic::MarkerOutOfRange { index, outputs } => write!(
+                f,
+                "marker output #{} out of range of {} outputs",
+                index, outputs
+            ),
+            PayloadDiagnostic::TooManyQuantities {
+                quantities,
+                outputs,
+            } => write!(
+                f,
+                "{} quantities for {} non-marker outputs",
+                quantities, outputs
+            ),
+            PayloadDiagnostic::TrailingZeroQuantities { start, count } => write!(
+                f,
+                "{} trailing zero quantities from quantity #{}",
+                count, start
+            ),
+        }
+    }
+}
+
+/// A marker output script larger than the relay policy of Bitcoin Core allows.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct StandardnessError {
+    /// The size, in bytes, of the script.
+    pub script_size: usize,
+    /// The size, in bytes, of the largest script relayed, `MAX_OP_RETURN_RELAY` by default.
+    pub limit: usize,
+}
+
+impl fmt::Display for StandardnessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "marker output script of {} bytes exceeds the relay limit of {} bytes",
+            self.script_size, self.limit
+        )
+    }
+}
+
+impl ::std::error::Error for StandardnessError {}
+
+/// How the scripts of marker outputs carry payloads.
+///
+/// Only `CarrierPolicy::Standard` follows the specification, the other placements are meant
+/// for experimental deployments relying on relays accepting such scripts. Colored transactions
+/// are only recognized by this crate in the standard placement.
+#[derive(PartialEq, Eq, Debug, Clone, Hash, Default)]
+pub enum CarrierPolicy {
+    /// OP_RETURN followed by a single push of the payload.
+    #[default]
+    Standard,
+    /// OP_RETURN followed by pushes of at most `chunk_size` bytes, concatenated into the
+    /// payload.
+    Split { chunk_size: usize },
+    /// OP_RETURN followed by a single push of the prefix and the payload.
+    Prefixed(Vec<u8>),
+}
+
+impl CarrierPolicy {
+    /// Returns the script carrying `payload`, failing if a quantity exceeds `MAX_QUANTITY`.
+    pub fn to_script(&self, payload: &Payload) -> Result<Script, error::Error> {
+        let data = payload.to_vec()?;
+        let builder = Builder::new().push_opcode(opcodes::all::OP_RETURN);
+        Ok(match *self {
+            CarrierPolicy::Standard => builder.push_slice(&data),
+            CarrierPolicy::Split { chunk_size } => data
+                .chunks(chunk_size.max(1))
+                .fold(builder, |b, chunk| b.push_slice(chunk)),
+            CarrierPolicy::Prefixed(ref prefix) => {
+                builder.push_slice(&[&prefix[..], &data[..]].concat())
+            }
+        }
+        .into_script())
+    }
+
+    /// Returns the size, in bytes, of the script carrying a payload of `payload_len` bytes,
+    /// without building it.
+    pub fn script_len(&self, payload_len: usize) -> usize {
+        let push_len = |n: usize| {
+            n + match n {
+                0..=75 => 1,
+                76..=0xff => 2,
+                0x100..=0xffff => 3,
+                _ => 5,
+            }
+        };
+        1 + match *self {
+            CarrierPolicy::Standard => push_len(payload_len),
+            CarrierPolicy::Split { chunk_size } => {
+                let chunk_size = chunk_size.max(1);
+                let full = payload_len / chunk_size;
+                let rest = payload_len % chunk_size;
+                full * push_len(chunk_size) + if rest > 0 { push_len(rest) } else { 0 }
+            }
+            CarrierPolicy::Prefixed(ref prefix) => push_len(prefix.len() + payload_len),
+        }
+    }
+
+    /// Returns the payload carried by `script`, failing if `script` does not carry one as this
+    /// policy places it.
+    pub fn from_script(&self, script: &Script) -> Result<Payload, Error> {
+        if !script.is_op_return() {
+            return Err(Error::ParseFailed("Not an OP_RETURN script."));
+        }
+        match *self {
+            CarrierPolicy::Standard => script.get_oa_payload(),
+            CarrierPolicy::Split { .. } => {
+                let mut data = vec![];
+                for instruction in script.iter(false).skip(1) {
+                    match instruction {
+                        Instruction::PushBytes(chunk) => data.extend_from_slice(chunk),
+                        _ => return Err(Error::ParseFailed("Non-push opcode after OP_RETURN.")),
+                    }
+                }
+                deserialize(&data)
+            }
+            CarrierPolicy::Prefixed(ref prefix) => {
+                let data = script.get_op_return_data();
+                if !data.starts_with(prefix) {
+                    return Err(Error::ParseFailed("Missing carrier prefix."));
+                }
+                deserialize(&data[prefix.len()..])
+            }
+        }
+    }
+}
+
+/// A version of the Open Assets protocol, as found in marker outputs.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash, Default)]
+pub enum MarkerVersion {
+    /// Version 1.0, the only version defined by the specification.
+    #[default]
+    V1,
+    /// A version unknown to this crate, e.g. one defined after it was released.
+    Unknown(u16),
+}
+
+impl MarkerVersion {
+    pub fn from_u16(version: u16) -> MarkerVersion {
+        match version {
+            VERSION => MarkerVersion::V1,
+            version => MarkerVersion::Unknown(version),
+        }
+    }
+
+    pub fn to_u16(self) -> u16 {
+        match self {
+            MarkerVersion::V1 => VERSION,
+            MarkerVersion::Unknown(version) => version,
+        }
+    }
+}
+
+/// Serialized as the number encoded in marker outputs, e.g. 256 for version 1.0.
+#[cfg(feature = "serde")]
+impl Serialize for MarkerVersion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u16(self.to_u16())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for MarkerVersion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        u16::deserialize(deserializer).map(MarkerVersion::from_u16)
+    }
+}
+
+impl fmt::Display for MarkerVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let version = self.to_u16();
+        write!(f, "{}.{}", version >> 8, version & 0xff)
+    }
+}
+
 #[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Payload {
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub version: MarkerVersion,
     pub quantities: Vec<u64>,
     pub metadata: Metadata,
 }
 
+impl Payload {
+    /// Creates a version 1.0 payload.
+    pub fn new(quantities: Vec<u64>, metadata: Metadata) -> Payload {
+        Payload {
+            version: MarkerVersion::V1,
+            quantities,
+            metadata,
+        }
+    }
+
+    /// Returns the marker output carrying this payload, i.e. an output of zero value locked by
+    /// OP_RETURN followed by a push of the serialized payload.
+    ///
+    /// Fails if a quantity exceeds `MAX_QUANTITY`.
+    pub fn to_txout(&self) -> Result<TxOut, error::Error> {
+        self.to_txout_with(&CarrierPolicy::Standard)
+    }
+
+    /// Returns the marker output carrying this payload as placed by `policy`, failing if a
+    /// quantity exceeds `MAX_QUANTITY`.
+    pub fn to_txout_with(&self, policy: &CarrierPolicy) -> Result<TxOut, error::Error> {
+        Ok(TxOut {
+            value: 0,
+            script_pubkey: policy.to_script(self)?,
+        })
+    }
+
+    /// Returns the marker output carrying this payload as placed by `carrier`, failing if a
+    /// quantity exceeds `MAX_QUANTITY` or with `Error::NonStandardMarker` if its script would
+    /// not be relayed by default.
+    pub fn to_standard_txout(&self, carrier: &CarrierPolicy) -> Result<TxOut, error::Error> {
+        self.to_standard_txout_with(carrier, MAX_OP_RETURN_RELAY)
+    }
+
+    /// Same as `to_standard_txout`, for relays accepting OP_RETURN scripts of at most `limit`
+    /// bytes.
+    pub fn to_standard_txout_with(
+        &self,
+        carrier: &CarrierPolicy,
+        limit: usize,
+    ) -> Result<TxOut, error::Error> {
+        let txout = self.to_txout_with(carrier)?;
+        self.check_standardness_with(carrier, limit)
+            .map_err(error::Error::NonStandardMarker)?;
+        Ok(txout)
+    }
+
+    /// Checks that the script carrying this payload as placed by `carrier` is at most
+    /// `MAX_OP_RETURN_RELAY` bytes long, so that the transaction is relayed by default.
+    pub fn check_standardness(&self, carrier: &CarrierPolicy) -> Result<(), StandardnessError> {
+        self.check_standardness_with(carrier, MAX_OP_RETURN_RELAY)
+    }
+
+    /// Same as `check_standardness`, for relays accepting OP_RETURN scripts of at most `limit`
+    /// bytes.
+    pub fn check_standardness_with(
+        &self,
+        carrier: &CarrierPolicy,
+        limit: usize,
+    ) -> Result<(), StandardnessError> {
+        check_script_len(carrier.script_len(self.encoded_len()), limit)
+    }
+
+    /// Returns the sum of the quantities, failing with `Error::QuantityOverflow` if it exceeds
+    /// `u64::MAX`.
+    pub fn total_quantity(&self) -> Result<u64, error::Error> {
+        checked_sum(self.quantities.iter().cloned())
+    }
+
+    /// Checks the payload against the transaction carrying it, whose `n_outputs` outputs
+    /// include the marker output at `marker_index`.
+    ///
+    /// Returns the problems found, none if the payload fits the transaction.
+    pub fn validate(&self, marker_index: usize, n_outputs: usize) -> Vec<PayloadDiagnostic> {
+        let mut diagnostics = vec![];
+        if marker_index >= n_outputs {
+            diagnostics.push(PayloadDiagnostic::MarkerOutOfRange {
+                index: marker_index,
+                outputs: n_outputs,
+            });
+        }
+        let outputs = n_outputs.saturating_sub(1);
+        if self.quantities.len() > outputs {
+            diagnostics.push(PayloadDiagnostic::TooManyQuantities {
+                quantities: self.quantities.len(),
+                outputs,
+            });
+        }
+        let count = self
+            .quantities
+            .iter()
+            .rev()
+            .take_while(|&&q| q == 0)
+            .count();
+        if count > 0 {
+            diagnostics.push(PayloadDiagnostic::TrailingZeroQuantities {
+                start: self.quantities.len() - count,
+                count,
+            });
+        }
+        diagnostics
+    }
+
+    /// Returns the size, in bytes, of the serialized payload, without serializing it.
+    pub fn encoded_len(&self) -> usize {
+        let quantities: usize = self
+            .quantities
+            .iter()
+            .map(|&q| leb128::encoded_len(q))
+            .sum();
+        let metadata = self.metadata.len();
+        4 + VarInt(self.quantities.len() as u64).encoded_length() as usize
+            + quantities
+            + VarInt(metadata as u64).encoded_length() as usize
+            + metadata
+    }
+
+    /// Returns true if the payload fits in the OP_RETURN output of a transaction relayed by
+    /// default, i.e. is at most `MAX_OP_RETURN_SIZE` bytes long.
+    pub fn fits_standard_op_return(&self) -> bool {
+        self.encoded_len() <= MAX_OP_RETURN_SIZE
+    }
+
+    /// Returns the hex encoding of the serialized payload, failing if a quantity exceeds
+    /// `MAX_QUANTITY`.
+    pub fn to_hex(&self) -> Result<String, error::Error> {
+        self.to_vec().map(hex::encode)
+    }
+
+    /// Encodes the payload, failing with `Error::QuantityTooLarge` if a quantity exceeds
+    /// `MAX_QUANTITY`.
+    ///
+    /// Every marker output built by this crate is encoded here, so that it never produces
+    /// invalid markers. `serialize` writes back any payload decoded by `deserialize`, including
+    /// ones holding such quantities.
+    pub fn to_vec(&self) -> Result<Vec<u8>, error::Error> {
+        if let Some(&quantity) = self.quantities.iter().find(|&&q| q > MAX_QUANTITY) {
+            return Err(error::Error::QuantityTooLarge {
+                quantity,
+                limit: MAX_QUANTITY,
+            });
+        }
+        Ok(serialize(self))
+    }
+
+    /// Decodes a payload from `data` under `policy`, failing unless `data` is consumed entirely.
+    pub fn from_slice(data: &[u8], policy: PayloadPolicy) -> Result<Payload, Error> {
+        Payload::from_slice_with_version(data, policy, false)
+    }
+
+    /// Same as `from_slice`, also decoding payloads of versions unknown to this crate, laid out
+    /// as version 1.0 payloads.
+    ///
+    /// Such payloads are not marker outputs to this crate, but may be displayed as ones, e.g. by
+    /// block explorers.
+    pub fn from_slice_any_version(data: &[u8], policy: PayloadPolicy) -> Result<Payload, Error> {
+        Payload::from_slice_with_version(data, policy, true)
+    }
+
+    /// Decodes a payload from the start of `data` under `policy`, returning it along with the
+    /// number of bytes it took. Unlike `from_slice`, the bytes following it are ignored.
+    ///
+    /// Markers whose push holds more than the payload are invalid, so this is only meant for
+    /// inspecting malformed markers.
+    pub fn from_slice_prefix(
+        data: &[u8],
+        policy: PayloadPolicy,
+    ) -> Result<(Payload, usize), Error> {
+        let mut cursor = Cursor::new(data);
+        let payload = Payload::decode(&mut cursor, policy, false)?;
+        Ok((payload, cursor.position() as usize))
+    }
+
+    fn from_slice_with_version(
+        data: &[u8],
+        policy: PayloadPolicy,
+        any_version: bool,
+    ) -> Result<Payload, Error> {
+        let mut cursor = Cursor::new(data);
+        let payload = Payload::decode(&mut cursor, policy, any_version)?;
+        if cursor.position() as usize == data.len() {
+            Ok(payload)
+        } else {
+            Err(Error::ParseFailed(
+                "data not consumed entirely when explicitly deserializing",
+            ))
+        }
+    }
+
+    fn decode<D: Decoder>(
+        d: &mut D,
+        policy: PayloadPolicy,
+        any_version: bool,
+    ) -> Result<Payload, Error> {
+        let marker: u16 = Decodable::consensus_decode(d)?;
+        if marker != MARKER.to_be() {
+            return Err(Error::ParseFailed("Invalid marker."));
+        }
+
+        let version: u16 = Decodable::consensus_decode(d)?;
+        let version = MarkerVersion::from_u16(u16::from_be(version));
+        if version != MarkerVersion::V1 && !any_version {
+            return Err(Error::ParseFailed("Invalid version."));
+        }
+
+        let VarInt(count): VarInt = Decodable::consensus_decode(d)?;
+        // the count is untrusted: each quantity takes at least one byte, so no standard payload
+        // holds more quantities than fit in the largest relayed script
+        let mut quantities: Vec<u64> =
+            Vec::with_capacity(cmp::min(count, MAX_OP_RETURN_RELAY as u64) as usize);
+
+        for _ in 0..count {
+            quantities.push(read_quantity(d, policy)?);
+        }
+
+        let payload = Payload {
+            version,
+            quantities,
+            metadata: Decodable::consensus_decode(d)?,
+        };
+        Ok(payload)
+    }
+}
+
+/// Reads a LEB128 encoded asset quantity.
+///
+/// Encodings of quantities exceeding 64 bits are rejected rather than wrapped, as are, under
+/// `PayloadPolicy::Strict`, encodings padded with trailing zero groups.
+fn read_quantity<D: Decoder>(d: &mut D, policy: PayloadPolicy) -> Result<u64, Error> {
+    let (value, length) = leb128::decode_from(d)?;
+    if policy == PayloadPolicy::Strict {
+        if length > leb128::encoded_len(value) {
+            return Err(Error::ParseFailed("Non-canonical asset quantity."));
+        }
+        if value > MAX_QUANTITY {
+            return Err(Error::ParseFailed("Asset quantity exceeds the maximum."));
+        }
+    }
+    Ok(value)
+}
+
+/// Displays the payload on one line, e.g.
+/// `OA 1.0 marker, quantities [100, 0, 123], metadata u=https://cpr.sm/5YgSU1Pg-q`.
+impl fmt::Display for Payload {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "OA {} marker, quantities {:?}, metadata {}",
+            self.version, self.quantities, self.metadata
+        )
+    }
+}
+
 impl<S: Encoder> Encodable<S> for Payload {
     fn consensus_encode(&self, s: &mut S) -> Result<(), Error> {
         MARKER.to_be().consensus_encode(s)?;
-        VERSION.to_be().consensus_encode(s)?;
+        self.version.to_u16().to_be().consensus_encode(s)?;
         VarInt(self.quantities.len() as u64).consensus_encode(s)?;
         // asset quantity
         for &q in self.quantities.iter() {
-            let mut value: u64 = q;
-            loop {
-                let mut byte = value & 0x7F;
-                value >>= 7;
-                if value != 0 {
-                    byte |= 0x80;
-                }
-                Encodable::consensus_encode(&(byte as u8), s)?;
-                if value == 0 {
-                    break;
-                }
-            }
+            leb128::encode_into(q, s)?;
         }
         self.metadata.consensus_encode(s)?;
         Ok(())
@@ -41,116 +542,539 @@ impl<S: Encoder> Encodable<S> for Payload {
 
 impl<D: Decoder> Decodable<D> for Payload {
     fn consensus_decode(d: &mut D) -> Result<Payload, Error> {
-        let marker: u16 = Decodable::consensus_decode(d)?;
+        Payload::decode(d, PayloadPolicy::Relaxed, false)
+    }
+}
+
+/// A payload borrowing its quantities and metadata from the data it is parsed from, sparing
+/// the allocations of `Payload` when scanning many outputs.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct PayloadRef<'a> {
+    count: usize,
+    quantities: &'a [u8],
+    metadata: &'a [u8],
+}
+
+impl<'a> PayloadRef<'a> {
+    /// Parses a version 1.0 payload from `data` under `policy`, failing unless `data` is
+    /// consumed entirely, like `Payload::from_slice`.
+    pub fn parse(data: &'a [u8], policy: PayloadPolicy) -> Result<PayloadRef<'a>, Error> {
+        let mut cursor = Cursor::new(data);
+        let marker: u16 = Decodable::consensus_decode(&mut cursor)?;
         if marker != MARKER.to_be() {
             return Err(Error::ParseFailed("Invalid marker."));
         }
-
-        let version: u16 = Decodable::consensus_decode(d)?;
+        let version: u16 = Decodable::consensus_decode(&mut cursor)?;
         if version != VERSION.to_be() {
             return Err(Error::ParseFailed("Invalid version."));
         }
 
-        let VarInt(count): VarInt = Decodable::consensus_decode(d)?;
-        let mut quantities: Vec<u64> = Vec::with_capacity(count as usize);
-
+        let VarInt(count): VarInt = Decodable::consensus_decode(&mut cursor)?;
+        let start = cursor.position() as usize;
         for _ in 0..count {
-            let mut value: u64 = 0;
-            let mut offset: u64 = 0;
-            loop {
-                let b: u8 = Decodable::consensus_decode(d)?;
-                value |= ((b as u64) & 0x7f) << offset;
-                if (b as u64) & 0x80 == 0 {
-                    break;
-                }
-                offset += 7;
-            }
-            quantities.push(value);
+            read_quantity(&mut cursor, policy)?;
         }
+        let end = cursor.position() as usize;
 
-        let payload = Payload {
-            quantities,
-            metadata: Decodable::consensus_decode(d)?,
-        };
-        return Ok(payload);
+        let VarInt(length): VarInt = Decodable::consensus_decode(&mut cursor)?;
+        let metadata = &data[cursor.position() as usize..];
+        if (metadata.len() as u64) < length {
+            return Err(Error::ParseFailed("Metadata shorter than its length."));
+        }
+        if metadata.len() as u64 != length {
+            return Err(Error::ParseFailed(
+                "data not consumed entirely when explicitly deserializing",
+            ));
+        }
+        Ok(PayloadRef {
+            count: count as usize,
+            quantities: &data[start..end],
+            metadata,
+        })
+    }
+
+    /// Parses the payload of the marker output locked by `script`, i.e. the data of its first
+    /// push following OP_RETURN.
+    pub fn from_script(script: &'a Script) -> Result<PayloadRef<'a>, Error> {
+        if !script.is_op_return() {
+            return Err(Error::ParseFailed("Not an OP_RETURN script."));
+        }
+        match script.iter(false).nth(1) {
+            Some(Instruction::PushBytes(data)) => PayloadRef::parse(data, PayloadPolicy::Relaxed),
+            _ => Err(Error::ParseFailed("No data pushed after OP_RETURN.")),
+        }
+    }
+
+    /// Returns the number of asset quantities.
+    pub fn quantity_count(&self) -> usize {
+        self.count
+    }
+
+    /// Returns an iterator decoding the asset quantities.
+    pub fn quantities(&self) -> Quantities<'a> {
+        Quantities {
+            cursor: Cursor::new(self.quantities),
+            remaining: self.count,
+        }
+    }
+
+    /// Returns the metadata.
+    pub fn metadata(&self) -> &'a [u8] {
+        self.metadata
+    }
+
+    /// Returns the payload owning copies of the quantities and metadata.
+    pub fn to_payload(&self) -> Payload {
+        Payload::new(
+            self.quantities().collect(),
+            Metadata::new(self.metadata.to_vec()),
+        )
     }
 }
 
-#[derive(PartialEq, Eq, Debug, Clone)]
-pub struct Metadata(Vec<u8>);
+/// An iterator over the asset quantities of a `PayloadRef`.
+#[derive(Debug, Clone)]
+pub struct Quantities<'a> {
+    cursor: Cursor<&'a [u8]>,
+    remaining: usize,
+}
+
+impl<'a> Iterator for Quantities<'a> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        // the quantities were validated when parsing the payload
+        read_quantity(&mut self.cursor, PayloadPolicy::Relaxed).ok()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a> ExactSizeIterator for Quantities<'a> {}
+
+/// The metadata of a marker output, by kind.
+///
+/// Encoded as is, `Metadata::Url` as `u=<url>` following the convention of Coinprism. Metadata
+/// decoded from a payload is always of the most specific kind, as returned by `Metadata::new`:
+/// `Metadata::Binary` never holds empty data nor data encoding a URL. Metadata compare by
+/// encoding, so `Metadata::Binary(b"u=<url>")` equals `Metadata::Url(<url>)`.
+#[derive(Debug, Clone)]
+pub enum Metadata {
+    /// No metadata.
+    Empty,
+    /// The URL of an asset definition file.
+    Url(String),
+    /// Any other metadata.
+    Binary(Vec<u8>),
+}
+
+impl Metadata {
+    /// Returns the metadata encoded as `data`, of the most specific kind.
+    pub fn new(data: Vec<u8>) -> Metadata {
+        if data.is_empty() {
+            return Metadata::Empty;
+        }
+        match recognize_url(&data) {
+            Some(MetadataScheme::Url(url)) => Metadata::Url(url),
+            _ => Metadata::Binary(data),
+        }
+    }
+
+    /// Returns true if the metadata encodes to no data.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the size, in bytes, of the encoded metadata.
+    pub fn len(&self) -> usize {
+        match *self {
+            Metadata::Empty => 0,
+            Metadata::Url(ref url) => URL_PREFIX.len() + url.len(),
+            Metadata::Binary(ref data) => data.len(),
+        }
+    }
+
+    /// Returns the encoded metadata.
+    pub fn into_bytes(self) -> Vec<u8> {
+        match self {
+            Metadata::Empty => vec![],
+            Metadata::Url(url) => format!("{}{}", URL_PREFIX, url).into_bytes(),
+            Metadata::Binary(data) => data,
+        }
+    }
+
+    /// Returns the metadata holding `content` compressed with DEFLATE, after
+    /// `COMPRESSED_PREFIX`.
+    ///
+    /// Only readers supporting the scheme understand such metadata, and the result may not be
+    /// shorter than `content` for short or random contents.
+    #[cfg(feature = "compression")]
+    pub fn compressed(content: &[u8]) -> Metadata {
+        let mut data = COMPRESSED_PREFIX.to_vec();
+        data.extend_from_slice(&deflate::compress_to_vec(content, 10));
+        Metadata::new(data)
+    }
+
+    /// Returns whether the metadata starts with `COMPRESSED_PREFIX`.
+    pub fn is_compressed(&self) -> bool {
+        self.as_bytes().starts_with(COMPRESSED_PREFIX)
+    }
+
+    /// Returns the content of the metadata, decompressed if it is compressed.
+    ///
+    /// Fails if compressed metadata is corrupt or expands beyond `MAX_DECOMPRESSED_SIZE`.
+    #[cfg(feature = "compression")]
+    pub fn content(&self) -> Result<Cow<'_, [u8]>, Error> {
+        let data = self.as_bytes();
+        if !data.starts_with(COMPRESSED_PREFIX) {
+            return Ok(data);
+        }
+        inflate::decompress_to_vec_with_limit(
+            &data[COMPRESSED_PREFIX.len()..],
+            MAX_DECOMPRESSED_SIZE,
+        )
+        .map(Cow::Owned)
+        .map_err(|_| Error::ParseFailed("Invalid compressed metadata."))
+    }
+
+    /// Returns metadata committing to the contract `document`, e.g. the terms of a security
+    /// token, as `h=` followed by the SHA-256 digest of the document.
+    pub fn contract_hash(document: &[u8]) -> Metadata {
+        let mut data = CONTRACT_HASH_PREFIX.to_vec();
+        data.extend_from_slice(&sha256::Hash::hash(document)[..]);
+        Metadata::new(data)
+    }
+
+    /// Returns the digest of the contract the metadata commits to, if it is written by
+    /// `Metadata::contract_hash`.
+    pub fn contract_commitment(&self) -> Option<sha256::Hash> {
+        let data = self.as_bytes();
+        if data.len() != CONTRACT_HASH_PREFIX.len() + 32 || !data.starts_with(CONTRACT_HASH_PREFIX)
+        {
+            return None;
+        }
+        sha256::Hash::from_slice(&data[CONTRACT_HASH_PREFIX.len()..]).ok()
+    }
+
+    /// Returns true if the metadata commits to the contract `document`.
+    pub fn verify_contract(&self, document: &[u8]) -> bool {
+        self.contract_commitment() == Some(sha256::Hash::hash(document))
+    }
+
+    /// Returns the encoded metadata, borrowed unless it is a URL.
+    pub fn as_bytes(&self) -> Cow<'_, [u8]> {
+        match *self {
+            Metadata::Empty => Cow::Borrowed(&[]),
+            Metadata::Url(ref url) => Cow::Owned(format!("{}{}", URL_PREFIX, url).into_bytes()),
+            Metadata::Binary(ref data) => Cow::Borrowed(data),
+        }
+    }
+
+    /// Returns the encoded metadata as a string, failing if it is not valid UTF-8.
+    pub fn as_str(&self) -> Result<Cow<'_, str>, Utf8Error> {
+        match *self {
+            Metadata::Empty => Ok(Cow::Borrowed("")),
+            Metadata::Url(ref url) => Ok(Cow::Owned(format!("{}{}", URL_PREFIX, url))),
+            Metadata::Binary(ref data) => str::from_utf8(data).map(Cow::Borrowed),
+        }
+    }
+
+    /// Returns what the metadata refers to, as recognized by the default `SchemeRegistry`.
+    pub fn scheme(&self) -> MetadataScheme {
+        SchemeRegistry::default().recognize(self)
+    }
 
+    /// Returns the hex encoding of the metadata.
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.as_bytes())
+    }
+
+    /// Decodes metadata from its hex encoding, e.g. as returned by block explorers.
+    pub fn from_hex(s: &str) -> Result<Metadata, Error> {
+        hex::decode(s)
+            .map(Metadata::new)
+            .map_err(|_| Error::ParseFailed("Invalid metadata hex."))
+    }
+
+    /// Returns the standard base64 encoding of the metadata, with padding.
+    pub fn to_base64(&self) -> String {
+        base64::encode(self.as_bytes())
+    }
+
+    /// Decodes metadata from its standard base64 encoding, with padding.
+    pub fn from_base64(s: &str) -> Result<Metadata, Error> {
+        base64::decode(s)
+            .map(Metadata::new)
+            .map_err(|_| Error::ParseFailed("Invalid metadata base64."))
+    }
+
+    /// Checks that the metadata fits in a marker output relayed by default, as placed by
+    /// `carrier` in a payload without quantities.
+    ///
+    /// Quantities take room too, so a payload carrying the metadata may still be too large:
+    /// check it with `Payload::check_standardness` once its quantities are known.
+    pub fn check_standardness(&self, carrier: &CarrierPolicy) -> Result<(), StandardnessError> {
+        let len = self.len();
+        let payload_len = 4 + 1 + VarInt(len as u64).encoded_length() as usize + len;
+        check_script_len(carrier.script_len(payload_len), MAX_OP_RETURN_RELAY)
+    }
+
+    /// Returns the URL of the asset definition file if the metadata follows the `u=<url>`
+    /// convention of Coinprism, as written by `TransferBuilder::with_metadata_url`.
+    #[cfg(feature = "url")]
+    pub fn asset_definition_url(&self) -> Option<Url> {
+        match *self {
+            Metadata::Url(ref url) => Url::parse(url).ok(),
+            _ => None,
+        }
+    }
+}
+
+impl PartialEq for Metadata {
+    fn eq(&self, other: &Metadata) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl Eq for Metadata {}
+
+impl From<Vec<u8>> for Metadata {
+    fn from(data: Vec<u8>) -> Metadata {
+        Metadata::new(data)
+    }
+}
+
+impl From<Metadata> for Vec<u8> {
+    fn from(metadata: Metadata) -> Vec<u8> {
+        metadata.into_bytes()
+    }
+}
+
+fn check_script_len(script_size: usize, limit: usize) -> Result<(), StandardnessError> {
+    if script_size > limit {
+        return Err(StandardnessError { script_size, limit });
+    }
+    Ok(())
+}
+
+/// Displays the metadata as a string if it is valid UTF-8, and hex encoded otherwise.
 impl fmt::Display for Metadata {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match String::from_utf8(self.0.clone()) {
+        match self.as_str() {
             Ok(s) => write!(f, "{}", s),
-            _ => panic!("invalid utf-8 string") 
+            Err(_) => write!(f, "{}", self.to_hex()),
         }
     }
 }
 
+/// Serialized as a string if the metadata is valid UTF-8, and as `{"hex": "<hex>"}` otherwise.
+#[cfg(feature = "serde")]
+impl Serialize for Metadata {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.as_str() {
+            Ok(s) => serializer.serialize_str(&s),
+            Err(_) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("hex", &self.to_hex())?;
+                map.end()
+            }
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Metadata {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct MetadataVisitor;
+
+        impl<'de> Visitor<'de> for MetadataVisitor {
+            type Value = Metadata;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a string or a map with a hex entry")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Metadata, E> {
+                Ok(Metadata::new(v.as_bytes().to_vec()))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Metadata, A::Error> {
+                let mut data = None;
+                while let Some(key) = map.next_key::<String>()? {
+                    if key != "hex" {
+                        return Err(de::Error::unknown_field(&key, &["hex"]));
+                    }
+                    let hex: String = map.next_value()?;
+                    data = Some(hex::decode(&hex).map_err(de::Error::custom)?);
+                }
+                data.map(Metadata::new)
+                    .ok_or_else(|| de::Error::missing_field("hex"))
+            }
+        }
+
+        deserializer.deserialize_any(MetadataVisitor)
+    }
+}
+
 impl<S: Encoder> Encodable<S> for Metadata {
     fn consensus_encode(&self, s: &mut S) -> Result<(), Error> {
-        self.0.consensus_encode(s)
+        VarInt(self.len() as u64).consensus_encode(s)?;
+        let (prefix, data): (&[u8], &[u8]) = match *self {
+            Metadata::Empty => (&[], &[]),
+            Metadata::Url(ref url) => (URL_PREFIX.as_bytes(), url.as_bytes()),
+            Metadata::Binary(ref data) => (&[], data),
+        };
+        for &byte in prefix.iter().chain(data) {
+            s.emit_u8(byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl<D: Decoder> Decodable<D> for Metadata {
+    fn consensus_decode(d: &mut D) -> Result<Metadata, Error> {
+        Ok(Metadata::new(Decodable::consensus_decode(d)?))
+    }
+}
+
+pub trait TxOutExt {
+    fn get_op_return_data(&self) -> Vec<u8>;
+
+    fn is_openassets_marker(&self) -> bool;
+
+    fn get_oa_payload(&self) -> Result<Payload, Error>;
+
+    /// Returns the data of every push following OP_RETURN, in script order.
+    fn get_all_op_return_pushes(&self) -> Vec<Vec<u8>>;
+
+    /// Returns the payload of the first push following OP_RETURN which parses as one.
+    ///
+    /// Unlike `get_oa_payload`, which follows the specification by only reading the first push,
+    /// this also finds payloads preceded by other data, as emitted by some wallets.
+    fn find_oa_payload(&self) -> Result<Payload, Error>;
+}
+
+impl TxOutExt for TxOut {
+    fn get_op_return_data(&self) -> Vec<u8> {
+        self.script_pubkey.get_op_return_data()
+    }
+
+    fn is_openassets_marker(&self) -> bool {
+        self.script_pubkey.is_openassets_marker()
+    }
+
+    fn get_oa_payload(&self) -> Result<Payload, Error> {
+        self.script_pubkey.get_oa_payload()
+    }
+
+    fn get_all_op_return_pushes(&self) -> Vec<Vec<u8>> {
+        self.script_pubkey.get_all_op_return_pushes()
     }
-}
 
-impl<D: Decoder> Decodable<D> for Metadata {
-    fn consensus_decode(d: &mut D) -> Result<Metadata, Error> {
-        Ok(Metadata(Decodable::consensus_decode(d)?))
+    fn find_oa_payload(&self) -> Result<Payload, Error> {
+        self.script_pubkey.find_oa_payload()
     }
 }
 
-pub trait TxOutExt {
+/// Same as `TxOutExt`, for the script locking an output.
+pub trait ScriptExt {
     fn get_op_return_data(&self) -> Vec<u8>;
 
     fn is_openassets_marker(&self) -> bool;
 
     fn get_oa_payload(&self) -> Result<Payload, Error>;
+
+    /// See `TxOutExt::get_all_op_return_pushes`.
+    fn get_all_op_return_pushes(&self) -> Vec<Vec<u8>>;
+
+    /// See `TxOutExt::find_oa_payload`.
+    fn find_oa_payload(&self) -> Result<Payload, Error>;
 }
 
-impl TxOutExt for TxOut {
+impl ScriptExt for Script {
     fn get_op_return_data(&self) -> Vec<u8> {
-        if self.script_pubkey.is_op_return() {
-            let mut script_iter = self.script_pubkey.iter(false);
+        if self.is_op_return() {
+            let mut script_iter = self.iter(false);
             script_iter.next(); // OP_RETURN
-            let item = script_iter.next();
-            if item.is_some() {
-                return match item.unwrap() {
-                    Instruction::PushBytes(value) => value.to_vec(),
-                    _ => vec![],
-                };
-            } else {
-                return vec![];
+            match script_iter.next() {
+                Some(Instruction::PushBytes(value)) => value.to_vec(),
+                _ => vec![],
             }
         } else {
-            return vec![];
+            vec![]
         }
     }
 
     fn is_openassets_marker(&self) -> bool {
-        if self.script_pubkey.is_op_return() {
+        if self.is_op_return() {
             let payload: Result<Payload, _> = self.get_oa_payload();
-            return payload.is_ok();
+            payload.is_ok()
         } else {
-            return false;
+            false
         }
     }
 
     fn get_oa_payload(&self) -> Result<Payload, Error> {
         let op_return_data: Vec<u8> = self.get_op_return_data();
-        let payload: Result<Payload, _> = deserialize(&op_return_data);
-        return payload;
+        deserialize(&op_return_data)
+    }
+
+    fn get_all_op_return_pushes(&self) -> Vec<Vec<u8>> {
+        if !self.is_op_return() {
+            return vec![];
+        }
+        self.iter(false)
+            .skip(1) // OP_RETURN
+            .filter_map(|i| match i {
+                Instruction::PushBytes(value) => Some(value.to_vec()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn find_oa_payload(&self) -> Result<Payload, Error> {
+        self.get_all_op_return_pushes()
+            .iter()
+            .filter_map(|data| deserialize(data).ok())
+            .next()
+            .ok_or(Error::ParseFailed("No Open Assets payload."))
     }
 }
 
+/// Returns the index and payload of every output of `tx` which is a valid marker output.
+///
+/// Only the first one is the marker output of the transaction, the others are regular outputs.
+pub fn marker_candidates(tx: &Transaction) -> Vec<(usize, Payload)> {
+    tx.output
+        .iter()
+        .enumerate()
+        .filter(|&(_, o)| o.script_pubkey.is_op_return())
+        .filter_map(|(i, o)| o.get_oa_payload().ok().map(|p| (i, p)))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
+    use bitcoin::blockdata::opcodes;
     use bitcoin::blockdata::script::Builder;
+    use bitcoin::consensus::deserialize;
     use bitcoin::consensus::serialize;
     use bitcoin::util::misc::hex_bytes;
-    use bitcoin::{Script, TxOut};
+    use bitcoin::{Script, Transaction, TxOut};
     use hex::decode as hex_decode;
-    use openassets::marker_output::{Metadata, Payload, TxOutExt};
+    use openassets::error;
+    use openassets::marker_output::{
+        marker_candidates, CarrierPolicy, MarkerVersion, Metadata, Payload, PayloadDiagnostic,
+        PayloadPolicy, PayloadRef, ScriptExt, StandardnessError, TxOutExt, CONTRACT_HASH_PREFIX,
+        MAX_OP_RETURN_RELAY, MAX_QUANTITY,
+    };
+    use openassets::metadata::{recognize_url, MetadataScheme};
 
     #[test]
     fn test_op_return_data() {
@@ -304,7 +1228,7 @@ mod tests {
         };
         let payload: Payload = marker_output.get_oa_payload().unwrap();
         assert_eq!(vec![1, 68], payload.quantities);
-        assert_eq!(Vec::<u8>::new(), payload.metadata.0);
+        assert_eq!(Vec::<u8>::new(), payload.metadata.into_bytes());
 
         // binary metadata
         let marker_output = TxOut {
@@ -317,7 +1241,7 @@ mod tests {
         let payload: Payload = marker_output.get_oa_payload().unwrap();
         assert_eq!(
             vec![0x01, 0x02, 0x03, 0x04, 0xff, 0xfe, 0xfd, 0xfc],
-            payload.metadata.0
+            payload.metadata.into_bytes()
         );
 
         // test for leb128
@@ -331,12 +1255,85 @@ mod tests {
     }
 
     #[test]
-    fn test_encode_payload() {
-        let metadata = Metadata("u=https://cpr.sm/5YgSU1Pg-q".as_bytes().to_vec());
-        let payload = Payload {
-            quantities: vec![100, 0, 123],
-            metadata,
+    fn test_hostile_quantity_count() {
+        // a quantity count of u64::MAX without quantities
+        let data = hex_decode("4f410100ffffffffffffffffff").unwrap();
+        assert!(Payload::from_slice(&data, PayloadPolicy::Relaxed).is_err());
+        assert!(Payload::from_slice_any_version(&data, PayloadPolicy::Strict).is_err());
+        assert!(deserialize::<Payload>(&data).is_err());
+        assert!(PayloadRef::parse(&data, PayloadPolicy::Relaxed).is_err());
+
+        let marker_output = TxOut {
+            value: 0,
+            script_pubkey: Builder::new()
+                .push_opcode(opcodes::all::OP_RETURN)
+                .push_slice(&data)
+                .into_script(),
+        };
+        assert!(!marker_output.is_openassets_marker());
+        assert!(marker_output.get_oa_payload().is_err());
+    }
+
+    #[test]
+    fn test_script_ext() {
+        let script = Builder::from(
+            hex_decode(
+                "6a244f4101000364007b1b753d68747470733a2f2f6370722e736d2f35596753553150672d71",
+            )
+            .unwrap(),
+        )
+        .into_script();
+        assert!(script.is_openassets_marker());
+        assert_eq!(
+            vec![100, 0, 123],
+            script.get_oa_payload().unwrap().quantities
+        );
+
+        let script = Builder::from(hex_decode("6a0401020304").unwrap()).into_script();
+        assert!(!script.is_openassets_marker());
+        assert!(script.get_oa_payload().is_err());
+        let script = Builder::from(
+            hex_decode("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac").unwrap(),
+        )
+        .into_script();
+        assert!(!script.is_openassets_marker());
+    }
+
+    #[test]
+    fn test_find_oa_payload() {
+        // OP_RETURN <junk> <payload>
+        let txout = TxOut {
+            value: 0,
+            script_pubkey: Builder::from(hex_decode("6a03010203084f410100020a0500").unwrap())
+                .into_script(),
+        };
+        assert_eq!(
+            vec![vec![1, 2, 3], hex_decode("4f410100020a0500").unwrap()],
+            txout.get_all_op_return_pushes()
+        );
+        assert!(txout.get_oa_payload().is_err());
+        assert_eq!(vec![10, 5], txout.find_oa_payload().unwrap().quantities);
+
+        let txout = TxOut {
+            value: 0,
+            script_pubkey: Builder::from(hex_decode("6a0301020300").unwrap()).into_script(),
+        };
+        assert!(txout.find_oa_payload().is_err());
+
+        let txout = TxOut {
+            value: 0,
+            script_pubkey: Builder::from(
+                hex_decode("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac").unwrap(),
+            )
+            .into_script(),
         };
+        assert!(txout.get_all_op_return_pushes().is_empty());
+    }
+
+    #[test]
+    fn test_encode_payload() {
+        let metadata = Metadata::new("u=https://cpr.sm/5YgSU1Pg-q".as_bytes().to_vec());
+        let payload = Payload::new(vec![100, 0, 123], metadata);
         let result: Vec<u8> = serialize(&payload);
         assert_eq!(
             hex_decode("4f4101000364007b1b753d68747470733a2f2f6370722e736d2f35596753553150672d71")
@@ -344,20 +1341,14 @@ mod tests {
             result
         );
 
-        let metadata = Metadata(vec![]);
-        let payload = Payload {
-            quantities: vec![1, 68],
-            metadata,
-        };
+        let metadata = Metadata::new(vec![]);
+        let payload = Payload::new(vec![1, 68], metadata);
         let result: Vec<u8> = serialize(&payload);
         assert_eq!(hex_decode("4f41010002014400").unwrap(), result);
 
         // binary metadata
-        let metadata = Metadata(vec![0x01, 0x02, 0x03, 0x04, 0xff, 0xfe, 0xfd, 0xfc]);
-        let payload = Payload {
-            quantities: vec![1, 68],
-            metadata,
-        };
+        let metadata = Metadata::new(vec![0x01, 0x02, 0x03, 0x04, 0xff, 0xfe, 0xfd, 0xfc]);
+        let payload = Payload::new(vec![1, 68], metadata);
         let result: Vec<u8> = serialize(&payload);
         assert_eq!(
             hex_decode("4f4101000201440801020304fffefdfc").unwrap(),
@@ -365,12 +1356,622 @@ mod tests {
         );
 
         // test for leb128
-        let metadata = Metadata(vec![]);
-        let payload = Payload {
-            quantities: vec![127, 128, 12857],
-            metadata,
-        };
+        let metadata = Metadata::new(vec![]);
+        let payload = Payload::new(vec![127, 128, 12857], metadata);
         let result: Vec<u8> = serialize(&payload);
         assert_eq!(hex_decode("4f410100037f8001b96400").unwrap(), result);
     }
+
+    #[test]
+    fn test_metadata() {
+        let metadata = Metadata::new(b"u=https://cpr.sm/5YgSU1Pg-q".to_vec());
+        assert_eq!(
+            &b"u=https://cpr.sm/5YgSU1Pg-q"[..],
+            &metadata.as_bytes()[..]
+        );
+        assert_eq!("u=https://cpr.sm/5YgSU1Pg-q", metadata.as_str().unwrap());
+        assert_eq!(
+            "753d68747470733a2f2f6370722e736d2f35596753553150672d71",
+            metadata.to_hex()
+        );
+
+        // not utf-8
+        let metadata = Metadata::new(vec![0xff, 0xfe, 0x41]);
+        assert!(metadata.as_str().is_err());
+        assert_eq!("fffe41", metadata.to_hex());
+        assert_eq!("fffe41", metadata.to_string());
+    }
+
+    #[test]
+    fn test_contract_hash() {
+        let document = b"The holder is entitled to one share of Example Inc.";
+        let metadata = Metadata::contract_hash(document);
+        assert_eq!(34, metadata.len());
+        assert!(metadata.as_bytes().starts_with(CONTRACT_HASH_PREFIX));
+        assert_eq!(
+            "683dba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            Metadata::contract_hash(b"abc").to_hex()
+        );
+        assert!(metadata.verify_contract(document));
+        assert!(!metadata.verify_contract(b"The holder is entitled to two shares."));
+        assert!(metadata
+            .check_standardness(&CarrierPolicy::Standard)
+            .is_ok());
+
+        for data in &[
+            &b""[..],
+            b"u=https://cpr.sm/5YgSU1Pg-q",
+            &metadata.as_bytes()[..33],
+            &metadata.as_bytes()[2..],
+        ] {
+            let metadata = Metadata::new(data.to_vec());
+            assert_eq!(None, metadata.contract_commitment());
+            assert!(!metadata.verify_contract(document));
+        }
+    }
+
+    #[test]
+    fn test_metadata_kinds() {
+        assert_eq!(Metadata::Empty, Metadata::new(vec![]));
+        assert!(Metadata::Empty.is_empty());
+        match Metadata::new(b"u=https://cpr.sm/5YgSU1Pg-q".to_vec()) {
+            Metadata::Url(ref url) => assert_eq!("https://cpr.sm/5YgSU1Pg-q", url),
+            m => panic!("expected Url, got {:?}", m),
+        }
+        for data in &[&b"u="[..], b"u=\xff", b"U=https://cpr.sm", b"\x01"] {
+            match Metadata::new(data.to_vec()) {
+                Metadata::Binary(ref d) => assert_eq!(data, d),
+                m => panic!("expected Binary, got {:?}", m),
+            }
+        }
+        // recognized like by the default scheme registry
+        for data in &[
+            &b"u=https://cpr.sm/5YgSU1Pg-q"[..],
+            b"u=",
+            b"u=\xff",
+            b"\x01",
+        ] {
+            let metadata = Metadata::new(data.to_vec());
+            assert_eq!(serialize(&data.to_vec()), serialize(&metadata));
+            let url = match metadata {
+                Metadata::Url(url) => Some(MetadataScheme::Url(url)),
+                _ => None,
+            };
+            assert_eq!(recognize_url(data), url);
+        }
+        assert_eq!(vec![0], serialize(&Metadata::Empty));
+
+        // metadata of any kind encodes like before, and compares by encoding
+        let url = Metadata::Url("https://cpr.sm/5YgSU1Pg-q".to_string());
+        assert_eq!(27, url.len());
+        assert_eq!(
+            Metadata::Binary(b"u=https://cpr.sm/5YgSU1Pg-q".to_vec()),
+            url
+        );
+        assert_eq!(Metadata::Binary(vec![]), Metadata::Empty);
+        assert_ne!(Metadata::Empty, Metadata::Binary(vec![0]));
+        let payload = Payload::new(vec![100, 0, 123], url.clone());
+        assert_eq!(
+            "4f4101000364007b1b753d68747470733a2f2f6370722e736d2f35596753553150672d71",
+            payload.to_hex().unwrap()
+        );
+        match deserialize::<Payload>(&serialize(&payload))
+            .unwrap()
+            .metadata
+        {
+            Metadata::Url(_) => {}
+            m => panic!("expected Url, got {:?}", m),
+        }
+        assert_eq!(
+            b"u=https://cpr.sm/5YgSU1Pg-q".to_vec(),
+            Vec::<u8>::from(url.clone())
+        );
+        assert_eq!(url, Metadata::from(url.clone().into_bytes()));
+        assert_eq!(
+            Metadata::Empty,
+            deserialize::<Payload>(&hex_decode("4f4101000000").unwrap())
+                .unwrap()
+                .metadata
+        );
+    }
+
+    #[test]
+    fn test_metadata_encodings() {
+        let metadata = Metadata::new(b"u=https://cpr.sm/5YgSU1Pg-q".to_vec());
+        assert_eq!("dT1odHRwczovL2Nwci5zbS81WWdTVTFQZy1x", metadata.to_base64());
+        assert_eq!(metadata, Metadata::from_hex(&metadata.to_hex()).unwrap());
+        assert_eq!(
+            metadata,
+            Metadata::from_base64(&metadata.to_base64()).unwrap()
+        );
+
+        let metadata = Metadata::new(vec![0xff, 0xfe, 0x41]);
+        assert_eq!("//5B", metadata.to_base64());
+        assert_eq!(metadata, Metadata::from_hex("FFFE41").unwrap());
+        assert_eq!(metadata, Metadata::from_base64("//5B").unwrap());
+        assert_eq!(Metadata::new(vec![]), Metadata::from_hex("").unwrap());
+        assert_eq!(Metadata::new(vec![]), Metadata::from_base64("").unwrap());
+
+        for invalid in &["f", "fg", "0x00"] {
+            assert!(Metadata::from_hex(invalid).is_err());
+        }
+        for invalid in &["//5", "//5B=", "-_5B", "dT1o!"] {
+            assert!(Metadata::from_base64(invalid).is_err());
+        }
+    }
+
+    #[test]
+    fn test_is_compressed() {
+        assert!(Metadata::new(b"z=\x01".to_vec()).is_compressed());
+        assert!(!Metadata::new(b"u=https://cpr.sm/5YgSU1Pg-q".to_vec()).is_compressed());
+        assert!(!Metadata::new(vec![]).is_compressed());
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_compressed() {
+        use openassets::marker_output::{COMPRESSED_PREFIX, MAX_DECOMPRESSED_SIZE};
+
+        let content = format!("{{\"name\": \"{}\"}}", "MyAsset ".repeat(20)).into_bytes();
+        let metadata = Metadata::compressed(&content);
+        assert!(metadata.is_compressed());
+        assert!(metadata.as_bytes().starts_with(COMPRESSED_PREFIX));
+        assert!(metadata.as_bytes().len() < content.len());
+        assert_eq!(content, metadata.content().unwrap().into_owned());
+
+        // payloads carry compressed metadata as is
+        let payload = Payload::new(vec![1], metadata.clone());
+        let decoded: Payload = deserialize(&serialize(&payload)).unwrap();
+        assert_eq!(content, decoded.metadata.content().unwrap().into_owned());
+
+        // uncompressed metadata is its own content
+        let metadata = Metadata::new(b"u=https://cpr.sm/5YgSU1Pg-q".to_vec());
+        assert_eq!(metadata.as_bytes(), &metadata.content().unwrap()[..]);
+
+        assert!(Metadata::new(b"z=\xff\xff".to_vec()).content().is_err());
+        let bomb = Metadata::compressed(&vec![0; MAX_DECOMPRESSED_SIZE + 1]);
+        assert!(bomb.content().is_err());
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn test_asset_definition_url() {
+        let metadata = Metadata::new(b"u=https://cpr.sm/5YgSU1Pg-q".to_vec());
+        assert_eq!(
+            "https://cpr.sm/5YgSU1Pg-q",
+            metadata.asset_definition_url().unwrap().as_str()
+        );
+        assert!(Metadata::new(b"https://cpr.sm/5YgSU1Pg-q".to_vec())
+            .asset_definition_url()
+            .is_none());
+        assert!(Metadata::new(b"u=not a url".to_vec())
+            .asset_definition_url()
+            .is_none());
+        assert!(Metadata::new(vec![0x75, 0x3d, 0xff])
+            .asset_definition_url()
+            .is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let payload = Payload::new(
+            vec![100, 0, 123],
+            Metadata::new(b"u=https://cpr.sm/5YgSU1Pg-q".to_vec()),
+        );
+        let json = serde_json::to_string(&payload).unwrap();
+        assert_eq!(
+            r#"{"version":256,"quantities":[100,0,123],"metadata":"u=https://cpr.sm/5YgSU1Pg-q"}"#,
+            json
+        );
+        assert_eq!(payload, serde_json::from_str(&json).unwrap());
+
+        // not utf-8
+        let payload = Payload::new(vec![1], Metadata::new(vec![0xff, 0xfe]));
+        let json = serde_json::to_string(&payload).unwrap();
+        assert_eq!(
+            r#"{"version":256,"quantities":[1],"metadata":{"hex":"fffe"}}"#,
+            json
+        );
+        assert_eq!(payload, serde_json::from_str(&json).unwrap());
+
+        // the version defaults to 1.0
+        let payload: Payload = serde_json::from_str(r#"{"quantities":[5],"metadata":""}"#).unwrap();
+        assert_eq!(Payload::new(vec![5], Metadata::new(vec![])), payload);
+        assert!(
+            serde_json::from_str::<Payload>(r#"{"quantities":[5],"metadata":{"hex":"zz"}}"#)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_payload_ref() {
+        let data =
+            hex_decode("4f4101000364007b1b753d68747470733a2f2f6370722e736d2f35596753553150672d71")
+                .unwrap();
+        let payload = PayloadRef::parse(&data, PayloadPolicy::Relaxed).unwrap();
+        assert_eq!(3, payload.quantity_count());
+        assert_eq!(
+            vec![100, 0, 123],
+            payload.quantities().collect::<Vec<u64>>()
+        );
+        assert_eq!(b"u=https://cpr.sm/5YgSU1Pg-q", payload.metadata());
+        assert_eq!(deserialize::<Payload>(&data).unwrap(), payload.to_payload());
+
+        let script = Builder::from(hex_decode("6a074f410100018001").unwrap()).into_script();
+        assert!(PayloadRef::from_script(&script).is_err());
+        let script = Builder::from(hex_decode("6a084f41010001ac0200").unwrap()).into_script();
+        let payload = PayloadRef::from_script(&script).unwrap();
+        assert_eq!(vec![300], payload.quantities().collect::<Vec<u64>>());
+        assert!(payload.metadata().is_empty());
+
+        // truncated metadata, trailing data, non-canonical quantity
+        for hex in &[
+            "4f4101000101030102",
+            "4f410100010101020304",
+            "4f41010001800000",
+        ] {
+            let data = hex_decode(hex).unwrap();
+            assert!(PayloadRef::parse(&data, PayloadPolicy::Strict).is_err());
+            assert!(Payload::from_slice(&data, PayloadPolicy::Strict).is_err());
+        }
+        let script = Builder::from(
+            hex_decode("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac").unwrap(),
+        )
+        .into_script();
+        assert!(PayloadRef::from_script(&script).is_err());
+    }
+
+    #[test]
+    fn test_encoded_len() {
+        for quantities in &[
+            vec![],
+            vec![0, 127, 128, 16_383, 16_384],
+            vec![MAX_QUANTITY, 1 << 56],
+            vec![1; 300],
+        ] {
+            for metadata in &[vec![], vec![1; 252], vec![1; 253]] {
+                let payload = Payload::new(quantities.clone(), Metadata::new(metadata.clone()));
+                assert_eq!(serialize(&payload).len(), payload.encoded_len());
+            }
+        }
+
+        // 4 bytes of marker and version, 1 byte of count and 1 byte of metadata length
+        let payload = Payload::new(vec![], Metadata::new(vec![1; 74]));
+        assert_eq!(80, payload.encoded_len());
+        assert!(payload.fits_standard_op_return());
+        let payload = Payload::new(vec![], Metadata::new(vec![1; 75]));
+        assert!(!payload.fits_standard_op_return());
+    }
+
+    #[test]
+    fn test_display_payload() {
+        let payload = Payload::new(
+            vec![100, 0, 123],
+            Metadata::new(b"u=https://cpr.sm/5YgSU1Pg-q".to_vec()),
+        );
+        assert_eq!(
+            "OA 1.0 marker, quantities [100, 0, 123], metadata u=https://cpr.sm/5YgSU1Pg-q",
+            payload.to_string()
+        );
+        assert_eq!(
+            "4f4101000364007b1b753d68747470733a2f2f6370722e736d2f35596753553150672d71",
+            payload.to_hex().unwrap()
+        );
+
+        let payload = Payload::new(vec![], Metadata::new(vec![0xff]));
+        assert_eq!(
+            "OA 1.0 marker, quantities [], metadata ff",
+            payload.to_string()
+        );
+        assert_eq!("4f4101000001ff", payload.to_hex().unwrap());
+    }
+
+    #[test]
+    fn test_carrier_policy() {
+        let payload = Payload::new(
+            vec![100, 0, 123],
+            Metadata::new(b"u=https://cpr.sm/5YgSU1Pg-q".to_vec()),
+        );
+        let standard = CarrierPolicy::Standard;
+        assert_eq!(
+            payload.to_txout().unwrap(),
+            payload.to_txout_with(&standard).unwrap()
+        );
+        assert_eq!(
+            payload,
+            standard
+                .from_script(&payload.to_txout().unwrap().script_pubkey)
+                .unwrap()
+        );
+
+        let split = CarrierPolicy::Split { chunk_size: 16 };
+        let script = split.to_script(&payload).unwrap();
+        assert_eq!(3, script.get_all_op_return_pushes().len());
+        assert!(!script.is_openassets_marker());
+        assert_eq!(payload, split.from_script(&script).unwrap());
+        assert!(standard.from_script(&script).is_err());
+
+        let prefixed = CarrierPolicy::Prefixed(b"exp".to_vec());
+        let script = prefixed.to_script(&payload).unwrap();
+        assert_eq!(
+            [&b"exp"[..], &serialize(&payload)[..]].concat(),
+            script.get_op_return_data()
+        );
+        assert_eq!(payload, prefixed.from_script(&script).unwrap());
+        assert!(prefixed
+            .from_script(&payload.to_txout().unwrap().script_pubkey)
+            .is_err());
+        assert!(CarrierPolicy::Prefixed(b"oth".to_vec())
+            .from_script(&script)
+            .is_err());
+    }
+
+    #[test]
+    fn test_check_standardness() {
+        let carriers = [
+            CarrierPolicy::Standard,
+            CarrierPolicy::Split { chunk_size: 16 },
+            CarrierPolicy::Split { chunk_size: 0 },
+            CarrierPolicy::Prefixed(b"exp".to_vec()),
+        ];
+        for carrier in &carriers {
+            for len in &[0, 74, 75, 100, 300, 70_000] {
+                let payload = Payload::new(vec![1, 2, 3], Metadata::new(vec![1; *len]));
+                assert_eq!(
+                    carrier.to_script(&payload).unwrap().len(),
+                    carrier.script_len(payload.encoded_len())
+                );
+            }
+        }
+
+        // OP_RETURN, OP_PUSHDATA1 and its length take 3 bytes on top of the payload
+        let standard = CarrierPolicy::Standard;
+        let metadata = Metadata::new(vec![1; 74]);
+        assert_eq!(Ok(()), metadata.check_standardness(&standard));
+        let payload = Payload::new(vec![], metadata.clone());
+        assert_eq!(Ok(()), payload.check_standardness(&standard));
+        assert_eq!(
+            payload.to_txout().unwrap(),
+            payload.to_standard_txout(&standard).unwrap()
+        );
+        let payload = Payload::new(vec![1], metadata.clone());
+        assert_eq!(
+            Err(StandardnessError {
+                script_size: 84,
+                limit: MAX_OP_RETURN_RELAY
+            }),
+            payload.check_standardness(&standard)
+        );
+        match payload.to_standard_txout(&standard) {
+            Err(error::Error::NonStandardMarker(e)) => assert_eq!(84, e.script_size),
+            r => panic!("expected NonStandardMarker, got {:?}", r),
+        }
+        assert_eq!(Ok(()), payload.check_standardness_with(&standard, 84));
+        assert!(payload.to_standard_txout_with(&standard, 84).is_ok());
+        assert_eq!(
+            Err(StandardnessError {
+                script_size: 84,
+                limit: MAX_OP_RETURN_RELAY
+            }),
+            Metadata::new(vec![1; 75]).check_standardness(&standard)
+        );
+
+        // other placements take more room
+        assert!(metadata
+            .check_standardness(&CarrierPolicy::Prefixed(b"exp".to_vec()))
+            .is_err());
+        assert!(metadata
+            .check_standardness(&CarrierPolicy::Split { chunk_size: 16 })
+            .is_err());
+        assert!(Metadata::new(vec![1; 60])
+            .check_standardness(&CarrierPolicy::Split { chunk_size: 16 })
+            .is_ok());
+    }
+
+    #[test]
+    fn test_to_txout() {
+        let payload = Payload::new(
+            vec![100, 0, 123],
+            Metadata::new("u=https://cpr.sm/5YgSU1Pg-q".as_bytes().to_vec()),
+        );
+        let txout = payload.to_txout().unwrap();
+        assert_eq!(0, txout.value);
+        assert_eq!(
+            Builder::from(
+                hex_decode(
+                    "6a244f4101000364007b1b753d68747470733a2f2f6370722e736d2f35596753553150672d71",
+                )
+                .unwrap(),
+            )
+            .into_script(),
+            txout.script_pubkey
+        );
+        assert_eq!(payload, txout.get_oa_payload().unwrap());
+    }
+
+    #[test]
+    fn test_decode_quantity_bounds() {
+        let decode = |hex: &str, policy| Payload::from_slice(&hex_decode(hex).unwrap(), policy);
+
+        let payload = decode("4f4101000180808080808080800100", PayloadPolicy::Strict).unwrap();
+        assert_eq!(vec![1 << 56], payload.quantities);
+        let payload = decode("4f41010001ffffffffffffffffff0100", PayloadPolicy::Relaxed).unwrap();
+        assert_eq!(vec![u64::MAX], payload.quantities);
+        // past 64 bits
+        assert!(decode("4f41010001ffffffffffffffffff0200", PayloadPolicy::Relaxed).is_err());
+        let hex = "4f410100018080808080808080808001";
+        assert!(decode(hex, PayloadPolicy::Relaxed).is_err());
+        assert!(deserialize::<Payload>(&hex_decode(hex).unwrap()).is_err());
+
+        // non-canonical encodings of 0 and 1
+        let payload = decode("4f41010002800081800000", PayloadPolicy::Relaxed).unwrap();
+        assert_eq!(vec![0, 1], payload.quantities);
+        assert!(decode("4f41010002800081800000", PayloadPolicy::Strict).is_err());
+        assert!(decode("4f410100010000", PayloadPolicy::Strict).is_ok());
+
+        // trailing data
+        assert!(decode("4f41010001000000", PayloadPolicy::Relaxed).is_err());
+    }
+
+    #[test]
+    fn test_trailing_data() {
+        let data = hex_decode("4f410100020a0500ffff").unwrap();
+        assert!(Payload::from_slice(&data, PayloadPolicy::Relaxed).is_err());
+        assert!(deserialize::<Payload>(&data).is_err());
+        let txout = TxOut {
+            value: 0,
+            script_pubkey: Builder::from(hex_decode("6a0a4f410100020a0500ffff").unwrap())
+                .into_script(),
+        };
+        assert!(txout.get_oa_payload().is_err());
+        assert!(!txout.is_openassets_marker());
+
+        let (payload, length) = Payload::from_slice_prefix(&data, PayloadPolicy::Relaxed).unwrap();
+        assert_eq!(vec![10, 5], payload.quantities);
+        assert_eq!(8, length);
+        assert!(Payload::from_slice_prefix(&data[..7], PayloadPolicy::Relaxed).is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let payload = |quantities: Vec<u64>| Payload::new(quantities, Metadata::new(vec![]));
+        assert!(payload(vec![10, 0, 5]).validate(1, 4).is_empty());
+        assert!(payload(vec![]).validate(0, 1).is_empty());
+
+        let diagnostics = payload(vec![10, 5, 0, 0]).validate(4, 4);
+        assert_eq!(
+            vec![
+                PayloadDiagnostic::MarkerOutOfRange {
+                    index: 4,
+                    outputs: 4
+                },
+                PayloadDiagnostic::TooManyQuantities {
+                    quantities: 4,
+                    outputs: 3
+                },
+                PayloadDiagnostic::TrailingZeroQuantities { start: 2, count: 2 },
+            ],
+            diagnostics
+        );
+        assert!(diagnostics[0].is_spec_violation());
+        assert!(diagnostics[1].is_spec_violation());
+        assert!(!diagnostics[2].is_spec_violation());
+        assert_eq!(
+            "4 quantities for 3 non-marker outputs",
+            diagnostics[1].to_string()
+        );
+    }
+
+    #[test]
+    fn test_total_quantity() {
+        let payload = Payload::new(vec![10, 0, 5], Metadata::new(vec![]));
+        assert_eq!(15, payload.total_quantity().unwrap());
+
+        // quantities [u64::MAX, 1]
+        let payload: Payload =
+            deserialize(&hex_decode("4f41010002ffffffffffffffffff010100").unwrap()).unwrap();
+        match payload.total_quantity() {
+            Err(error::Error::QuantityOverflow) => {}
+            _ => panic!("expected QuantityOverflow"),
+        }
+    }
+
+    #[test]
+    fn test_version() {
+        let data = hex_decode("4f410200020a0500").unwrap();
+        assert!(deserialize::<Payload>(&data).is_err());
+        assert!(Payload::from_slice(&data, PayloadPolicy::Relaxed).is_err());
+        let payload = Payload::from_slice_any_version(&data, PayloadPolicy::Relaxed).unwrap();
+        assert_eq!(MarkerVersion::Unknown(0x0200), payload.version);
+        assert_eq!("2.0", payload.version.to_string());
+        assert_eq!(vec![10, 5], payload.quantities);
+        assert_eq!(data, serialize(&payload));
+
+        let data = hex_decode("4f410100020a0500").unwrap();
+        let payload = Payload::from_slice_any_version(&data, PayloadPolicy::Relaxed).unwrap();
+        assert_eq!(MarkerVersion::V1, payload.version);
+        assert_eq!("1.0", payload.version.to_string());
+        assert_eq!(deserialize::<Payload>(&data).unwrap(), payload);
+    }
+
+    #[test]
+    fn test_max_quantity() {
+        let max = "4f41010001ffffffffffffffff7f00";
+        let payload =
+            Payload::from_slice(&hex_decode(max).unwrap(), PayloadPolicy::Strict).unwrap();
+        assert_eq!(vec![MAX_QUANTITY], payload.quantities);
+        assert_eq!(hex_decode(max).unwrap(), payload.to_vec().unwrap());
+
+        let over = "4f41010001808080808080808080010100";
+        let data = hex_decode(over).unwrap();
+        assert!(Payload::from_slice(&data, PayloadPolicy::Strict).is_err());
+        let payload = Payload::from_slice(&data, PayloadPolicy::Relaxed).unwrap();
+        assert_eq!(vec![MAX_QUANTITY + 1], payload.quantities);
+        // decoded, but never encoded
+        assert!(payload.to_vec().is_err());
+        assert!(payload.to_hex().is_err());
+        assert!(payload.to_txout().is_err());
+        for carrier in &[
+            CarrierPolicy::Standard,
+            CarrierPolicy::Split { chunk_size: 16 },
+            CarrierPolicy::Prefixed(b"exp".to_vec()),
+        ] {
+            assert!(payload.to_txout_with(carrier).is_err());
+            assert!(carrier.to_script(&payload).is_err());
+        }
+        match payload.to_vec() {
+            Err(error::Error::QuantityTooLarge { quantity, limit }) => {
+                assert_eq!(MAX_QUANTITY + 1, quantity);
+                assert_eq!(MAX_QUANTITY, limit);
+            }
+            r => panic!("expected QuantityTooLarge, got {:?}", r),
+        }
+        match payload.to_standard_txout(&CarrierPolicy::Standard) {
+            Err(error::Error::QuantityTooLarge { quantity, limit }) => {
+                assert_eq!(MAX_QUANTITY + 1, quantity);
+                assert_eq!(MAX_QUANTITY, limit);
+            }
+            r => panic!("expected QuantityTooLarge, got {:?}", r),
+        }
+        // but written back as decoded
+        assert_eq!(data, serialize(&payload));
+        let data = hex_decode("4f41010001808080808080808080010100").unwrap();
+        let payload: Payload = deserialize(&data).unwrap();
+        assert_eq!(vec![MAX_QUANTITY + 1], payload.quantities);
+        assert_eq!(data, serialize(&payload));
+        assert!(payload.to_vec().is_err());
+    }
+
+    #[test]
+    fn test_marker_candidates() {
+        let txout = |hex: &str| TxOut {
+            value: 0,
+            script_pubkey: Builder::from(hex_decode(hex).unwrap()).into_script(),
+        };
+        let tx = Transaction {
+            version: 1,
+            lock_time: 0,
+            input: vec![],
+            output: vec![
+                txout("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac"),
+                // invalid marker
+                txout("6a4f4201000364007b1b753d68747470733a2f2f6370722e736d2f35596753553150672d71"),
+                txout("6a084f41010002014400"),
+                txout("6a074f410100016400"),
+            ],
+        };
+        let candidates = marker_candidates(&tx);
+        assert_eq!(2, candidates.len());
+        assert_eq!(2, candidates[0].0);
+        assert_eq!(vec![1, 68], candidates[0].1.quantities);
+        assert_eq!(3, candidates[1].0);
+        assert_eq!(vec![100], candidates[1].1.quantities);
+
+        let tx = Transaction {
+            output: vec![txout("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac")],
+            ..tx
+        };
+        assert!(marker_candidates(&tx).is_empty());
+    }
 }
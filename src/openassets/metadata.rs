@@ -0,0 +1,436 @@
+use bitcoin::util::base58;
+use bitcoin_hashes::{sha256, sha256d, Hash};
+use openassets::marker_output::{Metadata, CONTRACT_HASH_PREFIX, MAX_OP_RETURN_SIZE};
+use std::error;
+use std::fmt::{self, Display, Formatter};
+use std::str;
+
+/// The prefix of asset definition URLs, following the convention of Coinprism.
+pub const URL_PREFIX: &str = "u=";
+
+/// The prefix of IPFS content identifiers written by `MetadataScheme::to_metadata`.
+pub const IPFS_PREFIX: &str = "ipfs://";
+
+/// The size, in bytes, of the largest metadata fitting in a marker output relayed by default,
+/// i.e. in a payload without quantities of `MAX_OP_RETURN_SIZE` bytes.
+pub const MAX_METADATA_SIZE: usize = MAX_OP_RETURN_SIZE - 6;
+
+/// What marker metadata refers to, as recognized by a `SchemeRegistry`.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum MetadataScheme {
+    /// The URL of an asset definition file, written `u=<url>`.
+    Url(String),
+    /// The transaction of the given txid, written as the 32 bytes of the txid in serialization
+    /// order, like in outpoints.
+    TxReference(sha256d::Hash),
+    /// The IPFS content identifier of an asset definition file, written `ipfs://<cid>`,
+    /// `/ipfs/<cid>` or as the bare CID.
+    Ipfs(String),
+    /// A commitment to a contract document, written `h=` followed by the 32 bytes of its
+    /// SHA-256 digest.
+    ContractHash(sha256::Hash),
+    /// Metadata of no recognized scheme, including empty metadata.
+    Unknown(Vec<u8>),
+}
+
+impl MetadataScheme {
+    /// Returns the metadata referring to the same thing, in the canonical form of its scheme.
+    pub fn to_metadata(&self) -> Metadata {
+        match *self {
+            MetadataScheme::Url(ref url) => {
+                Metadata::new(format!("{}{}", URL_PREFIX, url).into_bytes())
+            }
+            MetadataScheme::TxReference(ref txid) => Metadata::new(txid[..].to_vec()),
+            MetadataScheme::Ipfs(ref cid) => {
+                Metadata::new(format!("{}{}", IPFS_PREFIX, cid).into_bytes())
+            }
+            MetadataScheme::ContractHash(ref hash) => {
+                let mut data = CONTRACT_HASH_PREFIX.to_vec();
+                data.extend_from_slice(&hash[..]);
+                Metadata::new(data)
+            }
+            MetadataScheme::Unknown(ref data) => Metadata::new(data.clone()),
+        }
+    }
+}
+
+/// Recognizes the scheme of metadata, or returns None to leave it to the next recognizer.
+pub type SchemeRecognizer = fn(&[u8]) -> Option<MetadataScheme>;
+
+/// Recognizers of metadata schemes, tried in order until one recognizes the metadata.
+///
+/// The default registry recognizes URLs, then IPFS CIDs, then contract hashes, then txid
+/// references.
+#[derive(Clone)]
+pub struct SchemeRegistry {
+    recognizers: Vec<SchemeRecognizer>,
+}
+
+impl Default for SchemeRegistry {
+    fn default() -> SchemeRegistry {
+        SchemeRegistry {
+            recognizers: vec![
+                recognize_url,
+                recognize_ipfs,
+                recognize_contract_hash,
+                recognize_tx_reference,
+            ],
+        }
+    }
+}
+
+impl SchemeRegistry {
+    /// Returns a registry recognizing no scheme.
+    pub fn empty() -> SchemeRegistry {
+        SchemeRegistry {
+            recognizers: vec![],
+        }
+    }
+
+    /// Adds `recognizer`, tried after the recognizers already registered.
+    pub fn register(mut self, recognizer: SchemeRecognizer) -> SchemeRegistry {
+        self.recognizers.push(recognizer);
+        self
+    }
+
+    /// Returns the scheme of `metadata`, `MetadataScheme::Unknown` if no recognizer
+    /// recognizes it.
+    pub fn recognize(&self, metadata: &Metadata) -> MetadataScheme {
+        let data = metadata.as_bytes();
+        self.recognizers
+            .iter()
+            .filter_map(|recognize| recognize(&data))
+            .next()
+            .unwrap_or_else(|| MetadataScheme::Unknown(data.to_vec()))
+    }
+}
+
+/// Recognizes `u=<url>` metadata with a non-empty URL.
+pub fn recognize_url(data: &[u8]) -> Option<MetadataScheme> {
+    let url = str::from_utf8(data).ok()?.strip_prefix(URL_PREFIX)?;
+    if url.is_empty() {
+        return None;
+    }
+    Some(MetadataScheme::Url(url.to_string()))
+}
+
+/// Recognizes CIDv0 (base58 `Qm...`) and base32 CIDv1 (`b...`) content identifiers, bare or
+/// as `ipfs://<cid>` or `/ipfs/<cid>`.
+pub fn recognize_ipfs(data: &[u8]) -> Option<MetadataScheme> {
+    let s = str::from_utf8(data).ok()?;
+    let cid = s
+        .strip_prefix(IPFS_PREFIX)
+        .or_else(|| s.strip_prefix("/ipfs/"))
+        .unwrap_or(s);
+    if is_cid(cid) {
+        Some(MetadataScheme::Ipfs(cid.to_string()))
+    } else {
+        None
+    }
+}
+
+/// Recognizes contract hash commitments, as written by `Metadata::contract_hash`.
+pub fn recognize_contract_hash(data: &[u8]) -> Option<MetadataScheme> {
+    Metadata::new(data.to_vec())
+        .contract_commitment()
+        .map(MetadataScheme::ContractHash)
+}
+
+/// Recognizes metadata of exactly 32 bytes as a txid.
+pub fn recognize_tx_reference(data: &[u8]) -> Option<MetadataScheme> {
+    if data.len() != 32 {
+        return None;
+    }
+    sha256d::Hash::from_slice(data)
+        .ok()
+        .map(MetadataScheme::TxReference)
+}
+
+/// Errors raised while building metadata.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum MetadataError {
+    /// The metadata is `size` bytes long, over the limit of `limit` bytes.
+    TooLarge { size: usize, limit: usize },
+    /// The URL is empty or holds whitespace or control characters.
+    InvalidUrl(String),
+    /// The string is not an IPFS content identifier.
+    InvalidCid(String),
+}
+
+impl Display for MetadataError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            MetadataError::TooLarge { size, limit } => write!(
+                f,
+                "metadata of {} bytes exceeds the limit of {} bytes",
+                size, limit
+            ),
+            MetadataError::InvalidUrl(ref url) => write!(f, "invalid metadata URL: {:?}", url),
+            MetadataError::InvalidCid(ref cid) => write!(f, "invalid IPFS CID: {:?}", cid),
+        }
+    }
+}
+
+impl error::Error for MetadataError {}
+
+/// Builds marker metadata in the canonical form of its scheme, checking its size.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct MetadataBuilder {
+    scheme: MetadataScheme,
+    max_size: usize,
+}
+
+impl MetadataBuilder {
+    /// Builds `u=<url>` metadata pointing to an asset definition file.
+    pub fn url(url: &str) -> MetadataBuilder {
+        MetadataBuilder::new(MetadataScheme::Url(url.to_string()))
+    }
+
+    /// Builds `ipfs://<cid>` metadata pointing to an asset definition file stored on IPFS.
+    pub fn ipfs(cid: &str) -> MetadataBuilder {
+        MetadataBuilder::new(MetadataScheme::Ipfs(cid.to_string()))
+    }
+
+    /// Builds metadata referring to the transaction `txid`.
+    pub fn tx_reference(txid: sha256d::Hash) -> MetadataBuilder {
+        MetadataBuilder::new(MetadataScheme::TxReference(txid))
+    }
+
+    /// Builds metadata holding `data` as is.
+    pub fn raw(data: &[u8]) -> MetadataBuilder {
+        MetadataBuilder::new(MetadataScheme::Unknown(data.to_vec()))
+    }
+
+    fn new(scheme: MetadataScheme) -> MetadataBuilder {
+        MetadataBuilder {
+            scheme,
+            max_size: MAX_METADATA_SIZE,
+        }
+    }
+
+    /// Sets the size, in bytes, the metadata must not exceed. Defaults to `MAX_METADATA_SIZE`.
+    pub fn max_size(mut self, size: usize) -> MetadataBuilder {
+        self.max_size = size;
+        self
+    }
+
+    /// Returns the metadata, failing if it is malformed or too large.
+    pub fn build(&self) -> Result<Metadata, MetadataError> {
+        match self.scheme {
+            MetadataScheme::Url(ref url)
+                if url.is_empty() || url.chars().any(|c| c.is_whitespace() || c.is_control()) =>
+            {
+                return Err(MetadataError::InvalidUrl(url.clone()));
+            }
+            MetadataScheme::Ipfs(ref cid) if !is_cid(cid) => {
+                return Err(MetadataError::InvalidCid(cid.clone()));
+            }
+            _ => {}
+        }
+        let metadata = self.scheme.to_metadata();
+        let size = metadata.as_bytes().len();
+        if size > self.max_size {
+            return Err(MetadataError::TooLarge {
+                size,
+                limit: self.max_size,
+            });
+        }
+        Ok(metadata)
+    }
+}
+
+fn is_cid(s: &str) -> bool {
+    if s.starts_with("Qm") {
+        // a base58 encoded sha2-256 multihash
+        return s.len() == 46
+            && base58::from(s)
+                .map(|h| h.len() == 34 && h[0] == 0x12 && h[1] == 0x20)
+                .unwrap_or(false);
+    }
+    // a multibase base32 CID, at least as long as one of a sha2-256 multihash
+    s.starts_with('b')
+        && s.len() >= 59
+        && s[1..]
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || (b'2'..=b'7').contains(&b))
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin_hashes::hex::FromHex;
+    use bitcoin_hashes::sha256d;
+    use openassets::marker_output::Metadata;
+    use openassets::marker_output::{CarrierPolicy, Payload};
+    use openassets::metadata::{
+        MetadataBuilder, MetadataError, MetadataScheme, SchemeRegistry, MAX_METADATA_SIZE,
+    };
+
+    const CID_V0: &str = "QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG";
+    const CID_V1: &str = "bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi";
+
+    fn recognize(data: &[u8]) -> MetadataScheme {
+        Metadata::new(data.to_vec()).scheme()
+    }
+
+    #[test]
+    fn test_recognize() {
+        assert_eq!(
+            MetadataScheme::Url("https://cpr.sm/5YgSU1Pg-q".to_string()),
+            recognize(b"u=https://cpr.sm/5YgSU1Pg-q")
+        );
+        for data in &[
+            format!("ipfs://{}", CID_V0),
+            format!("/ipfs/{}", CID_V0),
+            CID_V0.to_string(),
+        ] {
+            assert_eq!(
+                MetadataScheme::Ipfs(CID_V0.to_string()),
+                recognize(data.as_bytes())
+            );
+        }
+        assert_eq!(
+            MetadataScheme::Ipfs(CID_V1.to_string()),
+            recognize(format!("ipfs://{}", CID_V1).as_bytes())
+        );
+        let txid = sha256d::Hash::from_hex(
+            "4b4d3b5de8b3a6c5b3e0e57c1a64f34b4dc59c8f2ec7d7dc2a0e49d2bc0ab1e7",
+        )
+        .unwrap();
+        assert_eq!(MetadataScheme::TxReference(txid), recognize(&txid[..]));
+        let contract = Metadata::contract_hash(b"abc");
+        assert_eq!(
+            MetadataScheme::ContractHash(contract.contract_commitment().unwrap()),
+            recognize(&contract.as_bytes())
+        );
+        assert_eq!(contract, contract.scheme().to_metadata());
+
+        for data in &[
+            &b""[..],
+            b"u=",
+            b"https://cpr.sm/5YgSU1Pg-q",
+            b"ipfs://QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbd0",
+            b"QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdGG",
+            b"bafybei",
+            &[0; 31],
+            b"\xff\xfe",
+        ] {
+            assert_eq!(MetadataScheme::Unknown(data.to_vec()), recognize(data));
+        }
+    }
+
+    #[test]
+    fn test_to_metadata() {
+        for data in &[
+            b"u=https://cpr.sm/5YgSU1Pg-q".to_vec(),
+            format!("ipfs://{}", CID_V1).into_bytes(),
+            vec![1; 32],
+            vec![0xff, 0xfe],
+            vec![],
+        ] {
+            let metadata = Metadata::new(data.clone());
+            assert_eq!(metadata, metadata.scheme().to_metadata());
+        }
+        assert_eq!(
+            Metadata::new(format!("ipfs://{}", CID_V0).into_bytes()),
+            MetadataScheme::Ipfs(CID_V0.to_string()).to_metadata()
+        );
+    }
+
+    #[test]
+    fn test_builder() {
+        let metadata = MetadataBuilder::url("https://cpr.sm/5YgSU1Pg-q")
+            .build()
+            .unwrap();
+        assert_eq!(
+            MetadataScheme::Url("https://cpr.sm/5YgSU1Pg-q".to_string()),
+            metadata.scheme()
+        );
+        let metadata = MetadataBuilder::ipfs(CID_V0).build().unwrap();
+        assert_eq!(
+            format!("ipfs://{}", CID_V0).as_bytes(),
+            &metadata.as_bytes()[..]
+        );
+        let txid = sha256d::Hash::from_hex(
+            "4b4d3b5de8b3a6c5b3e0e57c1a64f34b4dc59c8f2ec7d7dc2a0e49d2bc0ab1e7",
+        )
+        .unwrap();
+        let metadata = MetadataBuilder::tx_reference(txid).build().unwrap();
+        assert_eq!(MetadataScheme::TxReference(txid), metadata.scheme());
+        assert_eq!(
+            vec![1, 2, 3],
+            MetadataBuilder::raw(&[1, 2, 3])
+                .build()
+                .unwrap()
+                .into_bytes()
+        );
+
+        // the largest metadata fits in a standard marker output
+        let metadata = MetadataBuilder::raw(&[1; MAX_METADATA_SIZE])
+            .build()
+            .unwrap();
+        assert!(Payload::new(vec![], metadata)
+            .check_standardness(&CarrierPolicy::Standard)
+            .is_ok());
+        assert_eq!(
+            Err(MetadataError::TooLarge {
+                size: MAX_METADATA_SIZE + 1,
+                limit: MAX_METADATA_SIZE
+            }),
+            MetadataBuilder::raw(&[1; MAX_METADATA_SIZE + 1]).build()
+        );
+        assert!(MetadataBuilder::raw(&[1; 100])
+            .max_size(100)
+            .build()
+            .is_ok());
+        // ipfs://<cid> takes 66 bytes with a CIDv1
+        assert_eq!(
+            Err(MetadataError::TooLarge {
+                size: 66,
+                limit: 40
+            }),
+            MetadataBuilder::ipfs(CID_V1).max_size(40).build()
+        );
+
+        for url in &["", "https://example.com/a b", "https://example.com/\n"] {
+            assert_eq!(
+                Err(MetadataError::InvalidUrl(url.to_string())),
+                MetadataBuilder::url(url).build()
+            );
+        }
+        assert_eq!(
+            Err(MetadataError::InvalidCid("Qm".to_string())),
+            MetadataBuilder::ipfs("Qm").build()
+        );
+    }
+
+    #[test]
+    fn test_registry() {
+        fn recognize_tagged(data: &[u8]) -> Option<MetadataScheme> {
+            if data.starts_with(b"t=") {
+                Some(MetadataScheme::Url(format!(
+                    "https://example.com/{}",
+                    String::from_utf8_lossy(&data[2..])
+                )))
+            } else {
+                None
+            }
+        }
+
+        let metadata = Metadata::new(b"t=asset".to_vec());
+        assert_eq!(
+            MetadataScheme::Unknown(b"t=asset".to_vec()),
+            SchemeRegistry::default().recognize(&metadata)
+        );
+        assert_eq!(
+            MetadataScheme::Url("https://example.com/asset".to_string()),
+            SchemeRegistry::default()
+                .register(recognize_tagged)
+                .recognize(&metadata)
+        );
+        let metadata = Metadata::new(b"u=https://cpr.sm/5YgSU1Pg-q".to_vec());
+        assert_eq!(
+            MetadataScheme::Unknown(metadata.as_bytes().to_vec()),
+            SchemeRegistry::empty().recognize(&metadata)
+        );
+    }
+}
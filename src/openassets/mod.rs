@@ -1,3 +1,41 @@
 pub mod address;
+pub mod address_book;
+pub mod airdrop;
+#[cfg(feature = "proptest")]
+pub mod arbitrary;
 pub mod asset_id;
+#[cfg(feature = "tokio")]
+pub mod async_coloring;
+pub mod authenticity;
+pub mod block;
+pub mod builder;
+pub mod burn;
+pub mod cache;
+pub mod coloring;
+pub mod definition;
+pub mod dust;
+pub mod error;
+pub mod fee;
+pub mod fetch;
+pub mod index;
+pub mod leb128;
 pub mod marker_output;
+pub mod metadata;
+pub mod partial;
+pub mod quantity;
+pub mod reissuance;
+pub mod selection;
+pub mod sighash;
+#[cfg(feature = "signing")]
+pub mod signature;
+pub mod summary;
+pub mod swap;
+#[cfg(test)]
+mod test_helpers;
+pub mod trace;
+pub mod transaction;
+pub mod unsigned;
+pub mod uri;
+pub mod utxo;
+pub mod validation;
+pub mod wallet;
@@ -0,0 +1,206 @@
+use bitcoin::{Script, Transaction};
+use openassets::coloring::{
+    color_coinbase, find_marker, ColoredOutput, ColoringEngine, ColoringPolicy,
+};
+use openassets::error::Error;
+
+/// The color of an input as far as it is known to the caller.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum InputColor {
+    Known(ColoredOutput),
+    /// The output spent by the input could not be resolved.
+    Unknown,
+}
+
+/// The color of an output colored from partially known inputs.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum PartialColor {
+    /// The color does not depend on unknown inputs.
+    Known(ColoredOutput),
+    /// The output has this color, unless the unknown inputs make the transaction invalid.
+    Maybe(ColoredOutput),
+    /// The color depends on the unknown inputs.
+    Unknown,
+}
+
+impl ColoringEngine {
+    /// Colors each output of `tx` when the colors of some inputs are unknown, e.g. in a light
+    /// client which could not resolve every previous output.
+    ///
+    /// Assignments made before the first unknown input is reached are tagged
+    /// `PartialColor::Maybe`, since the unknown inputs may still make the whole transaction
+    /// invalid, and outputs which would consume units of unknown inputs are
+    /// `PartialColor::Unknown`. If every transfer quantity is covered before an unknown input
+    /// is reached, or the transaction is invalid regardless of the unknown inputs, all outputs
+    /// are `PartialColor::Known`.
+    pub fn color_transaction_partial(
+        &self,
+        tx: &Transaction,
+        issuance_script: &Script,
+        input_colors: &[InputColor],
+    ) -> Result<Vec<PartialColor>, Error> {
+        let known: Option<Vec<ColoredOutput>> = input_colors
+            .iter()
+            .map(|input| match *input {
+                InputColor::Known(ref color) => Some(color.clone()),
+                InputColor::Unknown => None,
+            })
+            .collect();
+        if let Some(known) = known {
+            let colors = self.color_transaction(tx, issuance_script, &known)?;
+            return Ok(colors.into_iter().map(PartialColor::Known).collect());
+        }
+        if tx.is_coin_base() {
+            return Ok(color_coinbase(tx)
+                .into_iter()
+                .map(PartialColor::Known)
+                .collect());
+        }
+        if input_colors.len() != tx.input.len() {
+            return Err(Error::InputCountMismatch {
+                expected: tx.input.len(),
+                actual: input_colors.len(),
+            });
+        }
+        let uncolored = || vec![PartialColor::Known(ColoredOutput::uncolored()); tx.output.len()];
+        let (marker_index, payload) = match find_marker(tx) {
+            Some(marker) => marker,
+            None => return Ok(uncolored()),
+        };
+        let inputs = input_colors.iter().map(|input| match *input {
+            InputColor::Known(ref color) => Some(color),
+            InputColor::Unknown => None,
+        });
+        let colors = self.assign_inputs(
+            tx,
+            issuance_script,
+            inputs,
+            marker_index,
+            &payload.quantities,
+            None,
+        );
+        match colors {
+            Ok((colors, unknown_reached)) => Ok(colors
+                .into_iter()
+                .map(|color| match color {
+                    Some(c) => {
+                        if unknown_reached && c.is_colored() {
+                            PartialColor::Maybe(c)
+                        } else {
+                            PartialColor::Known(c)
+                        }
+                    }
+                    None => PartialColor::Unknown,
+                })
+                .collect()),
+            Err(ref e) if self.policy == ColoringPolicy::Lenient && e.is_spec_violation() => {
+                Ok(uncolored())
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::network::constants::Network;
+    use openassets::asset_id::AssetId;
+    use openassets::coloring::{ColoredOutput, ColoringEngine, OutputKind};
+    use openassets::partial::{InputColor, PartialColor};
+    use openassets::test_helpers::{build_tx, script};
+
+    #[test]
+    fn test_color_transaction_partial() {
+        let engine = ColoringEngine::new(Network::Bitcoin);
+        let issuer = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let p2pkh = "76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac";
+        let asset_a = AssetId::new(&issuer, Network::Bitcoin);
+        let asset_b = AssetId::new(
+            &script("a914f9d499817e88ef7b10a88673296c6d6df2f4292d87"),
+            Network::Bitcoin,
+        );
+        let inputs = vec![
+            InputColor::Known(ColoredOutput::new(asset_b.clone(), 5, OutputKind::Transfer)),
+            InputColor::Unknown,
+        ];
+
+        // issue 10, transfer 3, 0, 4 and 1 units
+        let tx = build_tx(
+            2,
+            vec![
+                p2pkh,
+                "6a0b4f410100050a0300040100",
+                p2pkh,
+                p2pkh,
+                p2pkh,
+                p2pkh,
+            ],
+        );
+        assert_eq!(
+            vec![
+                PartialColor::Maybe(ColoredOutput::new(asset_a, 10, OutputKind::Issuance)),
+                PartialColor::Known(ColoredOutput::marker()),
+                PartialColor::Maybe(ColoredOutput::new(asset_b.clone(), 3, OutputKind::Transfer)),
+                PartialColor::Known(ColoredOutput::uncolored()),
+                PartialColor::Maybe(ColoredOutput::new(asset_b.clone(), 4, OutputKind::Transfer)),
+                PartialColor::Unknown,
+            ],
+            engine
+                .color_transaction_partial(&tx, &issuer, &inputs)
+                .unwrap()
+        );
+
+        // the unknown input is never reached
+        let tx = build_tx(2, vec!["6a074f410100010500", p2pkh]);
+        assert_eq!(
+            vec![
+                PartialColor::Known(ColoredOutput::marker()),
+                PartialColor::Known(ColoredOutput::new(asset_b.clone(), 5, OutputKind::Transfer)),
+            ],
+            engine
+                .color_transaction_partial(&tx, &issuer, &inputs)
+                .unwrap()
+        );
+
+        // known uncolored inputs carry no units, whatever their quantity
+        let uncolored = InputColor::Known(ColoredOutput {
+            asset_id: None,
+            asset_quantity: 10,
+            kind: OutputKind::Uncolored,
+        });
+        let tx = build_tx(2, vec!["6a074f410100010300", p2pkh]);
+        assert_eq!(
+            vec![
+                PartialColor::Known(ColoredOutput::marker()),
+                PartialColor::Unknown,
+            ],
+            engine
+                .color_transaction_partial(&tx, &issuer, &[uncolored.clone(), InputColor::Unknown])
+                .unwrap()
+        );
+        assert_eq!(
+            vec![PartialColor::Known(ColoredOutput::uncolored()); 2],
+            engine
+                .color_transaction_partial(&tx, &issuer, &[uncolored.clone(), uncolored])
+                .unwrap()
+        );
+
+        // invalid regardless of the unknown input
+        let inputs = vec![
+            InputColor::Known(ColoredOutput::new(asset_b.clone(), 5, OutputKind::Transfer)),
+            InputColor::Known(ColoredOutput::new(
+                AssetId::new(&issuer, Network::Bitcoin),
+                5,
+                OutputKind::Transfer,
+            )),
+            InputColor::Unknown,
+        ];
+        let tx = build_tx(3, vec!["6a074f410100010600", p2pkh]);
+        assert_eq!(
+            vec![PartialColor::Known(ColoredOutput::uncolored()); 2],
+            engine
+                .color_transaction_partial(&tx, &issuer, &inputs)
+                .unwrap()
+        );
+    }
+}
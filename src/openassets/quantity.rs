@@ -0,0 +1,196 @@
+use openassets::definition::MAX_DIVISIBILITY;
+use openassets::error::Error;
+use std::error;
+use std::fmt::{self, Display, Formatter};
+
+/// Sums asset quantities, failing with `Error::QuantityOverflow` instead of wrapping around.
+pub fn checked_sum<I: IntoIterator<Item = u64>>(quantities: I) -> Result<u64, Error> {
+    quantities
+        .into_iter()
+        .try_fold(0u64, |sum, q| sum.checked_add(q))
+        .ok_or(Error::QuantityOverflow)
+}
+
+/// Errors raised while parsing asset quantities.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuantityError {
+    /// The divisibility exceeds `MAX_DIVISIBILITY`.
+    DivisibilityTooLarge(u8),
+    /// The string is not a decimal number.
+    InvalidFormat,
+    /// The number has more decimal places than the divisibility of the asset.
+    TooPrecise,
+    /// The quantity does not fit in a u64.
+    Overflow,
+}
+
+impl Display for QuantityError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            QuantityError::DivisibilityTooLarge(d) => write!(
+                f,
+                "divisibility too large: {}, at most {}",
+                d, MAX_DIVISIBILITY
+            ),
+            QuantityError::InvalidFormat => write!(f, "invalid quantity format"),
+            QuantityError::TooPrecise => write!(f, "quantity more precise than the asset"),
+            QuantityError::Overflow => write!(f, "quantity overflow"),
+        }
+    }
+}
+
+impl error::Error for QuantityError {}
+
+/// Formats the raw `quantity` of an asset as a decimal number with `divisibility` decimal
+/// places, e.g. 1234 with a divisibility of 2 as `12.34`.
+///
+/// Fails if `divisibility` exceeds `MAX_DIVISIBILITY`, e.g. as read from a hostile asset
+/// definition file.
+pub fn format_quantity(quantity: u64, divisibility: u8) -> Result<String, QuantityError> {
+    if divisibility > MAX_DIVISIBILITY {
+        return Err(QuantityError::DivisibilityTooLarge(divisibility));
+    }
+    if divisibility == 0 {
+        return Ok(quantity.to_string());
+    }
+    let unit = 10u64.pow(u32::from(divisibility));
+    Ok(format!(
+        "{}.{:0width$}",
+        quantity / unit,
+        quantity % unit,
+        width = divisibility as usize
+    ))
+}
+
+/// Parses a decimal number into the raw quantity of an asset with `divisibility` decimal
+/// places, e.g. `12.34` with a divisibility of 2 into 1234.
+///
+/// Trailing zeros beyond the divisibility are accepted, other extra decimal places are not.
+pub fn parse_quantity(s: &str, divisibility: u8) -> Result<u64, QuantityError> {
+    if divisibility > MAX_DIVISIBILITY {
+        return Err(QuantityError::DivisibilityTooLarge(divisibility));
+    }
+    let (integer, fraction) = match s.find('.') {
+        Some(i) => (&s[..i], &s[i + 1..]),
+        None => (s, ""),
+    };
+    if integer.is_empty() && fraction.is_empty()
+        || !integer
+            .bytes()
+            .chain(fraction.bytes())
+            .all(|b| b.is_ascii_digit())
+    {
+        return Err(QuantityError::InvalidFormat);
+    }
+    let places = divisibility as usize;
+    let (fraction, excess) = if fraction.len() > places {
+        (&fraction[..places], &fraction[places..])
+    } else {
+        (fraction, "")
+    };
+    if excess.bytes().any(|b| b != b'0') {
+        return Err(QuantityError::TooPrecise);
+    }
+
+    let mut quantity = 0u64;
+    let padding = places - fraction.len();
+    for b in integer
+        .bytes()
+        .chain(fraction.bytes())
+        .chain((0..padding).map(|_| b'0'))
+    {
+        quantity = quantity
+            .checked_mul(10)
+            .and_then(|q| q.checked_add(u64::from(b - b'0')))
+            .ok_or(QuantityError::Overflow)?;
+    }
+    Ok(quantity)
+}
+
+#[cfg(test)]
+mod tests {
+    use openassets::error::Error;
+    use openassets::quantity::{checked_sum, format_quantity, parse_quantity, QuantityError};
+
+    #[test]
+    fn test_checked_sum() {
+        assert_eq!(0, checked_sum(vec![]).unwrap());
+        assert_eq!(6, checked_sum(vec![1, 2, 3]).unwrap());
+        assert_eq!(u64::MAX, checked_sum(vec![u64::MAX - 1, 1]).unwrap());
+        match checked_sum(vec![u64::MAX, 1]) {
+            Err(Error::QuantityOverflow) => {}
+            _ => panic!("expected QuantityOverflow"),
+        }
+    }
+
+    #[test]
+    fn test_format_quantity() {
+        for &(expected, quantity, divisibility) in &[
+            ("1234", 1234, 0),
+            ("12.34", 1234, 2),
+            ("12.30", 1230, 2),
+            ("0.05", 5, 2),
+            ("0.00", 0, 2),
+            ("1.8446744073709551615", u64::MAX, 19),
+            ("18446744073709551615", u64::MAX, 0),
+        ] {
+            assert_eq!(
+                Ok(expected.to_string()),
+                format_quantity(quantity, divisibility)
+            );
+        }
+        assert_eq!(
+            Err(QuantityError::DivisibilityTooLarge(20)),
+            format_quantity(1, 20)
+        );
+        assert_eq!(
+            Err(QuantityError::DivisibilityTooLarge(255)),
+            format_quantity(1, 255)
+        );
+    }
+
+    #[test]
+    fn test_parse_quantity() {
+        assert_eq!(Ok(1234), parse_quantity("12.34", 2));
+        assert_eq!(Ok(1230), parse_quantity("12.3", 2));
+        assert_eq!(Ok(1200), parse_quantity("12", 2));
+        assert_eq!(Ok(1200), parse_quantity("12.", 2));
+        assert_eq!(Ok(5), parse_quantity(".05", 2));
+        assert_eq!(Ok(1234), parse_quantity("12.3400", 2));
+        assert_eq!(Ok(1234), parse_quantity("1234", 0));
+        assert_eq!(Ok(1234), parse_quantity("1234.0", 0));
+        assert_eq!(Ok(u64::MAX), parse_quantity("1.8446744073709551615", 19));
+        for &(quantity, divisibility) in &[(0, 0), (1234, 2), (7, 8), (u64::MAX, 19)] {
+            assert_eq!(
+                Ok(quantity),
+                parse_quantity(
+                    &format_quantity(quantity, divisibility).unwrap(),
+                    divisibility
+                )
+            );
+        }
+
+        assert_eq!(Err(QuantityError::TooPrecise), parse_quantity("12.345", 2));
+        assert_eq!(Err(QuantityError::TooPrecise), parse_quantity("0.5", 0));
+        assert_eq!(
+            Err(QuantityError::Overflow),
+            parse_quantity("18446744073709551616", 0)
+        );
+        assert_eq!(
+            Err(QuantityError::Overflow),
+            parse_quantity("184467440737095516.16", 2)
+        );
+        assert_eq!(
+            Err(QuantityError::DivisibilityTooLarge(20)),
+            parse_quantity("1", 20)
+        );
+        for invalid in &["", ".", "-1", "+1", " 1", "1,5", "1.2.3", "1e3", "abc"] {
+            assert_eq!(
+                Err(QuantityError::InvalidFormat),
+                parse_quantity(invalid, 2),
+                "{}",
+                invalid
+            );
+        }
+    }
+}
@@ -0,0 +1,324 @@
+use bitcoin::blockdata::transaction::SigHashType;
+use bitcoin::network::constants::Network;
+use bitcoin::{OutPoint, Script, Transaction, TxOut};
+use openassets::address::ToScriptPubkey;
+use openassets::asset_id::AssetId;
+use openassets::builder::{
+    marker_txout, preview, push_change, validate_layout, BuildError, Preview, Sequencing,
+};
+use openassets::coloring::OutputKind;
+use openassets::dust::DustPolicy;
+use openassets::fee::estimate_fee;
+use openassets::marker_output::{Metadata, Payload, MAX_OP_RETURN_SIZE};
+use openassets::quantity::checked_sum;
+use openassets::sighash::{signing_inputs, SigningInput};
+use openassets::unsigned::UnsignedColoredTransaction;
+use openassets::utxo::ColoredUtxo;
+
+/// Builds transactions issuing additional units of an existing asset.
+///
+/// The first input spends an output locked by the issuance script of the asset, the other
+/// inputs fund the transaction. Units held by the inputs are sent back to the change script.
+#[derive(Debug, Clone)]
+pub struct ReissuanceBuilder {
+    asset_id: AssetId,
+    inputs: Vec<ColoredUtxo>,
+    issuances: Vec<(u64, Script)>,
+    change_script: Script,
+    fee: u64,
+    fee_rate: Option<u64>,
+    dust: DustPolicy,
+    sequencing: Sequencing,
+    metadata: Metadata,
+    max_marker_size: usize,
+}
+
+impl ReissuanceBuilder {
+    /// Creates a builder issuing units of `asset_id` by spending `issuance_utxo`.
+    ///
+    /// Fails if the script of `issuance_utxo` is not the issuance script of `asset_id`.
+    pub fn new(
+        asset_id: AssetId,
+        network: Network,
+        issuance_utxo: ColoredUtxo,
+        change_script: Script,
+    ) -> Result<ReissuanceBuilder, BuildError> {
+        let actual = AssetId::new(&issuance_utxo.txout.script_pubkey, network);
+        if actual != asset_id {
+            return Err(BuildError::AssetIdMismatch {
+                expected: asset_id,
+                actual,
+            });
+        }
+        Ok(ReissuanceBuilder {
+            asset_id,
+            inputs: vec![issuance_utxo],
+            issuances: vec![],
+            change_script,
+            fee: 0,
+            fee_rate: None,
+            dust: DustPolicy::default(),
+            sequencing: Sequencing::default(),
+            metadata: Metadata::Empty,
+            max_marker_size: MAX_OP_RETURN_SIZE,
+        })
+    }
+
+    /// Adds a UTXO funding the transaction.
+    pub fn add_input(mut self, utxo: ColoredUtxo) -> Self {
+        self.inputs.push(utxo);
+        self
+    }
+
+    /// Issues `quantity` units to `recipient`, a script, an Open Assets address or a bitcoin
+    /// address.
+    pub fn add_issuance<R: ToScriptPubkey>(mut self, quantity: u64, recipient: R) -> Self {
+        self.issuances
+            .push((quantity, recipient.to_script_pubkey()));
+        self
+    }
+
+    /// Sets the fee paid by the transaction, in satoshis.
+    pub fn fee(mut self, fee: u64) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    /// Sets the fee rate, in satoshis per virtual byte, the fee is estimated from once the
+    /// transaction is signed. Overrides the fee set by `fee`.
+    pub fn fee_rate(mut self, fee_rate: u64) -> Self {
+        self.fee_rate = Some(fee_rate);
+        self
+    }
+
+    /// Sets the policy giving the value of the outputs carrying assets.
+    pub fn dust_policy(mut self, dust: DustPolicy) -> Self {
+        self.dust = dust;
+        self
+    }
+
+    /// Sets the metadata of the marker output to `url`, e.g. the URL of the asset definition
+    /// file.
+    ///
+    /// See `TransferBuilder::with_metadata_url`.
+    pub fn with_metadata_url(mut self, url: &str) -> Self {
+        self.metadata = Metadata::Url(url.to_string());
+        self
+    }
+
+    /// Sets the size, in bytes, the payload of the marker output must not exceed.
+    ///
+    /// See `TransferBuilder::max_marker_size`.
+    pub fn max_marker_size(mut self, size: usize) -> Self {
+        self.max_marker_size = size;
+        self
+    }
+
+    /// Signals that the transaction can be replaced, as defined by BIP 125.
+    ///
+    /// See `TransferBuilder::enable_rbf`.
+    pub fn enable_rbf(mut self) -> Self {
+        self.sequencing.rbf = true;
+        self
+    }
+
+    /// Sets the lock time of the transaction.
+    ///
+    /// See `TransferBuilder::set_locktime`.
+    pub fn set_locktime(mut self, lock_time: u32) -> Self {
+        self.sequencing.lock_time = lock_time;
+        self
+    }
+
+    /// Sets the sequence number of the input spending `outpoint`.
+    pub fn set_sequence(mut self, outpoint: OutPoint, sequence: u32) -> Self {
+        self.sequencing.sequences.insert(outpoint, sequence);
+        self
+    }
+
+    /// Returns the asset issued.
+    pub fn asset_id(&self) -> &AssetId {
+        &self.asset_id
+    }
+
+    /// Builds the unsigned transaction.
+    ///
+    /// The issuance outputs come first, followed by the marker output and, for each colored
+    /// input in input order, a change output holding its units. The bitcoin change output
+    /// comes last and is omitted when it would be dust.
+    pub fn build(&self) -> Result<Transaction, BuildError> {
+        self.build_with_payload().map(|(tx, _)| tx)
+    }
+
+    /// Returns the size, fee and asset quantities of the transaction `build` would return,
+    /// along with the payload of its marker output.
+    pub fn preview(&self) -> Result<Preview, BuildError> {
+        let (tx, payload) = self.build_with_payload()?;
+        preview(tx, payload, &self.inputs, self.asset_id.network)
+    }
+
+    /// Builds the unsigned transaction, bundled with the outputs it spends and the colors of
+    /// its outputs for an external signer.
+    pub fn build_unsigned(&self) -> Result<UnsignedColoredTransaction, BuildError> {
+        let tx = self.build()?;
+        Ok(UnsignedColoredTransaction::new(
+            tx,
+            &self.inputs,
+            self.asset_id.network,
+        )?)
+    }
+
+    /// Same as `build`, also returning the payload of the marker output.
+    fn build_with_payload(&self) -> Result<(Transaction, Payload), BuildError> {
+        let changes: Vec<u64> = self
+            .inputs
+            .iter()
+            .filter(|u| u.colored.is_colored())
+            .map(|u| u.colored.asset_quantity)
+            .collect();
+        let mut quantities: Vec<u64> = self.issuances.iter().map(|i| i.0).collect();
+        quantities.extend(changes.iter().cloned());
+        checked_sum(quantities.iter().cloned())?;
+        let payload = Payload::new(quantities, self.metadata.clone());
+
+        let mut output: Vec<TxOut> = self
+            .issuances
+            .iter()
+            .map(|(_, script)| TxOut {
+                value: self.dust.value,
+                script_pubkey: script.clone(),
+            })
+            .collect();
+        output.push(marker_txout(&payload, self.max_marker_size)?);
+        output.extend(changes.iter().map(|_| TxOut {
+            value: self.dust.value,
+            script_pubkey: self.change_script.clone(),
+        }));
+        for (index, o) in output.iter().enumerate() {
+            if index != self.issuances.len() && self.dust.is_dust(o) {
+                return Err(BuildError::DustOutput {
+                    index,
+                    value: o.value,
+                    threshold: self.dust.threshold(&o.script_pubkey),
+                });
+            }
+        }
+        let mut tx = Transaction {
+            version: 1,
+            lock_time: self.sequencing.lock_time,
+            input: self
+                .inputs
+                .iter()
+                .map(|u| self.sequencing.txin(u))
+                .collect(),
+            output,
+        };
+
+        let prev_scripts: Vec<&Script> =
+            self.inputs.iter().map(|u| &u.txout.script_pubkey).collect();
+        let available = checked_sum(self.inputs.iter().map(|u| u.txout.value))?;
+        push_change(&mut tx, available, &self.change_script, &self.dust, |tx| {
+            self.estimate_fee(tx, &prev_scripts)
+        })?;
+
+        let mut layout: Vec<OutputKind> = self
+            .issuances
+            .iter()
+            .map(|i| match i.0 {
+                0 => OutputKind::Uncolored,
+                _ => OutputKind::Issuance,
+            })
+            .collect();
+        layout.push(OutputKind::Marker);
+        layout.extend(changes.iter().map(|_| OutputKind::Transfer));
+        validate_layout(&tx, &layout)?;
+        Ok((tx, payload))
+    }
+
+    /// Returns, for each input of `tx` built by this builder, what its signer needs to know to
+    /// sign with `sighash_type`.
+    pub fn signing_inputs(
+        &self,
+        tx: &Transaction,
+        sighash_type: SigHashType,
+    ) -> Result<Vec<SigningInput>, BuildError> {
+        Ok(signing_inputs(tx, &self.inputs, sighash_type)?)
+    }
+
+    /// Returns the fee of `tx`, estimated from the fee rate if set.
+    fn estimate_fee(&self, tx: &Transaction, prev_scripts: &[&Script]) -> u64 {
+        match self.fee_rate {
+            Some(fee_rate) => estimate_fee(tx, prev_scripts, fee_rate),
+            None => self.fee,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::network::constants::Network;
+    use openassets::asset_id::AssetId;
+    use openassets::builder::BuildError;
+    use openassets::coloring::{ColoredOutput, ColoringEngine, ColoringPolicy, OutputKind};
+    use openassets::reissuance::ReissuanceBuilder;
+    use openassets::test_helpers::{script, utxo_with_script};
+
+    #[test]
+    fn test_build() {
+        let issuer = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let alice = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+        let asset_id = AssetId::new(&issuer, Network::Bitcoin);
+        let other_asset_id = AssetId::new(&alice, Network::Bitcoin);
+        let held = ColoredOutput::new(other_asset_id.clone(), 7, OutputKind::Transfer);
+        let builder = ReissuanceBuilder::new(
+            asset_id.clone(),
+            Network::Bitcoin,
+            utxo_with_script(0, 600, &issuer, held.clone()),
+            issuer.clone(),
+        )
+        .unwrap()
+        .add_input(utxo_with_script(
+            1,
+            10_000,
+            &issuer,
+            ColoredOutput::uncolored(),
+        ))
+        .add_issuance(500, alice.clone())
+        .add_issuance(100, issuer.clone())
+        .fee(1_000);
+        let tx = builder.build().unwrap();
+
+        let colors = ColoringEngine::with_policy(Network::Bitcoin, ColoringPolicy::Strict)
+            .color_transaction(&tx, &issuer, &[held, ColoredOutput::uncolored()])
+            .unwrap();
+        assert_eq!(
+            vec![
+                ColoredOutput::new(asset_id.clone(), 500, OutputKind::Issuance),
+                ColoredOutput::new(asset_id.clone(), 100, OutputKind::Issuance),
+                ColoredOutput::marker(),
+                ColoredOutput::new(other_asset_id, 7, OutputKind::Transfer),
+                ColoredOutput::uncolored(),
+            ],
+            colors
+        );
+        assert_eq!(10_600 - 3 * 600 - 1_000, tx.output[4].value);
+
+        let preview = builder.preview().unwrap();
+        assert_eq!(1_000, preview.fee);
+        assert_eq!(vec![500, 100, 7], preview.payload.quantities);
+        assert_eq!(600, preview.summary.get(&asset_id).unwrap().issued);
+
+        match ReissuanceBuilder::new(
+            asset_id.clone(),
+            Network::Bitcoin,
+            utxo_with_script(0, 600, &alice, ColoredOutput::uncolored()),
+            issuer,
+        ) {
+            Err(BuildError::AssetIdMismatch { expected, actual }) => {
+                assert_eq!(asset_id, expected);
+                assert_eq!(AssetId::new(&alice, Network::Bitcoin), actual);
+            }
+            _ => panic!("expected AssetIdMismatch"),
+        }
+    }
+}
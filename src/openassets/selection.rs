@@ -0,0 +1,261 @@
+use openassets::asset_id::AssetId;
+use openassets::builder::BuildError;
+use openassets::dust::DEFAULT_DUST_VALUE;
+use openassets::quantity::checked_sum;
+use openassets::utxo::ColoredUtxo;
+
+/// The maximum number of branches explored by `SelectionStrategy::BranchAndBound`.
+const MAX_TRIES: usize = 100_000;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum SelectionStrategy {
+    /// Selects the largest UTXOs first until the target is met.
+    #[default]
+    LargestFirst,
+    /// Searches for UTXOs meeting the target without change, falling back to
+    /// `LargestFirst` if there are none.
+    BranchAndBound,
+}
+
+/// Selects the UTXOs to spend to send units of assets, keeping colored and uncolored UTXOs
+/// apart.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct CoinSelector {
+    pub strategy: SelectionStrategy,
+}
+
+impl CoinSelector {
+    pub fn new(strategy: SelectionStrategy) -> CoinSelector {
+        CoinSelector { strategy }
+    }
+
+    /// Selects UTXOs from `utxos` holding at least the quantity of each asset in `targets`,
+    /// and at least `value` satoshis, e.g. the fee and the value of the outputs.
+    ///
+    /// Colored UTXOs are only selected to meet asset targets, and the satoshis they hold count
+    /// towards `value`. Uncolored UTXOs then cover the remaining satoshis. A branch and bound
+    /// search looks for colored UTXOs holding exactly the target quantity, and for uncolored
+    /// UTXOs exceeding the remaining satoshis by less than the dust value, so that no change
+    /// output is needed.
+    pub fn select(
+        &self,
+        utxos: &[ColoredUtxo],
+        targets: &[(AssetId, u64)],
+        value: u64,
+    ) -> Result<Vec<ColoredUtxo>, BuildError> {
+        let mut selected: Vec<ColoredUtxo> = vec![];
+        let mut asset_ids: Vec<&AssetId> = vec![];
+        for (asset_id, _) in targets {
+            if !asset_ids.contains(&asset_id) {
+                asset_ids.push(asset_id);
+            }
+        }
+        for asset_id in asset_ids {
+            let required = checked_sum(targets.iter().filter(|t| &t.0 == asset_id).map(|t| t.1))?;
+            let candidates: Vec<&ColoredUtxo> = utxos
+                .iter()
+                .filter(|u| u.colored.asset_id.as_ref() == Some(asset_id))
+                .collect();
+            let quantities: Vec<u64> = candidates
+                .iter()
+                .map(|u| u.colored.asset_quantity)
+                .collect();
+            match self.select_amounts(&quantities, required, 0) {
+                Some(indices) => {
+                    selected.extend(indices.into_iter().map(|i| candidates[i].clone()))
+                }
+                None => {
+                    return Err(BuildError::InsufficientAssetUnits {
+                        asset_id: asset_id.clone(),
+                        required,
+                        available: checked_sum(quantities)?,
+                    })
+                }
+            }
+        }
+
+        let colored_value = checked_sum(selected.iter().map(|u| u.txout.value))?;
+        if colored_value >= value {
+            return Ok(selected);
+        }
+        let candidates: Vec<&ColoredUtxo> = utxos
+            .iter()
+            .filter(|u| u.colored.asset_id.is_none())
+            .collect();
+        let values: Vec<u64> = candidates.iter().map(|u| u.txout.value).collect();
+        match self.select_amounts(&values, value - colored_value, DEFAULT_DUST_VALUE) {
+            Some(indices) => selected.extend(indices.into_iter().map(|i| candidates[i].clone())),
+            None => {
+                return Err(BuildError::InsufficientFunds {
+                    required: value,
+                    available: checked_sum(values.into_iter().chain(Some(colored_value)))?,
+                })
+            }
+        }
+        Ok(selected)
+    }
+
+    /// Returns the indices of `amounts` to select to meet `target`, or `None` if their total is
+    /// short of it. Branch and bound looks for a total within `window` above the target.
+    fn select_amounts(&self, amounts: &[u64], target: u64, window: u64) -> Option<Vec<usize>> {
+        if target == 0 {
+            return Some(vec![]);
+        }
+        let mut order: Vec<usize> = (0..amounts.len()).collect();
+        order.sort_by(|&a, &b| amounts[b].cmp(&amounts[a]));
+        if self.strategy == SelectionStrategy::BranchAndBound {
+            if let Some(indices) = branch_and_bound(amounts, &order, target, window) {
+                return Some(indices);
+            }
+        }
+        let mut total: u64 = 0;
+        let mut indices = vec![];
+        for i in order {
+            if total >= target {
+                break;
+            }
+            total = total.saturating_add(amounts[i]);
+            indices.push(i);
+        }
+        if total >= target {
+            Some(indices)
+        } else {
+            None
+        }
+    }
+}
+
+/// Searches depth-first, in `order`, for `amounts` totaling between `target` and
+/// `target + window`.
+fn branch_and_bound(
+    amounts: &[u64],
+    order: &[usize],
+    target: u64,
+    window: u64,
+) -> Option<Vec<usize>> {
+    let mut remaining: Vec<u64> = vec![0; order.len() + 1];
+    for depth in (0..order.len()).rev() {
+        remaining[depth] = remaining[depth + 1].saturating_add(amounts[order[depth]]);
+    }
+    let upper = target.saturating_add(window);
+    let mut selection: Vec<bool> = vec![];
+    let mut total: u64 = 0;
+    let mut tries = 0;
+    loop {
+        tries += 1;
+        let depth = selection.len();
+        let backtrack = if total > upper || total.saturating_add(remaining[depth]) < target {
+            true
+        } else if total >= target {
+            return Some(
+                selection
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, &included)| included)
+                    .map(|(d, _)| order[d])
+                    .collect(),
+            );
+        } else {
+            false
+        };
+        if tries > MAX_TRIES {
+            return None;
+        }
+        if !backtrack {
+            // include the next amount first
+            selection.push(true);
+            total += amounts[order[depth]];
+            continue;
+        }
+        // drop trailing exclusions, then exclude the last inclusion
+        while let Some(&false) = selection.last() {
+            selection.pop();
+        }
+        match selection.last_mut() {
+            Some(included) => {
+                *included = false;
+                total -= amounts[order[selection.len() - 1]];
+            }
+            None => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::network::constants::Network;
+    use bitcoin::Script;
+    use openassets::asset_id::AssetId;
+    use openassets::builder::BuildError;
+    use openassets::coloring::{ColoredOutput, OutputKind};
+    use openassets::selection::{CoinSelector, SelectionStrategy};
+    use openassets::test_helpers::utxo;
+    use openassets::utxo::ColoredUtxo;
+
+    fn vouts(utxos: &[ColoredUtxo]) -> Vec<u32> {
+        let mut vouts: Vec<u32> = utxos.iter().map(|u| u.outpoint.vout).collect();
+        vouts.sort();
+        vouts
+    }
+
+    #[test]
+    fn test_select() {
+        let asset_a = AssetId::new(&Script::new(), Network::Bitcoin);
+        let asset_b = AssetId::new(&Script::new(), Network::Testnet);
+        let colored = |vout, asset_id: &AssetId, quantity| {
+            utxo(
+                vout,
+                600,
+                ColoredOutput::new(asset_id.clone(), quantity, OutputKind::Transfer),
+            )
+        };
+        let utxos = vec![
+            colored(0, &asset_a, 50),
+            colored(1, &asset_a, 30),
+            colored(2, &asset_a, 20),
+            colored(3, &asset_b, 10),
+            utxo(4, 5_000, ColoredOutput::uncolored()),
+            utxo(5, 3_000, ColoredOutput::uncolored()),
+            utxo(6, 2_000, ColoredOutput::uncolored()),
+        ];
+        let targets = vec![(asset_a.clone(), 45), (asset_a.clone(), 5)];
+
+        let largest_first = CoinSelector::new(SelectionStrategy::LargestFirst);
+        let selected = largest_first.select(&utxos, &targets, 0).unwrap();
+        assert_eq!(vec![0], vouts(&selected));
+        let selected = largest_first.select(&utxos, &targets, 5_500).unwrap();
+        assert_eq!(vec![0, 4], vouts(&selected));
+
+        // exact quantities and 5000 + 2000 satoshis avoid change
+        let bnb = CoinSelector::new(SelectionStrategy::BranchAndBound);
+        let targets = vec![(asset_a.clone(), 50), (asset_b.clone(), 10)];
+        let selected = bnb.select(&utxos, &targets[1..], 0).unwrap();
+        assert_eq!(vec![3], vouts(&selected));
+        let selected = bnb.select(&utxos, &[(asset_a.clone(), 50)], 0).unwrap();
+        assert_eq!(vec![0], vouts(&selected));
+        let selected = bnb.select(&utxos, &[(asset_a.clone(), 70)], 0).unwrap();
+        assert_eq!(vec![0, 2], vouts(&selected));
+        let selected = bnb.select(&utxos, &[], 6_800).unwrap();
+        assert_eq!(vec![4, 6], vouts(&selected));
+
+        // no exact match, falls back to largest first
+        let selected = bnb.select(&utxos, &[(asset_a.clone(), 55)], 0).unwrap();
+        assert_eq!(vec![0, 1], vouts(&selected));
+
+        match bnb.select(&utxos, &[(asset_b.clone(), 11)], 0) {
+            Err(BuildError::InsufficientAssetUnits {
+                required: 11,
+                available: 10,
+                ..
+            }) => {}
+            _ => panic!("expected InsufficientAssetUnits"),
+        }
+        match bnb.select(&utxos, &targets, 20_000) {
+            Err(BuildError::InsufficientFunds {
+                required: 20_000,
+                available: 11_200,
+            }) => {}
+            _ => panic!("expected InsufficientFunds"),
+        }
+    }
+}
@@ -0,0 +1,246 @@
+use bitcoin::blockdata::opcodes;
+use bitcoin::blockdata::script::Builder;
+use bitcoin::blockdata::transaction::SigHashType;
+use bitcoin::consensus::serialize;
+use bitcoin::{OutPoint, Script, Transaction};
+use bitcoin_hashes::{sha256d, Hash};
+use openassets::error::Error;
+use openassets::utxo::ColoredUtxo;
+
+/// What the signer of an input needs to know.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct SigningInput {
+    /// The output spent by the input.
+    pub outpoint: OutPoint,
+    /// The script signed.
+    pub script_code: Script,
+    /// The value of the output spent, in satoshis.
+    pub value: u64,
+    /// Whether the input is signed as defined by BIP 143, i.e. in the witness.
+    pub segwit: bool,
+    /// The hash to sign.
+    pub sighash: sha256d::Hash,
+}
+
+/// Returns the script signed by the input spending `prev_script`, and whether the input is
+/// signed as defined by BIP 143.
+///
+/// `redeem_script` is the script wrapped by `prev_script` if it is P2SH. A P2WPKH script,
+/// native or wrapped, is signed through the P2PKH script of its public key hash. Other
+/// scripts are signed as is.
+pub fn script_code(prev_script: &Script, redeem_script: Option<&Script>) -> (Script, bool) {
+    let script = redeem_script.unwrap_or(prev_script);
+    if script.is_v0_p2wpkh() {
+        let code = Builder::new()
+            .push_opcode(opcodes::all::OP_DUP)
+            .push_opcode(opcodes::all::OP_HASH160)
+            .push_slice(&script[2..])
+            .push_opcode(opcodes::all::OP_EQUALVERIFY)
+            .push_opcode(opcodes::all::OP_CHECKSIG)
+            .into_script();
+        (code, true)
+    } else {
+        (script.clone(), false)
+    }
+}
+
+/// Returns the hash signed by input `index` of `tx`, spending `value` satoshis locked by
+/// `prev_script`.
+///
+/// `redeem_script` is the script wrapped by `prev_script` if it is P2SH. P2WPKH inputs,
+/// native or wrapped, are hashed as defined by BIP 143, and other inputs as legacy inputs.
+pub fn signature_hash(
+    tx: &Transaction,
+    index: usize,
+    prev_script: &Script,
+    redeem_script: Option<&Script>,
+    value: u64,
+    sighash_type: SigHashType,
+) -> sha256d::Hash {
+    let (script_code, segwit) = script_code(prev_script, redeem_script);
+    if segwit {
+        segwit_signature_hash(tx, index, &script_code, value, sighash_type)
+    } else {
+        tx.signature_hash(index, &script_code, sighash_type.as_u32())
+    }
+}
+
+/// Returns, for each input of `tx`, in input order, what its signer needs to know to sign
+/// with `sighash_type`.
+///
+/// `utxos` holds the outputs spent by `tx`, in any order. Fails if one is missing.
+pub fn signing_inputs(
+    tx: &Transaction,
+    utxos: &[ColoredUtxo],
+    sighash_type: SigHashType,
+) -> Result<Vec<SigningInput>, Error> {
+    tx.input
+        .iter()
+        .enumerate()
+        .map(|(index, input)| {
+            let outpoint = input.previous_output;
+            let utxo = utxos
+                .iter()
+                .find(|u| u.outpoint == outpoint)
+                .ok_or(Error::OutputNotFound(outpoint))?;
+            let prev_script = &utxo.txout.script_pubkey;
+            let redeem_script = utxo.redeem_script.as_ref();
+            let (script_code, segwit) = script_code(prev_script, redeem_script);
+            let value = utxo.txout.value;
+            Ok(SigningInput {
+                outpoint,
+                script_code,
+                value,
+                segwit,
+                sighash: signature_hash(tx, index, prev_script, redeem_script, value, sighash_type),
+            })
+        })
+        .collect()
+}
+
+/// Returns the hash signed by segwit input `index` of `tx`, as defined by BIP 143.
+fn segwit_signature_hash(
+    tx: &Transaction,
+    index: usize,
+    script_code: &Script,
+    value: u64,
+    sighash_type: SigHashType,
+) -> sha256d::Hash {
+    let anyone_can_pay = sighash_type.as_u32() & 0x80 != 0;
+    let base_type = SigHashType::from_u32(sighash_type.as_u32() & 0x1f);
+    let zero = sha256d::Hash::from_slice(&[0; 32]).unwrap();
+    let hash_prevouts = if anyone_can_pay {
+        zero
+    } else {
+        let data: Vec<u8> = tx
+            .input
+            .iter()
+            .flat_map(|i| serialize(&i.previous_output))
+            .collect();
+        sha256d::Hash::hash(&data)
+    };
+    let hash_sequence = if anyone_can_pay || base_type != SigHashType::All {
+        zero
+    } else {
+        let data: Vec<u8> = tx
+            .input
+            .iter()
+            .flat_map(|i| serialize(&i.sequence))
+            .collect();
+        sha256d::Hash::hash(&data)
+    };
+    let hash_outputs = match base_type {
+        SigHashType::All => {
+            let data: Vec<u8> = tx.output.iter().flat_map(serialize).collect();
+            sha256d::Hash::hash(&data)
+        }
+        SigHashType::Single if index < tx.output.len() => {
+            sha256d::Hash::hash(&serialize(&tx.output[index]))
+        }
+        _ => zero,
+    };
+
+    let input = &tx.input[index];
+    let mut preimage = serialize(&tx.version);
+    preimage.extend(&hash_prevouts[..]);
+    preimage.extend(&hash_sequence[..]);
+    preimage.extend(serialize(&input.previous_output));
+    preimage.extend(serialize(script_code));
+    preimage.extend(serialize(&value));
+    preimage.extend(serialize(&input.sequence));
+    preimage.extend(&hash_outputs[..]);
+    preimage.extend(serialize(&tx.lock_time));
+    preimage.extend(serialize(&sighash_type.as_u32()));
+    sha256d::Hash::hash(&preimage)
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::blockdata::transaction::SigHashType;
+    use bitcoin::consensus::deserialize;
+    use bitcoin::{Transaction, TxOut};
+    use bitcoin_hashes::{sha256d, Hash};
+    use hex::decode as hex_decode;
+    use openassets::coloring::ColoredOutput;
+    use openassets::error::Error;
+    use openassets::sighash::{script_code, signature_hash, signing_inputs, SigningInput};
+    use openassets::test_helpers::script;
+    use openassets::utxo::ColoredUtxo;
+
+    fn hash(hex: &str) -> sha256d::Hash {
+        sha256d::Hash::from_slice(&hex_decode(hex).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_signature_hash() {
+        // native P2WPKH example of BIP 143
+        let tx: Transaction = deserialize(&hex_decode("0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000").unwrap()).unwrap();
+        let p2wpkh = script("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1");
+        assert_eq!(
+            (
+                script("76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac"),
+                true
+            ),
+            script_code(&p2wpkh, None)
+        );
+        assert_eq!(
+            hash("c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670"),
+            signature_hash(&tx, 1, &p2wpkh, None, 600_000_000, SigHashType::All)
+        );
+
+        // P2SH-P2WPKH example of BIP 143
+        let tx: Transaction = deserialize(&hex_decode("0100000001db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a54770100000000feffffff02b8b4eb0b000000001976a914a457b684d7f0d539a46a45bbc043f35b59d0d96388ac0008af2f000000001976a914fd270b1ee6abcaea97fea7ad0402e8bd8ad6d77c88ac92040000").unwrap()).unwrap();
+        let p2sh = script("a9144733f37cf4db86fbc2efed2500b4f4e49f31202387");
+        let redeem_script = script("001479091972186c449eb1ded22b78e40d009bdf0089");
+        assert_eq!(
+            hash("64f3b0f4dd2bb3aa1ce8566d220cc74dda9df97d8490cc81d89d735c92e59fb6"),
+            signature_hash(
+                &tx,
+                0,
+                &p2sh,
+                Some(&redeem_script),
+                1_000_000_000,
+                SigHashType::All
+            )
+        );
+
+        // legacy inputs are hashed by rust-bitcoin
+        let p2pkh = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+        assert_eq!((p2pkh.clone(), false), script_code(&p2pkh, None));
+        assert_eq!(
+            tx.signature_hash(0, &p2pkh, 1),
+            signature_hash(&tx, 0, &p2pkh, None, 0, SigHashType::All)
+        );
+    }
+
+    #[test]
+    fn test_signing_inputs() {
+        // P2SH-P2WPKH example of BIP 143
+        let tx: Transaction = deserialize(&hex_decode("0100000001db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a54770100000000feffffff02b8b4eb0b000000001976a914a457b684d7f0d539a46a45bbc043f35b59d0d96388ac0008af2f000000001976a914fd270b1ee6abcaea97fea7ad0402e8bd8ad6d77c88ac92040000").unwrap()).unwrap();
+        let outpoint = tx.input[0].previous_output;
+        let utxo = ColoredUtxo::new(
+            outpoint,
+            TxOut {
+                value: 1_000_000_000,
+                script_pubkey: script("a9144733f37cf4db86fbc2efed2500b4f4e49f31202387"),
+            },
+            ColoredOutput::uncolored(),
+        )
+        .with_redeem_script(script("001479091972186c449eb1ded22b78e40d009bdf0089"));
+        assert_eq!(
+            vec![SigningInput {
+                outpoint,
+                script_code: script("76a91479091972186c449eb1ded22b78e40d009bdf008988ac"),
+                value: 1_000_000_000,
+                segwit: true,
+                sighash: hash("64f3b0f4dd2bb3aa1ce8566d220cc74dda9df97d8490cc81d89d735c92e59fb6"),
+            }],
+            signing_inputs(&tx, &[utxo], SigHashType::All).unwrap()
+        );
+
+        match signing_inputs(&tx, &[], SigHashType::All) {
+            Err(Error::OutputNotFound(o)) => assert_eq!(outpoint, o),
+            _ => panic!("expected OutputNotFound"),
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use bitcoin::network::constants::Network;
+use bitcoin::util::key::{PrivateKey, PublicKey};
+use bitcoin_hashes::{sha256d, Hash};
+use openassets::asset_id::AssetId;
+use openassets::definition::{AssetDefinition, DefinitionError};
+use secp256k1::{self, Message, Secp256k1};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// A detached signature of an asset definition by the key of its issuer, along with the
+/// public key it verifies against.
+///
+/// Encoded as the hex of the public key followed by the 64 bytes of the compact signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DefinitionSignature {
+    pub public_key: PublicKey,
+    pub signature: secp256k1::Signature,
+}
+
+/// Who is expected to have signed an asset definition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Issuer<'a> {
+    PublicKey(&'a PublicKey),
+    /// The issuer of the asset, i.e. the key whose P2PKH script has the asset id.
+    AssetId(&'a AssetId),
+}
+
+/// Returns the digest signed by issuers, the double SHA-256 of the asset definition `file` as
+/// served, byte for byte.
+pub fn signature_hash(file: &[u8]) -> sha256d::Hash {
+    sha256d::Hash::hash(file)
+}
+
+/// Signs the asset definition `file` with `key`, e.g. the key issuing the asset.
+///
+/// The signature covers the bytes of the file, so it is invalidated by any change to them,
+/// including reformatting the JSON without changing the definition.
+pub fn sign(file: &[u8], key: &PrivateKey) -> DefinitionSignature {
+    let secp = Secp256k1::signing_only();
+    let message = Message::from_slice(&signature_hash(file)[..]).expect("32 byte digest");
+    DefinitionSignature {
+        public_key: PublicKey::from_private_key(&secp, key),
+        signature: secp.sign(&message, &key.key),
+    }
+}
+
+/// Checks that `signature` signs the asset definition `file` with the key of `issuer`.
+pub fn verify(
+    file: &[u8],
+    signature: &DefinitionSignature,
+    issuer: Issuer,
+) -> Result<(), DefinitionError> {
+    let issued = match issuer {
+        Issuer::PublicKey(public_key) => *public_key == signature.public_key,
+        Issuer::AssetId(asset_id) => {
+            let script =
+                bitcoin::Address::p2pkh(&signature.public_key, Network::Bitcoin).script_pubkey();
+            AssetId::new(&script, Network::Bitcoin).same_asset(asset_id)
+        }
+    };
+    if !issued {
+        return Err(DefinitionError::InvalidSignature);
+    }
+    let message = Message::from_slice(&signature_hash(file)[..]).expect("32 byte digest");
+    Secp256k1::verification_only()
+        .verify(&message, &signature.signature, &signature.public_key.key)
+        .map_err(|_| DefinitionError::InvalidSignature)
+}
+
+/// Checks that `signature` signs the asset definition `file` with the key of `issuer`, then
+/// parses and validates the file.
+pub fn verify_json(
+    file: &[u8],
+    signature: &DefinitionSignature,
+    issuer: Issuer,
+) -> Result<AssetDefinition, DefinitionError> {
+    verify(file, signature, issuer)?;
+    AssetDefinition::from_json(file)
+}
+
+impl Display for DefinitionSignature {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            hex::encode(self.public_key.to_bytes()),
+            hex::encode(&self.signature.serialize_compact()[..])
+        )
+    }
+}
+
+impl FromStr for DefinitionSignature {
+    type Err = DefinitionError;
+
+    fn from_str(s: &str) -> Result<DefinitionSignature, DefinitionError> {
+        let data = hex::decode(s).map_err(|_| DefinitionError::InvalidSignature)?;
+        if data.len() < 64 {
+            return Err(DefinitionError::InvalidSignature);
+        }
+        let (key, signature) = data.split_at(data.len() - 64);
+        Ok(DefinitionSignature {
+            public_key: PublicKey::from_slice(key)
+                .map_err(|_| DefinitionError::InvalidSignature)?,
+            signature: secp256k1::Signature::from_compact(signature)
+                .map_err(|_| DefinitionError::InvalidSignature)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::blockdata::script::Builder;
+    use bitcoin::network::constants::Network;
+    use bitcoin::util::key::{PrivateKey, PublicKey};
+    use openassets::asset_id::AssetId;
+    use openassets::definition::{AssetDefinition, DefinitionError};
+    use openassets::signature::{sign, verify, verify_json, DefinitionSignature, Issuer};
+    use secp256k1::Secp256k1;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_sign() {
+        let key =
+            PrivateKey::from_wif("KwDiBf89QgGbjEhKnhXJuH7LrciVrZi3qYjgd9M7rFU73sVHnoWn").unwrap();
+        let public_key = PublicKey::from_private_key(&Secp256k1::signing_only(), &key);
+        let script = bitcoin::Address::p2pkh(&public_key, Network::Bitcoin).script_pubkey();
+        let asset_id = AssetId::new(&script, Network::Testnet);
+
+        let file = format!(r#"{{"asset_ids": ["{}"], "name": "MyAsset"}}"#, asset_id);
+        let file = file.as_bytes();
+        let signature = sign(file, &key);
+        assert_eq!(public_key, signature.public_key);
+        assert_eq!(
+            Ok(()),
+            verify(file, &signature, Issuer::PublicKey(&public_key))
+        );
+        assert_eq!(Ok(()), verify(file, &signature, Issuer::AssetId(&asset_id)));
+        assert_eq!(
+            Some("MyAsset".to_string()),
+            verify_json(file, &signature, Issuer::AssetId(&asset_id))
+                .unwrap()
+                .name
+        );
+
+        let encoded = signature.to_string();
+        assert_eq!((33 + 64) * 2, encoded.len());
+        assert_eq!(signature, DefinitionSignature::from_str(&encoded).unwrap());
+
+        // the same definition, reformatted, is another file
+        let reformatted = AssetDefinition::from_json(file).unwrap().to_json();
+        assert_eq!(
+            AssetDefinition::from_json(file).unwrap(),
+            AssetDefinition::from_json(reformatted.as_bytes()).unwrap()
+        );
+        assert_eq!(
+            Err(DefinitionError::InvalidSignature),
+            verify(
+                reformatted.as_bytes(),
+                &signature,
+                Issuer::AssetId(&asset_id)
+            )
+        );
+        assert_eq!(
+            Err(DefinitionError::InvalidSignature),
+            verify_json(
+                reformatted.as_bytes(),
+                &signature,
+                Issuer::AssetId(&asset_id)
+            )
+        );
+
+        // another file, key or asset
+        let forged = br#"{"name": "Forged"}"#;
+        assert_eq!(
+            Err(DefinitionError::InvalidSignature),
+            verify(forged, &signature, Issuer::AssetId(&asset_id))
+        );
+        let other =
+            PrivateKey::from_wif("L4rK1yDtCWekvXuE6oXD9jCYfFNV2cWRpVuPLBcCU2z8TrisoyY1").unwrap();
+        let other_key = PublicKey::from_private_key(&Secp256k1::signing_only(), &other);
+        assert_eq!(
+            Err(DefinitionError::InvalidSignature),
+            verify(file, &signature, Issuer::PublicKey(&other_key))
+        );
+        assert_eq!(
+            Err(DefinitionError::InvalidSignature),
+            verify(file, &sign(file, &other), Issuer::AssetId(&asset_id))
+        );
+        let other_asset = AssetId::new(&Builder::new().into_script(), Network::Bitcoin);
+        assert_eq!(
+            Err(DefinitionError::InvalidSignature),
+            verify(file, &signature, Issuer::AssetId(&other_asset))
+        );
+
+        for invalid in &["", "00", &encoded[2..], &encoded[..encoded.len() - 2]] {
+            assert!(DefinitionSignature::from_str(invalid).is_err());
+        }
+    }
+}
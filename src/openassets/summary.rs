@@ -0,0 +1,123 @@
+use bitcoin::Transaction;
+use openassets::asset_id::AssetId;
+use openassets::burn::is_burn;
+use openassets::coloring::{ColoredOutput, OutputKind};
+use openassets::error::Error;
+use std::collections::HashMap;
+
+/// The quantities of one asset moved by a transaction.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct AssetFlow {
+    /// Units held by the inputs.
+    pub consumed: u64,
+    /// Units created by issuance outputs.
+    pub issued: u64,
+    /// Units sent to spendable transfer outputs.
+    pub transferred: u64,
+    /// Units held by the inputs which do not reach a spendable output.
+    pub burned: u64,
+}
+
+/// The per-asset quantities moved by a colored transaction.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct AssetSummary {
+    pub assets: HashMap<AssetId, AssetFlow>,
+}
+
+impl AssetSummary {
+    /// Summarizes `tx` from the colors of its inputs and outputs, as returned by
+    /// `ColoringEngine`.
+    pub fn from_transaction(
+        tx: &Transaction,
+        input_colors: &[ColoredOutput],
+        output_colors: &[ColoredOutput],
+    ) -> Result<AssetSummary, Error> {
+        let mut assets: HashMap<AssetId, AssetFlow> = HashMap::new();
+        if !tx.is_coin_base() {
+            for input in input_colors.iter() {
+                if let Some(ref asset_id) = input.asset_id {
+                    let flow = assets.entry(asset_id.clone()).or_default();
+                    flow.consumed = flow
+                        .consumed
+                        .checked_add(input.asset_quantity)
+                        .ok_or(Error::QuantityOverflow)?;
+                }
+            }
+        }
+        for (output, color) in tx.output.iter().zip(output_colors.iter()) {
+            let asset_id = match color.asset_id {
+                Some(ref asset_id) => asset_id,
+                None => continue,
+            };
+            let flow = assets.entry(asset_id.clone()).or_default();
+            let total = match color.kind {
+                OutputKind::Issuance => &mut flow.issued,
+                OutputKind::Transfer if !is_burn(output, color) => &mut flow.transferred,
+                _ => continue,
+            };
+            *total = total
+                .checked_add(color.asset_quantity)
+                .ok_or(Error::QuantityOverflow)?;
+        }
+        for flow in assets.values_mut() {
+            flow.burned = flow.consumed.saturating_sub(flow.transferred);
+        }
+        Ok(AssetSummary { assets })
+    }
+
+    /// Returns the quantities moved for `asset_id`, if the transaction involves it.
+    pub fn get(&self, asset_id: &AssetId) -> Option<&AssetFlow> {
+        self.assets.get(asset_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::network::constants::Network;
+    use openassets::asset_id::AssetId;
+    use openassets::coloring::{ColoredOutput, ColoringEngine, OutputKind};
+    use openassets::summary::{AssetFlow, AssetSummary};
+    use openassets::test_helpers::{build_tx, script};
+
+    #[test]
+    fn test_from_transaction() {
+        let issuer = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let p2pkh = "76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac";
+        let asset_a = AssetId::new(&issuer, Network::Bitcoin);
+        let asset_b = AssetId::new(
+            &script("a914f9d499817e88ef7b10a88673296c6d6df2f4292d87"),
+            Network::Bitcoin,
+        );
+        let inputs = vec![ColoredOutput::new(
+            asset_b.clone(),
+            10,
+            OutputKind::Transfer,
+        )];
+
+        // issue 5 units, transfer 3 units and send 2 units to OP_RETURN, 5 units are left over
+        let tx = build_tx(1, vec![p2pkh, "6a094f4101000305030200", p2pkh, "6a00"]);
+        let outputs = ColoringEngine::new(Network::Bitcoin)
+            .color_transaction(&tx, &issuer, &inputs)
+            .unwrap();
+        let summary = AssetSummary::from_transaction(&tx, &inputs, &outputs).unwrap();
+        assert_eq!(2, summary.assets.len());
+        assert_eq!(
+            Some(&AssetFlow {
+                consumed: 0,
+                issued: 5,
+                transferred: 0,
+                burned: 0,
+            }),
+            summary.get(&asset_a)
+        );
+        assert_eq!(
+            Some(&AssetFlow {
+                consumed: 10,
+                issued: 0,
+                transferred: 3,
+                burned: 7,
+            }),
+            summary.get(&asset_b)
+        );
+    }
+}
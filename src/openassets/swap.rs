@@ -0,0 +1,198 @@
+use bitcoin::blockdata::transaction::SigHashType;
+use bitcoin::{Script, Transaction};
+use openassets::asset_id::AssetId;
+use openassets::builder::{marker_txout, validate_layout, BuildError, TransferBuilder};
+use openassets::coloring::OutputKind;
+use openassets::marker_output::{Payload, MAX_OP_RETURN_SIZE};
+use openassets::sighash::{signing_inputs, SigningInput};
+use openassets::utxo::ColoredUtxo;
+
+/// One side of an asset swap.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct SwapParty {
+    /// The colored UTXOs holding the asset given, and the uncolored UTXOs funding this side.
+    pub inputs: Vec<ColoredUtxo>,
+    /// The asset given to the other party.
+    pub asset_id: AssetId,
+    /// The quantity given to the other party.
+    pub quantity: u64,
+    /// The script receiving the asset of the other party.
+    pub receive_script: Script,
+    /// The script receiving the colored and bitcoin change of this side.
+    pub change_script: Script,
+    /// The share of the fee paid by this side, in satoshis.
+    pub fee: u64,
+}
+
+/// Builds transactions swapping units of one asset for units of another, atomically.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct SwapBuilder {
+    a: SwapParty,
+    b: SwapParty,
+}
+
+impl SwapBuilder {
+    pub fn new(a: SwapParty, b: SwapParty) -> SwapBuilder {
+        SwapBuilder { a, b }
+    }
+
+    /// Builds the unsigned transaction.
+    ///
+    /// Each side is laid out as a transfer from the party to the other, with its own colored
+    /// and bitcoin change, and the inputs and outputs of the side of `a` come first. The
+    /// bitcoin change output of `a`, if any, gets a zero quantity in the marker output, so
+    /// that the quantities of `b` still line up with its outputs.
+    pub fn build(&self) -> Result<Transaction, BuildError> {
+        let (tx_a, payload_a) = side(&self.a, &self.b).build_with_payload()?;
+        let (tx_b, payload_b) = side(&self.b, &self.a).build_with_payload()?;
+
+        let mut quantities = payload_a.quantities;
+        quantities.resize(tx_a.output.len() - 1, 0);
+        quantities.extend(payload_b.quantities);
+        let mut layout = vec![OutputKind::Marker];
+        layout.extend(quantities.iter().map(|&q| match q {
+            0 => OutputKind::Uncolored,
+            _ => OutputKind::Transfer,
+        }));
+        let payload = Payload::new(quantities, payload_a.metadata);
+        let mut output = vec![marker_txout(&payload, MAX_OP_RETURN_SIZE)?];
+        output.extend(tx_a.output.into_iter().skip(1));
+        output.extend(tx_b.output.into_iter().skip(1));
+        let mut input = tx_a.input;
+        input.extend(tx_b.input);
+        let tx = Transaction {
+            version: tx_a.version,
+            lock_time: tx_a.lock_time,
+            input,
+            output,
+        };
+        validate_layout(&tx, &layout)?;
+        Ok(tx)
+    }
+
+    /// Returns, for each input of `tx` built by this builder, what its signer needs to know to
+    /// sign with `sighash_type`. Each party signs the inputs spending its own UTXOs.
+    pub fn signing_inputs(
+        &self,
+        tx: &Transaction,
+        sighash_type: SigHashType,
+    ) -> Result<Vec<SigningInput>, BuildError> {
+        let utxos: Vec<ColoredUtxo> = self
+            .a
+            .inputs
+            .iter()
+            .chain(&self.b.inputs)
+            .cloned()
+            .collect();
+        Ok(signing_inputs(tx, &utxos, sighash_type)?)
+    }
+}
+
+/// Returns the builder of the transfer from `from` to `to`.
+fn side(from: &SwapParty, to: &SwapParty) -> TransferBuilder {
+    from.inputs
+        .iter()
+        .fold(TransferBuilder::new(from.change_script.clone()), |b, u| {
+            b.add_input(u.clone())
+        })
+        .add_recipient(
+            from.asset_id.clone(),
+            from.quantity,
+            to.receive_script.clone(),
+        )
+        .fee(from.fee)
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::network::constants::Network;
+    use bitcoin::Script;
+    use openassets::asset_id::AssetId;
+    use openassets::builder::BuildError;
+    use openassets::coloring::{ColoredOutput, ColoringEngine, ColoringPolicy, OutputKind};
+    use openassets::marker_output::TxOutExt;
+    use openassets::swap::{SwapBuilder, SwapParty};
+    use openassets::test_helpers::{script, utxo};
+
+    #[test]
+    fn test_build() {
+        let alice = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let bob = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+        let asset_x = AssetId::new(&alice, Network::Bitcoin);
+        let asset_y = AssetId::new(&bob, Network::Bitcoin);
+        let x = ColoredOutput::new(asset_x.clone(), 100, OutputKind::Transfer);
+        let y = ColoredOutput::new(asset_y.clone(), 50, OutputKind::Transfer);
+        let a = SwapParty {
+            inputs: vec![
+                utxo(0, 600, x.clone()),
+                utxo(1, 5_000, ColoredOutput::uncolored()),
+            ],
+            asset_id: asset_x.clone(),
+            quantity: 30,
+            receive_script: alice.clone(),
+            change_script: alice.clone(),
+            fee: 1_000,
+        };
+        let b = SwapParty {
+            inputs: vec![
+                utxo(2, 600, y.clone()),
+                utxo(3, 5_000, ColoredOutput::uncolored()),
+            ],
+            asset_id: asset_y.clone(),
+            quantity: 20,
+            receive_script: bob.clone(),
+            change_script: bob.clone(),
+            fee: 1_000,
+        };
+        let tx = SwapBuilder::new(a.clone(), b.clone()).build().unwrap();
+
+        assert_eq!(
+            vec![30, 70, 0, 20, 30],
+            tx.output[0].get_oa_payload().unwrap().quantities
+        );
+        let input_colors = vec![x, ColoredOutput::uncolored(), y, ColoredOutput::uncolored()];
+        let colors = ColoringEngine::with_policy(Network::Bitcoin, ColoringPolicy::Strict)
+            .color_transaction(&tx, &Script::new(), &input_colors)
+            .unwrap();
+        let outputs: Vec<(&Script, ColoredOutput)> = tx
+            .output
+            .iter()
+            .map(|o| &o.script_pubkey)
+            .zip(colors)
+            .skip(1)
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    &bob,
+                    ColoredOutput::new(asset_x.clone(), 30, OutputKind::Transfer)
+                ),
+                (
+                    &alice,
+                    ColoredOutput::new(asset_x.clone(), 70, OutputKind::Transfer)
+                ),
+                (&alice, ColoredOutput::uncolored()),
+                (
+                    &alice,
+                    ColoredOutput::new(asset_y.clone(), 20, OutputKind::Transfer)
+                ),
+                (
+                    &bob,
+                    ColoredOutput::new(asset_y.clone(), 30, OutputKind::Transfer)
+                ),
+                (&bob, ColoredOutput::uncolored()),
+            ],
+            outputs
+        );
+        assert_eq!(3_400, tx.output[3].value);
+        assert_eq!(3_400, tx.output[6].value);
+
+        let b = SwapParty { quantity: 60, ..b };
+        match SwapBuilder::new(a, b).build() {
+            Err(BuildError::InsufficientAssetUnits { asset_id, .. }) => {
+                assert_eq!(asset_y, asset_id)
+            }
+            _ => panic!("expected InsufficientAssetUnits"),
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! Fixtures shared by the unit tests of the openassets modules.
+
+use bitcoin::blockdata::script::Builder;
+use bitcoin::{OutPoint, Script, Transaction, TxIn, TxOut};
+use hex::decode as hex_decode;
+use openassets::coloring::ColoredOutput;
+use openassets::utxo::ColoredUtxo;
+
+/// An output script, given either as a `Script` or as its hex encoding.
+pub trait IntoScript {
+    fn into_script(self) -> Script;
+}
+
+impl IntoScript for Script {
+    fn into_script(self) -> Script {
+        self
+    }
+}
+
+impl IntoScript for &str {
+    fn into_script(self) -> Script {
+        script(self)
+    }
+}
+
+/// Returns the script encoded by the hex string `hex`.
+pub fn script(hex: &str) -> Script {
+    Builder::from(hex_decode(hex).unwrap()).into_script()
+}
+
+/// Returns a transaction spending outputs 0 to `inputs - 1` of the all-zero txid, with one
+/// 600 satoshi output per script in `outputs`.
+pub fn build_tx<S: IntoScript>(inputs: usize, outputs: Vec<S>) -> Transaction {
+    let prevs = (0..inputs)
+        .map(|i| OutPoint {
+            txid: Default::default(),
+            vout: i as u32,
+        })
+        .collect();
+    build_spending_tx(prevs, outputs)
+}
+
+/// Returns a transaction spending `prevs`, with one 600 satoshi output per script in `outputs`.
+pub fn build_spending_tx<S: IntoScript>(prevs: Vec<OutPoint>, outputs: Vec<S>) -> Transaction {
+    Transaction {
+        version: 1,
+        lock_time: 0,
+        input: prevs
+            .into_iter()
+            .map(|previous_output| TxIn {
+                previous_output,
+                script_sig: Script::new(),
+                sequence: 0xFFFF_FFFF,
+                witness: vec![],
+            })
+            .collect(),
+        output: outputs
+            .into_iter()
+            .map(|s| TxOut {
+                value: 600,
+                script_pubkey: s.into_script(),
+            })
+            .collect(),
+    }
+}
+
+/// Returns an unspent output of `value` satoshis at `vout` of the all-zero txid, with an empty
+/// script.
+pub fn utxo(vout: u32, value: u64, colored: ColoredOutput) -> ColoredUtxo {
+    utxo_with_script(vout, value, &Script::new(), colored)
+}
+
+/// Returns an unspent output of `value` satoshis at `vout` of the all-zero txid, locked by
+/// `script`.
+pub fn utxo_with_script(
+    vout: u32,
+    value: u64,
+    script: &Script,
+    colored: ColoredOutput,
+) -> ColoredUtxo {
+    ColoredUtxo::new(
+        OutPoint {
+            txid: Default::default(),
+            vout,
+        },
+        TxOut {
+            value,
+            script_pubkey: script.clone(),
+        },
+        colored,
+    )
+}
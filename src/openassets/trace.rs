@@ -0,0 +1,114 @@
+use openassets::asset_id::AssetId;
+use std::fmt::{self, Display, Formatter};
+
+/// A single decision taken while coloring a transaction.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum TraceStep {
+    /// The transaction is a coinbase transaction, none of its outputs are colored.
+    Coinbase,
+    /// The transaction has no valid marker output.
+    NoMarker,
+    /// The marker output at `index` lists `quantities`.
+    MarkerFound { index: usize, quantities: Vec<u64> },
+    /// The marker lists more quantities than there are non-marker outputs.
+    TooManyQuantities { quantities: usize, outputs: usize },
+    /// `quantity` units of `asset_id` were issued to `output`.
+    Issued {
+        output: usize,
+        asset_id: AssetId,
+        quantity: u64,
+    },
+    /// `quantity` units of `asset_id` were moved from `input` to `output`.
+    Transferred {
+        input: usize,
+        output: usize,
+        asset_id: AssetId,
+        quantity: u64,
+    },
+    /// `input` holds a different asset than the units already assigned to `output`.
+    MixedAssets { input: usize, output: usize },
+    /// The inputs ran out of units while `missing` units of `output` were still unassigned.
+    Underflow { output: usize, missing: u64 },
+}
+
+impl Display for TraceStep {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            TraceStep::Coinbase => write!(f, "coinbase transaction, outputs are uncolored"),
+            TraceStep::NoMarker => write!(f, "no marker output, outputs are uncolored"),
+            TraceStep::MarkerFound {
+                index,
+                ref quantities,
+            } => write!(
+                f,
+                "marker output #{} with quantities {:?}",
+                index, quantities
+            ),
+            TraceStep::TooManyQuantities {
+                quantities,
+                outputs,
+            } => write!(
+                f,
+                "{} quantities for {} outputs, outputs are uncolored",
+                quantities, outputs
+            ),
+            TraceStep::Issued {
+                output,
+                ref asset_id,
+                quantity,
+            } => write!(
+                f,
+                "issued {} of {} to output #{}",
+                quantity, asset_id, output
+            ),
+            TraceStep::Transferred {
+                input,
+                output,
+                ref asset_id,
+                quantity,
+            } => write!(
+                f,
+                "transferred {} of {} from input #{} to output #{}",
+                quantity, asset_id, input, output
+            ),
+            TraceStep::MixedAssets { input, output } => write!(
+                f,
+                "input #{} mixes assets into output #{}, outputs are uncolored",
+                input, output
+            ),
+            TraceStep::Underflow { output, missing } => write!(
+                f,
+                "inputs exhausted with {} units missing for output #{}, outputs are uncolored",
+                missing, output
+            ),
+        }
+    }
+}
+
+/// The steps taken while coloring a transaction, in order.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct ColoringTrace {
+    pub steps: Vec<TraceStep>,
+}
+
+impl ColoringTrace {
+    pub fn new() -> ColoringTrace {
+        ColoringTrace { steps: vec![] }
+    }
+}
+
+impl Display for ColoringTrace {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for step in self.steps.iter() {
+            writeln!(f, "{}", step)?;
+        }
+        Ok(())
+    }
+}
+
+/// Appends the step built by `step` to `trace` when tracing is enabled.
+pub(crate) fn record<F: FnOnce() -> TraceStep>(trace: &mut Option<&mut ColoringTrace>, step: F) {
+    if let Some(ref mut trace) = *trace {
+        trace.steps.push(step());
+    }
+}
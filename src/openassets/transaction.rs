@@ -0,0 +1,119 @@
+use bitcoin::network::constants::Network;
+use bitcoin::Transaction;
+use openassets::coloring::{ColoredOutput, ColoringEngine, OutputProvider};
+use openassets::error::Error;
+use openassets::marker_output::{marker_candidates, Payload, TxOutExt};
+
+pub trait TransactionExt {
+    /// Returns the index and payload of the first valid marker output.
+    fn find_marker_output(&self) -> Option<(usize, Payload)>;
+
+    /// Returns the index of the first valid marker output.
+    fn marker_index(&self) -> Option<usize>;
+
+    /// Returns the index and payload of every output which is a valid marker output, of which
+    /// only the first is the marker output of the transaction.
+    fn all_markers(&self) -> Vec<(usize, Payload)>;
+
+    /// Colors each output, resolving the colors of the inputs from `provider`.
+    fn get_asset_outputs<P: OutputProvider>(
+        &self,
+        provider: &P,
+        network: Network,
+    ) -> Result<Vec<ColoredOutput>, Error>;
+}
+
+impl TransactionExt for Transaction {
+    fn find_marker_output(&self) -> Option<(usize, Payload)> {
+        self.output
+            .iter()
+            .enumerate()
+            .filter(|&(_, o)| o.script_pubkey.is_op_return())
+            .find_map(|(i, o)| o.get_oa_payload().ok().map(|p| (i, p)))
+    }
+
+    fn marker_index(&self) -> Option<usize> {
+        self.find_marker_output().map(|(index, _)| index)
+    }
+
+    fn all_markers(&self) -> Vec<(usize, Payload)> {
+        marker_candidates(self)
+    }
+
+    fn get_asset_outputs<P: OutputProvider>(
+        &self,
+        provider: &P,
+        network: Network,
+    ) -> Result<Vec<ColoredOutput>, Error> {
+        ColoringEngine::new(network).resolve_transaction(self, provider)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::network::constants::Network;
+    use bitcoin::OutPoint;
+    use openassets::asset_id::AssetId;
+    use openassets::coloring::{ColoredOutput, OutputKind};
+    use openassets::test_helpers::{build_spending_tx, script};
+    use openassets::transaction::TransactionExt;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_find_marker_output() {
+        let p2pkh = "76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac";
+        let tx = build_spending_tx(
+            vec![OutPoint::default()],
+            vec![p2pkh, "6a084f41010002014400", "6a074f410100016400"],
+        );
+        let (index, payload) = tx.find_marker_output().unwrap();
+        assert_eq!(1, index);
+        assert_eq!(vec![1, 68], payload.quantities);
+        assert_eq!(Some(1), tx.marker_index());
+        let markers = tx.all_markers();
+        assert_eq!(
+            vec![1, 2],
+            markers.iter().map(|m| m.0).collect::<Vec<usize>>()
+        );
+        assert_eq!(vec![100], markers[1].1.quantities);
+
+        // OP_RETURN outputs other than marker outputs are skipped
+        let tx = build_spending_tx(
+            vec![OutPoint::default()],
+            vec!["6a0401020304", "6a074f410100016400"],
+        );
+        assert_eq!(Some(1), tx.marker_index());
+
+        let tx = build_spending_tx(vec![OutPoint::default()], vec![p2pkh]);
+        assert!(tx.find_marker_output().is_none());
+        assert!(tx.marker_index().is_none());
+        assert!(tx.all_markers().is_empty());
+    }
+
+    #[test]
+    fn test_get_asset_outputs() {
+        let issuer = "76a914010966776006953d5567439e5e39f86a0d273bee88ac";
+        let p2pkh = "76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac";
+        let funding = build_spending_tx(vec![OutPoint::default()], vec![issuer]);
+        let issuance = build_spending_tx(
+            vec![OutPoint {
+                txid: funding.txid(),
+                vout: 0,
+            }],
+            vec![p2pkh, "6a074f410100016400"],
+        );
+        let mut provider = HashMap::new();
+        provider.insert(funding.txid(), funding);
+
+        let asset_id = AssetId::new(&script(issuer), Network::Testnet);
+        assert_eq!(
+            vec![
+                ColoredOutput::new(asset_id, 100, OutputKind::Issuance),
+                ColoredOutput::marker(),
+            ],
+            issuance
+                .get_asset_outputs(&provider, Network::Testnet)
+                .unwrap()
+        );
+    }
+}
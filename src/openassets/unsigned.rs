@@ -0,0 +1,265 @@
+use bitcoin::blockdata::transaction::SigHashType;
+use bitcoin::consensus::encode;
+use bitcoin::consensus::{Decodable, Decoder, Encodable, Encoder};
+use bitcoin::network::constants::Network;
+use bitcoin::{Script, Transaction};
+use bitcoin_hashes::{hash160, Hash};
+use openassets::asset_id::AssetId;
+use openassets::builder::BuildError;
+use openassets::coloring::{ColoredOutput, ColoringEngine, ColoringPolicy, OutputKind};
+use openassets::error::Error;
+use openassets::sighash::{signing_inputs, SigningInput};
+use openassets::utxo::ColoredUtxo;
+
+/// An unsigned transaction along with the outputs it spends and the colors of its outputs,
+/// handed to an external signer and back.
+///
+/// The bundle is encoded with `bitcoin::consensus::serialize` and decoded with
+/// `bitcoin::consensus::deserialize`.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct UnsignedColoredTransaction {
+    /// The unsigned transaction.
+    pub tx: Transaction,
+    /// The outputs spent by the transaction, in input order.
+    pub inputs: Vec<ColoredUtxo>,
+    /// The color of each output of the transaction.
+    pub output_colors: Vec<ColoredOutput>,
+}
+
+impl UnsignedColoredTransaction {
+    /// Bundles `tx` with the outputs it spends, taken from `utxos`, and the colors of its
+    /// outputs on `network`.
+    ///
+    /// Fails if an output spent by `tx` is missing from `utxos`, or if `tx` is not a valid Open
+    /// Assets transaction.
+    pub fn new(
+        tx: Transaction,
+        utxos: &[ColoredUtxo],
+        network: Network,
+    ) -> Result<UnsignedColoredTransaction, Error> {
+        let inputs = tx
+            .input
+            .iter()
+            .map(|i| {
+                utxos
+                    .iter()
+                    .find(|u| u.outpoint == i.previous_output)
+                    .cloned()
+                    .ok_or(Error::OutputNotFound(i.previous_output))
+            })
+            .collect::<Result<Vec<ColoredUtxo>, Error>>()?;
+        let output_colors = ColoringEngine::with_policy(network, ColoringPolicy::Strict)
+            .color_transaction(
+                &tx,
+                inputs
+                    .first()
+                    .map(|u| &u.txout.script_pubkey)
+                    .unwrap_or(&Script::new()),
+                &inputs
+                    .iter()
+                    .map(|u| u.colored.clone())
+                    .collect::<Vec<ColoredOutput>>(),
+            )?;
+        Ok(UnsignedColoredTransaction {
+            tx,
+            inputs,
+            output_colors,
+        })
+    }
+
+    /// Returns the script spent by each input, in input order.
+    pub fn prev_scripts(&self) -> Vec<&Script> {
+        self.inputs.iter().map(|u| &u.txout.script_pubkey).collect()
+    }
+
+    /// Returns the color of each input, in input order.
+    pub fn input_colors(&self) -> Vec<ColoredOutput> {
+        self.inputs.iter().map(|u| u.colored.clone()).collect()
+    }
+
+    /// Returns, for each input, what its signer needs to know to sign with `sighash_type`.
+    pub fn signing_inputs(&self, sighash_type: SigHashType) -> Result<Vec<SigningInput>, Error> {
+        signing_inputs(&self.tx, &self.inputs, sighash_type)
+    }
+
+    /// Returns `signed`, the transaction signed by the external signer, after checking that
+    /// only the script_sigs and witnesses of its inputs differ from the unsigned transaction.
+    pub fn finalize(&self, signed: Transaction) -> Result<Transaction, BuildError> {
+        let mut unsigned = signed.clone();
+        for input in unsigned.input.iter_mut() {
+            input.script_sig = Script::new();
+            input.witness = vec![];
+        }
+        if unsigned != self.tx {
+            return Err(BuildError::TransactionMismatch);
+        }
+        Ok(signed)
+    }
+}
+
+impl<S: Encoder> Encodable<S> for UnsignedColoredTransaction {
+    fn consensus_encode(&self, s: &mut S) -> Result<(), encode::Error> {
+        self.tx.consensus_encode(s)?;
+        self.inputs.consensus_encode(s)?;
+        self.output_colors.consensus_encode(s)
+    }
+}
+
+impl<D: Decoder> Decodable<D> for UnsignedColoredTransaction {
+    fn consensus_decode(d: &mut D) -> Result<UnsignedColoredTransaction, encode::Error> {
+        Ok(UnsignedColoredTransaction {
+            tx: Decodable::consensus_decode(d)?,
+            inputs: Decodable::consensus_decode(d)?,
+            output_colors: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl<S: Encoder> Encodable<S> for ColoredUtxo {
+    fn consensus_encode(&self, s: &mut S) -> Result<(), encode::Error> {
+        self.outpoint.consensus_encode(s)?;
+        self.txout.consensus_encode(s)?;
+        self.colored.consensus_encode(s)?;
+        match self.redeem_script {
+            Some(ref script) => {
+                1u8.consensus_encode(s)?;
+                script.consensus_encode(s)
+            }
+            None => 0u8.consensus_encode(s),
+        }
+    }
+}
+
+impl<D: Decoder> Decodable<D> for ColoredUtxo {
+    fn consensus_decode(d: &mut D) -> Result<ColoredUtxo, encode::Error> {
+        let utxo = ColoredUtxo::new(
+            Decodable::consensus_decode(d)?,
+            Decodable::consensus_decode(d)?,
+            Decodable::consensus_decode(d)?,
+        );
+        let flag: u8 = Decodable::consensus_decode(d)?;
+        match flag {
+            0 => Ok(utxo),
+            1 => Ok(utxo.with_redeem_script(Decodable::consensus_decode(d)?)),
+            _ => Err(encode::Error::ParseFailed("Invalid redeem script flag.")),
+        }
+    }
+}
+
+impl<S: Encoder> Encodable<S> for ColoredOutput {
+    fn consensus_encode(&self, s: &mut S) -> Result<(), encode::Error> {
+        match self.asset_id {
+            Some(ref asset_id) => {
+                1u8.consensus_encode(s)?;
+                asset_id.hash[..].to_vec().consensus_encode(s)?;
+                asset_id.network.magic().consensus_encode(s)?;
+            }
+            None => 0u8.consensus_encode(s)?,
+        }
+        self.asset_quantity.consensus_encode(s)?;
+        let kind: u8 = match self.kind {
+            OutputKind::Issuance => 0,
+            OutputKind::Transfer => 1,
+            OutputKind::Marker => 2,
+            OutputKind::Uncolored => 3,
+        };
+        kind.consensus_encode(s)
+    }
+}
+
+impl<D: Decoder> Decodable<D> for ColoredOutput {
+    fn consensus_decode(d: &mut D) -> Result<ColoredOutput, encode::Error> {
+        let flag: u8 = Decodable::consensus_decode(d)?;
+        let asset_id = match flag {
+            0 => None,
+            1 => {
+                let hash: Vec<u8> = Decodable::consensus_decode(d)?;
+                let hash = hash160::Hash::from_slice(&hash)
+                    .map_err(|_| encode::Error::ParseFailed("Invalid asset id."))?;
+                let magic: u32 = Decodable::consensus_decode(d)?;
+                let network = Network::from_magic(magic)
+                    .ok_or(encode::Error::ParseFailed("Invalid network."))?;
+                Some(AssetId { hash, network })
+            }
+            _ => return Err(encode::Error::ParseFailed("Invalid asset id flag.")),
+        };
+        let asset_quantity: u64 = Decodable::consensus_decode(d)?;
+        let kind: u8 = Decodable::consensus_decode(d)?;
+        let kind = match kind {
+            0 => OutputKind::Issuance,
+            1 => OutputKind::Transfer,
+            2 => OutputKind::Marker,
+            3 => OutputKind::Uncolored,
+            _ => return Err(encode::Error::ParseFailed("Invalid output kind.")),
+        };
+        Ok(ColoredOutput {
+            asset_id,
+            asset_quantity,
+            kind,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::blockdata::transaction::SigHashType;
+    use bitcoin::consensus::{deserialize, serialize};
+    use bitcoin::network::constants::Network;
+    use bitcoin::{OutPoint, TxOut};
+    use openassets::asset_id::AssetId;
+    use openassets::builder::{BuildError, TransferBuilder};
+    use openassets::coloring::{ColoredOutput, OutputKind};
+    use openassets::test_helpers::script;
+    use openassets::unsigned::UnsignedColoredTransaction;
+    use openassets::utxo::ColoredUtxo;
+
+    #[test]
+    fn test_unsigned_colored_transaction() {
+        let change = script("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let alice = script("76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac");
+        let asset_id = AssetId::new(&alice, Network::Testnet);
+        let colored = ColoredUtxo::new(
+            OutPoint {
+                txid: Default::default(),
+                vout: 1,
+            },
+            TxOut {
+                value: 10_000,
+                script_pubkey: script("a9144733f37cf4db86fbc2efed2500b4f4e49f31202387"),
+            },
+            ColoredOutput::new(asset_id.clone(), 10, OutputKind::Transfer),
+        )
+        .with_redeem_script(script("001479091972186c449eb1ded22b78e40d009bdf0089"));
+        let unsigned = TransferBuilder::new(change)
+            .add_input(colored)
+            .add_recipient(asset_id.clone(), 4, alice)
+            .fee(1_000)
+            .build_unsigned()
+            .unwrap();
+        assert_eq!(
+            vec![
+                ColoredOutput::marker(),
+                ColoredOutput::new(asset_id.clone(), 4, OutputKind::Transfer),
+                ColoredOutput::new(asset_id, 6, OutputKind::Transfer),
+                ColoredOutput::uncolored(),
+            ],
+            unsigned.output_colors
+        );
+
+        let decoded: UnsignedColoredTransaction = deserialize(&serialize(&unsigned)).unwrap();
+        assert_eq!(unsigned, decoded);
+        let signing = decoded.signing_inputs(SigHashType::All).unwrap();
+        assert!(signing[0].segwit);
+
+        let mut signed = unsigned.tx.clone();
+        signed.input[0].script_sig = script("16001479091972186c449eb1ded22b78e40d009bdf0089");
+        signed.input[0].witness = vec![vec![1; 72], vec![2; 33]];
+        assert_eq!(signed, decoded.finalize(signed.clone()).unwrap());
+
+        signed.output[1].value += 1;
+        match decoded.finalize(signed) {
+            Err(BuildError::TransactionMismatch) => {}
+            _ => panic!("expected TransactionMismatch"),
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use bitcoin::consensus::encode;
+use openassets::address::Address;
+use openassets::asset_id::AssetId;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// The scheme of payment URIs.
+pub const PAYMENT_URI_SCHEME: &str = "bitcoin";
+
+/// A request to pay a quantity of an asset to an address, encoded as a BIP21-style
+/// `bitcoin:<address>?asset=<asset id>&amount=<quantity>&label=<label>&message=<message>` URI
+/// like legacy Open Assets wallets do.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct PaymentUri {
+    pub address: Address,
+    pub asset_id: AssetId,
+    /// The requested quantity of the asset.
+    pub amount: Option<u64>,
+    pub label: Option<String>,
+    pub message: Option<String>,
+}
+
+impl PaymentUri {
+    pub fn new(address: Address, asset_id: AssetId) -> PaymentUri {
+        PaymentUri {
+            address,
+            asset_id,
+            amount: None,
+            label: None,
+            message: None,
+        }
+    }
+
+    pub fn amount(mut self, amount: u64) -> PaymentUri {
+        self.amount = Some(amount);
+        self
+    }
+
+    pub fn label(mut self, label: &str) -> PaymentUri {
+        self.label = Some(label.to_string());
+        self
+    }
+
+    pub fn message(mut self, message: &str) -> PaymentUri {
+        self.message = Some(message.to_string());
+        self
+    }
+}
+
+impl Display for PaymentUri {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "{}:{}?asset={}",
+            PAYMENT_URI_SCHEME, self.address, self.asset_id
+        )?;
+        if let Some(amount) = self.amount {
+            write!(fmt, "&amount={}", amount)?;
+        }
+        if let Some(ref label) = self.label {
+            write!(fmt, "&label={}", percent_encode(label))?;
+        }
+        if let Some(ref message) = self.message {
+            write!(fmt, "&message={}", percent_encode(message))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses payment URIs, which must have an `asset` parameter. Unknown query parameters are
+/// ignored unless they are prefixed with `req-`.
+impl FromStr for PaymentUri {
+    type Err = encode::Error;
+
+    fn from_str(s: &str) -> Result<PaymentUri, encode::Error> {
+        let (address, params) = split(s, PAYMENT_URI_SCHEME)?;
+        let address = Address::from_str(address)?;
+        let mut asset_id = None;
+        let mut amount = None;
+        let mut label = None;
+        let mut message = None;
+        for (key, value) in params {
+            match key {
+                "asset" => asset_id = Some(AssetId::from_str(value)?),
+                "amount" => amount = Some(parse_amount(value)?),
+                "label" => label = Some(percent_decode(value)?),
+                "message" => message = Some(percent_decode(value)?),
+                k => check_optional(k)?,
+            }
+        }
+        let asset_id =
+            asset_id.ok_or(encode::Error::ParseFailed("Missing URI asset parameter."))?;
+        Ok(PaymentUri {
+            address,
+            asset_id,
+            amount,
+            label,
+            message,
+        })
+    }
+}
+
+/// The key and value of each query parameter of a URI.
+pub(crate) type Params<'a> = Vec<(&'a str, &'a str)>;
+
+/// Splits a URI of `scheme`, matched case-insensitively, into its path and query parameters.
+pub(crate) fn split<'a>(s: &'a str, scheme: &str) -> Result<(&'a str, Params<'a>), encode::Error> {
+    let (actual, rest) = match s.find(':') {
+        Some(i) => (&s[..i], &s[i + 1..]),
+        None => return Err(encode::Error::ParseFailed("Missing URI scheme.")),
+    };
+    if !actual.eq_ignore_ascii_case(scheme) {
+        return Err(encode::Error::ParseFailed("Invalid URI scheme."));
+    }
+    let (path, query) = match rest.find('?') {
+        Some(i) => (&rest[..i], Some(&rest[i + 1..])),
+        None => (rest, None),
+    };
+    let params = query
+        .into_iter()
+        .flat_map(|q| q.split('&'))
+        .map(|param| match param.find('=') {
+            Some(i) => (&param[..i], &param[i + 1..]),
+            None => (param, ""),
+        })
+        .collect();
+    Ok((path, params))
+}
+
+/// Fails on unknown parameters which are required, i.e. prefixed with `req-`.
+pub(crate) fn check_optional(key: &str) -> Result<(), encode::Error> {
+    if key.starts_with("req-") {
+        return Err(encode::Error::ParseFailed(
+            "Unsupported required URI parameter.",
+        ));
+    }
+    Ok(())
+}
+
+pub(crate) fn parse_amount(value: &str) -> Result<u64, encode::Error> {
+    value
+        .parse()
+        .map_err(|_| encode::Error::ParseFailed("Invalid URI amount."))
+}
+
+pub(crate) fn percent_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for &b in s.as_bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", b));
+        }
+    }
+    encoded
+}
+
+pub(crate) fn percent_decode(s: &str) -> Result<String, encode::Error> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut iter = s.bytes();
+    while let Some(b) = iter.next() {
+        bytes.push(match b {
+            b'%' => {
+                let hi = iter.next().and_then(|d| (d as char).to_digit(16));
+                let lo = iter.next().and_then(|d| (d as char).to_digit(16));
+                match (hi, lo) {
+                    (Some(hi), Some(lo)) => (hi * 16 + lo) as u8,
+                    _ => return Err(encode::Error::ParseFailed("Invalid URI percent encoding.")),
+                }
+            }
+            b'+' => b' ',
+            b => b,
+        });
+    }
+    String::from_utf8(bytes)
+        .map_err(|_| encode::Error::ParseFailed("Invalid UTF-8 in URI parameter."))
+}
+
+#[cfg(test)]
+mod tests {
+    use openassets::address::Address;
+    use openassets::asset_id::AssetId;
+    use openassets::uri::PaymentUri;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_payment_uri() {
+        let address = Address::from_str("akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U6E").unwrap();
+        let asset_id = AssetId::from_str("ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC").unwrap();
+        let uri = PaymentUri::new(address.clone(), asset_id.clone());
+        assert_eq!(
+            "bitcoin:akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U6E?asset=ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC",
+            uri.to_string()
+        );
+        assert_eq!(uri, PaymentUri::from_str(&uri.to_string()).unwrap());
+
+        let uri = uri.amount(500).label("Coffee shop").message("Order #42");
+        let encoded = uri.to_string();
+        assert_eq!(
+            "bitcoin:akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U6E?asset=ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC&amount=500&label=Coffee%20shop&message=Order%20%2342",
+            encoded
+        );
+        assert_eq!(uri, PaymentUri::from_str(&encoded).unwrap());
+
+        // parameters in any order, unknown optional ones ignored
+        assert_eq!(
+            PaymentUri::new(address, asset_id).amount(500),
+            PaymentUri::from_str(
+                "BITCOIN:akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U6E?amount=500&foo=bar&asset=ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC"
+            )
+            .unwrap()
+        );
+
+        for invalid in &[
+            "bitcoin:akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U6E",
+            "bitcoin:akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U6E?amount=500",
+            "openassets:akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U6E?asset=ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC",
+            "bitcoin:1F2AQr6oqNtcJQ6p9SiCLQTrHuM9en44H8?asset=ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC",
+            "bitcoin:akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U6E?asset=akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U6E",
+            "bitcoin:akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U6E?asset=ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC&amount=0.5",
+            "bitcoin:akQz3f1v9JrnJAeGBC4pNzGNRdWXKan4U6E?asset=ALn3aK1fSuG27N96UGYB1kUYUpGKRhBuBC&req-foo=1",
+        ] {
+            assert!(PaymentUri::from_str(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use bitcoin::{OutPoint, Script, TxOut};
+use openassets::coloring::ColoredOutput;
+
+/// An unspent output along with its color.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct ColoredUtxo {
+    pub outpoint: OutPoint,
+    pub txout: TxOut,
+    pub colored: ColoredOutput,
+    /// The script wrapped by the script of `txout`, if it is P2SH.
+    pub redeem_script: Option<Script>,
+    /// The number of blocks confirming the transaction of the output, 0 if it is unconfirmed.
+    ///
+    /// Not part of the encoding of unsigned colored transactions.
+    pub confirmations: u32,
+}
+
+impl ColoredUtxo {
+    pub fn new(outpoint: OutPoint, txout: TxOut, colored: ColoredOutput) -> ColoredUtxo {
+        ColoredUtxo {
+            outpoint,
+            txout,
+            colored,
+            redeem_script: None,
+            confirmations: 0,
+        }
+    }
+
+    /// Sets the script wrapped by the P2SH script of the output.
+    pub fn with_redeem_script(mut self, redeem_script: Script) -> Self {
+        self.redeem_script = Some(redeem_script);
+        self
+    }
+
+    /// Sets the number of blocks confirming the output.
+    pub fn with_confirmations(mut self, confirmations: u32) -> Self {
+        self.confirmations = confirmations;
+        self
+    }
+}
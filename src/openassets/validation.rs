@@ -0,0 +1,192 @@
+use bitcoin::network::constants::Network;
+use bitcoin::{Script, Transaction};
+use openassets::asset_id::AssetId;
+use openassets::coloring::{
+    find_marker, ColoredOutput, ColoringEngine, ColoringPolicy, OutputKind,
+};
+use openassets::error::Error;
+use std::collections::HashMap;
+use std::error;
+use std::fmt::{self, Display, Formatter};
+
+/// Reasons for rejecting an Open Assets transfer.
+#[derive(Debug)]
+pub enum ValidationError {
+    /// The transaction does not follow the coloring rules.
+    Coloring(Error),
+    /// Transfer `output` claims `missing` more units than the inputs hold, i.e. it would issue
+    /// units outside of the issuance section.
+    UnbackedUnits { output: usize, missing: u64 },
+    /// More units of `asset_id` are transferred than the inputs hold.
+    Inflation {
+        asset_id: AssetId,
+        input_quantity: u64,
+        output_quantity: u64,
+    },
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            ValidationError::Coloring(ref e) => Display::fmt(e, f),
+            ValidationError::UnbackedUnits { output, missing } => write!(
+                f,
+                "output #{} transfers {} units not held by the inputs",
+                output, missing
+            ),
+            ValidationError::Inflation {
+                ref asset_id,
+                input_quantity,
+                output_quantity,
+            } => write!(
+                f,
+                "{} units of {} transferred from {} input units",
+                output_quantity, asset_id, input_quantity
+            ),
+        }
+    }
+}
+
+impl error::Error for ValidationError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            ValidationError::Coloring(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for ValidationError {
+    fn from(e: Error) -> Self {
+        ValidationError::Coloring(e)
+    }
+}
+
+/// Checks that `tx` does not transfer more units of any asset than its inputs hold.
+///
+/// `input_colors` holds the color of each input, in input order. Units issued by the outputs
+/// before the marker output are new units and are not checked against the inputs. Transactions
+/// without a marker output, including coinbase transactions, transfer nothing and are valid.
+pub fn validate_transfer(
+    tx: &Transaction,
+    input_colors: &[ColoredOutput],
+) -> Result<(), ValidationError> {
+    if tx.is_coin_base() {
+        return Ok(());
+    }
+    if input_colors.len() != tx.input.len() {
+        return Err(Error::InputCountMismatch {
+            expected: tx.input.len(),
+            actual: input_colors.len(),
+        }
+        .into());
+    }
+    let (marker_index, payload) = match find_marker(tx) {
+        Some(marker) => marker,
+        None => return Ok(()),
+    };
+
+    // the issuance asset id is irrelevant, only transfer outputs are checked
+    let engine = ColoringEngine::with_policy(Network::Bitcoin, ColoringPolicy::Strict);
+    let outputs = engine
+        .assign(
+            tx,
+            &Script::new(),
+            input_colors,
+            marker_index,
+            &payload,
+            None,
+        )
+        .map_err(|e| match e {
+            Error::InsufficientInputUnits { output, missing } => {
+                ValidationError::UnbackedUnits { output, missing }
+            }
+            e => ValidationError::Coloring(e),
+        })?;
+
+    let input_totals = totals(input_colors.iter())?;
+    let output_totals = totals(outputs.iter().filter(|o| o.kind == OutputKind::Transfer))?;
+    for (asset_id, &output_quantity) in output_totals.iter() {
+        let input_quantity = input_totals.get(asset_id).cloned().unwrap_or(0);
+        if output_quantity > input_quantity {
+            return Err(ValidationError::Inflation {
+                asset_id: (*asset_id).clone(),
+                input_quantity,
+                output_quantity,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Sums the quantities of `outputs` per asset.
+fn totals<'a, I: Iterator<Item = &'a ColoredOutput>>(
+    outputs: I,
+) -> Result<HashMap<&'a AssetId, u64>, Error> {
+    let mut totals = HashMap::new();
+    for output in outputs {
+        if let Some(ref asset_id) = output.asset_id {
+            let total = totals.entry(asset_id).or_insert(0u64);
+            *total = total
+                .checked_add(output.asset_quantity)
+                .ok_or(Error::QuantityOverflow)?;
+        }
+    }
+    Ok(totals)
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::network::constants::Network;
+    use openassets::asset_id::AssetId;
+    use openassets::coloring::{ColoredOutput, OutputKind};
+    use openassets::error::Error;
+    use openassets::test_helpers::{build_tx, script};
+    use openassets::validation::{validate_transfer, ValidationError};
+
+    #[test]
+    fn test_validate_transfer() {
+        let p2pkh = "76a91446c2fbfbecc99a63148fa076de58cf29b0bcf0b088ac";
+        let asset_a = AssetId::new(
+            &script("76a914010966776006953d5567439e5e39f86a0d273bee88ac"),
+            Network::Bitcoin,
+        );
+        let asset_b = AssetId::new(
+            &script("a914f9d499817e88ef7b10a88673296c6d6df2f4292d87"),
+            Network::Bitcoin,
+        );
+        let inputs = vec![
+            ColoredOutput::new(asset_a.clone(), 5, OutputKind::Transfer),
+            ColoredOutput::new(asset_b.clone(), 3, OutputKind::Transfer),
+        ];
+
+        // issue 100 units, transfer 5 of A and 3 of B
+        let tx = build_tx(2, vec![p2pkh, "6a094f4101000364050300", p2pkh, p2pkh]);
+        assert!(validate_transfer(&tx, &inputs).is_ok());
+
+        // transfer 5 of A and 4 of B
+        let tx = build_tx(2, vec!["6a084f41010002050400", p2pkh, p2pkh]);
+        match validate_transfer(&tx, &inputs) {
+            Err(ValidationError::UnbackedUnits { output, missing }) => {
+                assert_eq!(2, output);
+                assert_eq!(1, missing);
+            }
+            _ => panic!("expected UnbackedUnits"),
+        }
+
+        // transfer 6 units mixing A and B
+        let tx = build_tx(2, vec!["6a074f410100010600", p2pkh]);
+        match validate_transfer(&tx, &inputs) {
+            Err(ValidationError::Coloring(Error::MixedAssets { input, output })) => {
+                assert_eq!(1, input);
+                assert_eq!(1, output);
+            }
+            _ => panic!("expected MixedAssets"),
+        }
+
+        // no marker output
+        let tx = build_tx(2, vec![p2pkh]);
+        assert!(validate_transfer(&tx, &inputs).is_ok());
+        assert!(validate_transfer(&tx, &inputs[..1]).is_err());
+    }
+}
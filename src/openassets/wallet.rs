@@ -0,0 +1,274 @@
+use bitcoin::OutPoint;
+use openassets::asset_id::AssetId;
+use openassets::error::Error;
+use openassets::quantity::checked_sum;
+use openassets::utxo::ColoredUtxo;
+use std::collections::{BTreeMap, HashMap};
+
+/// Returns the quantity of each asset held in `utxos`, failing with `Error::QuantityOverflow`
+/// if one does not fit in a u64.
+pub fn balances<'a, I: IntoIterator<Item = &'a ColoredUtxo>>(
+    utxos: I,
+) -> Result<HashMap<AssetId, u64>, Error> {
+    let mut balances: HashMap<AssetId, u64> = HashMap::new();
+    for utxo in utxos {
+        if let Some(ref asset_id) = utxo.colored.asset_id {
+            let balance = balances.entry(asset_id.clone()).or_insert(0);
+            *balance = checked_sum(vec![*balance, utxo.colored.asset_quantity])?;
+        }
+    }
+    Ok(balances)
+}
+
+/// Returns the satoshis held in the uncolored outputs of `utxos`, i.e. spendable without
+/// moving assets, failing with `Error::QuantityOverflow` if they do not fit in a u64.
+pub fn uncolored_balance<'a, I: IntoIterator<Item = &'a ColoredUtxo>>(
+    utxos: I,
+) -> Result<u64, Error> {
+    checked_sum(
+        utxos
+            .into_iter()
+            .filter(|utxo| utxo.colored.asset_id.is_none())
+            .map(|utxo| utxo.txout.value),
+    )
+}
+
+/// Returns the outputs of `utxos` confirmed by at least `min_confirmations` blocks, e.g. to
+/// compute balances of confirmed outputs only.
+pub fn confirmed<'a, I: IntoIterator<Item = &'a ColoredUtxo>>(
+    utxos: I,
+    min_confirmations: u32,
+) -> impl Iterator<Item = &'a ColoredUtxo> {
+    utxos
+        .into_iter()
+        .filter(move |utxo| utxo.confirmations >= min_confirmations)
+}
+
+/// Storage for the colored outputs held by a wallet.
+pub trait ColoredWallet {
+    /// Adds `utxo`, e.g. an output received by the wallet, replacing the output of the same
+    /// outpoint.
+    fn add(&mut self, utxo: ColoredUtxo);
+
+    /// Removes the output of `outpoint`, e.g. once spent, returning it.
+    fn spend(&mut self, outpoint: &OutPoint) -> Option<ColoredUtxo>;
+
+    /// Returns the unspent outputs of the wallet.
+    fn list(&self) -> Vec<ColoredUtxo>;
+
+    /// Returns the quantity of `asset_id` held by the wallet, failing with
+    /// `Error::QuantityOverflow` if it does not fit in a u64.
+    fn balance(&self, asset_id: &AssetId) -> Result<u64, Error> {
+        checked_sum(
+            self.list()
+                .iter()
+                .filter(|utxo| utxo.colored.asset_id.as_ref() == Some(asset_id))
+                .map(|utxo| utxo.colored.asset_quantity),
+        )
+    }
+}
+
+/// A `ColoredWallet` keeping its outputs in memory, listed by outpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MemoryWallet {
+    utxos: BTreeMap<OutPoint, ColoredUtxo>,
+}
+
+impl MemoryWallet {
+    pub fn new() -> MemoryWallet {
+        MemoryWallet::default()
+    }
+
+    pub fn get(&self, outpoint: &OutPoint) -> Option<&ColoredUtxo> {
+        self.utxos.get(outpoint)
+    }
+
+    /// Returns the number of unspent outputs.
+    pub fn len(&self) -> usize {
+        self.utxos.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.utxos.is_empty()
+    }
+}
+
+impl ColoredWallet for MemoryWallet {
+    fn add(&mut self, utxo: ColoredUtxo) {
+        self.utxos.insert(utxo.outpoint, utxo);
+    }
+
+    fn spend(&mut self, outpoint: &OutPoint) -> Option<ColoredUtxo> {
+        self.utxos.remove(outpoint)
+    }
+
+    fn list(&self) -> Vec<ColoredUtxo> {
+        self.utxos.values().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::network::constants::Network;
+    use bitcoin::Script;
+    use hex::decode as hex_decode;
+    use openassets::asset_id::AssetId;
+    use openassets::coloring::{ColoredOutput, OutputKind};
+    use openassets::error::Error;
+    use openassets::test_helpers::utxo;
+    use openassets::wallet::{balances, confirmed, uncolored_balance, ColoredWallet, MemoryWallet};
+
+    fn asset_id(script: &str) -> AssetId {
+        AssetId::new(&Script::from(hex_decode(script).unwrap()), Network::Bitcoin)
+    }
+
+    #[test]
+    fn test_memory_wallet() {
+        let a = asset_id("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let b = asset_id("76a914fc779d4bab5b0b0fa6f2b5d1e3e5b8d3c54f8b4d88ac");
+        let mut wallet = MemoryWallet::new();
+        assert!(wallet.is_empty());
+        assert_eq!(0, wallet.balance(&a).unwrap());
+
+        wallet.add(utxo(
+            0,
+            600,
+            ColoredOutput::new(a.clone(), 10, OutputKind::Transfer),
+        ));
+        wallet.add(utxo(
+            1,
+            600,
+            ColoredOutput::new(b.clone(), 5, OutputKind::Issuance),
+        ));
+        wallet.add(utxo(
+            2,
+            600,
+            ColoredOutput::new(a.clone(), 20, OutputKind::Transfer),
+        ));
+        wallet.add(utxo(3, 600, ColoredOutput::uncolored()).with_confirmations(6));
+        assert_eq!(4, wallet.len());
+        assert_eq!(
+            vec![0, 1, 2, 3],
+            wallet
+                .list()
+                .iter()
+                .map(|utxo| utxo.outpoint.vout)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            6,
+            wallet
+                .get(&wallet.list()[3].outpoint)
+                .unwrap()
+                .confirmations
+        );
+        assert_eq!(30, wallet.balance(&a).unwrap());
+        assert_eq!(5, wallet.balance(&b).unwrap());
+
+        // the same asset on another network is another asset
+        let mut testnet = a.clone();
+        testnet.network = Network::Testnet;
+        assert_eq!(0, wallet.balance(&testnet).unwrap());
+
+        let spent = wallet.list()[0].outpoint;
+        assert_eq!(10, wallet.spend(&spent).unwrap().colored.asset_quantity);
+        assert_eq!(None, wallet.spend(&spent));
+        assert_eq!(20, wallet.balance(&a).unwrap());
+
+        // adding the same outpoint replaces the output
+        let replaced = utxo(
+            2,
+            600,
+            ColoredOutput::new(a.clone(), 7, OutputKind::Transfer),
+        );
+        wallet.add(replaced);
+        assert_eq!(3, wallet.len());
+        assert_eq!(7, wallet.balance(&a).unwrap());
+
+        wallet.add(utxo(
+            4,
+            600,
+            ColoredOutput::new(a.clone(), u64::MAX, OutputKind::Transfer),
+        ));
+        match wallet.balance(&a) {
+            Err(Error::QuantityOverflow) => {}
+            r => panic!("expected QuantityOverflow, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_balances() {
+        let a = asset_id("76a914010966776006953d5567439e5e39f86a0d273bee88ac");
+        let b = asset_id("76a914fc779d4bab5b0b0fa6f2b5d1e3e5b8d3c54f8b4d88ac");
+        let mut uncolored = utxo(4, 600, ColoredOutput::uncolored()).with_confirmations(1);
+        uncolored.txout.value = 10_000;
+        let utxos = vec![
+            utxo(
+                0,
+                600,
+                ColoredOutput::new(a.clone(), 10, OutputKind::Transfer),
+            )
+            .with_confirmations(3),
+            utxo(
+                1,
+                600,
+                ColoredOutput::new(b.clone(), 5, OutputKind::Issuance),
+            ),
+            utxo(
+                2,
+                600,
+                ColoredOutput::new(a.clone(), 20, OutputKind::Transfer),
+            )
+            .with_confirmations(1),
+            utxo(
+                3,
+                600,
+                ColoredOutput::new(b.clone(), 0, OutputKind::Transfer),
+            )
+            .with_confirmations(6),
+            uncolored,
+        ];
+
+        let all = balances(&utxos).unwrap();
+        assert_eq!(2, all.len());
+        assert_eq!(30, all[&a]);
+        assert_eq!(5, all[&b]);
+        assert_eq!(10_000, uncolored_balance(&utxos).unwrap());
+
+        let confirmed_balances = balances(confirmed(&utxos, 1)).unwrap();
+        assert_eq!(30, confirmed_balances[&a]);
+        assert_eq!(0, confirmed_balances[&b]);
+        assert_eq!(10_000, uncolored_balance(confirmed(&utxos, 1)).unwrap());
+        let deep = balances(confirmed(&utxos, 3)).unwrap();
+        assert_eq!(10, deep[&a]);
+        assert_eq!(0, deep[&b]);
+        assert_eq!(0, uncolored_balance(confirmed(&utxos, 3)).unwrap());
+        assert!(balances(confirmed(&utxos, 7)).unwrap().is_empty());
+
+        let wallet_balances = {
+            let mut wallet = MemoryWallet::new();
+            for utxo in &utxos {
+                wallet.add(utxo.clone());
+            }
+            balances(&wallet.list()).unwrap()
+        };
+        assert_eq!(all, wallet_balances);
+
+        let overflow = vec![
+            utxo(
+                0,
+                600,
+                ColoredOutput::new(a.clone(), u64::MAX, OutputKind::Transfer),
+            ),
+            utxo(
+                1,
+                600,
+                ColoredOutput::new(a.clone(), 1, OutputKind::Transfer),
+            ),
+        ];
+        match balances(&overflow) {
+            Err(Error::QuantityOverflow) => {}
+            r => panic!("expected QuantityOverflow, got {:?}", r),
+        }
+    }
+}